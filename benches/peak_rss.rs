@@ -0,0 +1,133 @@
+//! Compares the peak memory usage of reading the metadata of a large model with `fs::read` and
+//! with [`ModelFile`] (which memory-maps the model).
+//!
+//! Run with `cargo bench --bench peak_rss`. The size of the synthetic model in MiB can be set with
+//! the `PEAK_RSS_MODEL_MIB` environment variable (default: 1024). Peak RSS is read from
+//! `/proc/self/status`, so this only works on Linux.
+
+use std::{env, fs, path::Path, process::Command, time::Instant};
+
+use flatbuffers::FlatBufferBuilder;
+use tflite_metadump::{v3c::tflite as fb, ModelFile, ModelInfo, METADATA_NAME};
+
+/// Environment variable that makes the benchmark run a single measurement in a child process.
+const CHILD_ENV: &str = "PEAK_RSS_CHILD";
+
+fn main() {
+    if let Ok(mode) = env::var(CHILD_ENV) {
+        let path = env::args_os().last().unwrap();
+        return measure(&mode, Path::new(&path));
+    }
+
+    let mib = env::var("PEAK_RSS_MODEL_MIB").map_or(1024, |s| s.parse().unwrap());
+    let path = env::temp_dir().join(format!("peak_rss_{}.tflite", std::process::id()));
+    fs::write(&path, synthetic_model(mib << 20)).unwrap();
+    println!("synthetic model: {mib} MiB");
+
+    for mode in ["fs::read", "ModelFile"] {
+        // Peak RSS only ever grows, so each measurement needs a fresh process.
+        let output = Command::new(env::current_exe().unwrap())
+            .env(CHILD_ENV, mode)
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        print!("{}", String::from_utf8_lossy(&output.stdout));
+    }
+
+    fs::remove_file(&path).unwrap();
+}
+
+fn measure(mode: &str, path: &Path) {
+    let start = Instant::now();
+    let meta = match mode {
+        "fs::read" => ModelInfo::from_bytes(&fs::read(path).unwrap()),
+        _ => ModelInfo::from_bytes(&ModelFile::open(path).unwrap()),
+    };
+    let elapsed = start.elapsed();
+    assert_eq!(meta.unwrap().name.as_deref(), Some("synthetic"));
+    println!(
+        "{mode:>10}: peak RSS {:>7.1} MiB, {:>8.3} ms",
+        peak_rss_kib() as f64 / 1024.0,
+        elapsed.as_secs_f64() * 1000.0,
+    );
+}
+
+/// Returns the peak resident set size of the current process.
+fn peak_rss_kib() -> u64 {
+    let status = fs::read_to_string("/proc/self/status").unwrap();
+    let line = status.lines().find(|l| l.starts_with("VmHWM:")).unwrap();
+    line.split_whitespace().nth(1).unwrap().parse().unwrap()
+}
+
+/// Builds a model with a single constant tensor of `size` bytes and a small metadata buffer.
+fn synthetic_model(size: usize) -> Vec<u8> {
+    let meta = ModelInfo {
+        name: Some("synthetic".into()),
+        ..Default::default()
+    }
+    .to_flatbuffer();
+
+    let mut fbb = FlatBufferBuilder::with_capacity(size + meta.len() + 1024);
+    let empty = fb::Buffer::create(&mut fbb, &Default::default());
+    let weights = fbb.create_vector(&vec![1u8; size]);
+    let weights = fb::Buffer::create(
+        &mut fbb,
+        &fb::BufferArgs {
+            data: Some(weights),
+            ..Default::default()
+        },
+    );
+    let meta = fbb.create_vector(&meta);
+    let meta = fb::Buffer::create(
+        &mut fbb,
+        &fb::BufferArgs {
+            data: Some(meta),
+            ..Default::default()
+        },
+    );
+    let buffers = fbb.create_vector(&[empty, weights, meta]);
+
+    let shape = fbb.create_vector(&[size as i32]);
+    let tensor = fb::Tensor::create(
+        &mut fbb,
+        &fb::TensorArgs {
+            shape: Some(shape),
+            type_: fb::TensorType::UINT8,
+            buffer: 1,
+            ..Default::default()
+        },
+    );
+    let tensors = fbb.create_vector(&[tensor]);
+    let subgraph = fb::SubGraph::create(
+        &mut fbb,
+        &fb::SubGraphArgs {
+            tensors: Some(tensors),
+            ..Default::default()
+        },
+    );
+    let subgraphs = fbb.create_vector(&[subgraph]);
+
+    let name = fbb.create_string(METADATA_NAME);
+    let metadata = fb::Metadata::create(
+        &mut fbb,
+        &fb::MetadataArgs {
+            name: Some(name),
+            buffer: 2,
+        },
+    );
+    let metadata = fbb.create_vector(&[metadata]);
+
+    let model = fb::Model::create(
+        &mut fbb,
+        &fb::ModelArgs {
+            version: 3,
+            subgraphs: Some(subgraphs),
+            buffers: Some(buffers),
+            metadata: Some(metadata),
+            ..Default::default()
+        },
+    );
+    fbb.finish(model, Some("TFL3"));
+    fbb.finished_data().to_vec()
+}
@@ -0,0 +1,785 @@
+//! Generation of SSD anchors like MediaPipe's `SsdAnchorsCalculator`, and recovery of the
+//! generator parameters from a [`FixedAnchorsSchema`][crate::FixedAnchorsSchema].
+
+use anyhow::{bail, ensure, Context};
+use serde::Serialize;
+
+use crate::FixedAnchor;
+
+/// The parameters of MediaPipe's `SsdAnchorsCalculatorOptions`.
+///
+/// Each layer `i` has a grid of `ceil(input_size / strides[i])` cells, and each cell has one
+/// anchor per aspect ratio (plus one with the interpolated scale), centered on the cell. Layers
+/// with the same stride share a grid, so their anchors are interleaved per cell.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SsdAnchorGenerator {
+    pub input_size_width: u32,
+    pub input_size_height: u32,
+    /// Scale of the first layer, relative to the input size.
+    pub min_scale: f32,
+    /// Scale of the last layer; the scales of the layers in between are interpolated linearly.
+    pub max_scale: f32,
+    /// Position of the anchor centers within their cell.
+    pub anchor_offset_x: f32,
+    pub anchor_offset_y: f32,
+    /// Stride of each layer; `num_layers` is the length of this list.
+    pub strides: Vec<u32>,
+    pub aspect_ratios: Vec<f32>,
+    /// Whether all anchors have width and height 1, so that the box tensor predicts absolute sizes.
+    pub fixed_anchor_size: bool,
+    /// Whether the first layer only has 3 anchors per cell: aspect ratio 1 with scale 0.1, and
+    /// aspect ratios 2 and 0.5 with the layer's scale.
+    pub reduce_boxes_in_lowest_layer: bool,
+    /// Aspect ratio of an extra anchor per cell whose scale is the geometric mean of the scales of
+    /// the layer and the next one (or 1 for the last layer); 0 disables it.
+    pub interpolated_scale_aspect_ratio: f32,
+}
+
+impl Default for SsdAnchorGenerator {
+    /// Returns the defaults of `SsdAnchorsCalculatorOptions`.
+    fn default() -> Self {
+        Self {
+            input_size_width: 0,
+            input_size_height: 0,
+            min_scale: 0.0,
+            max_scale: 0.0,
+            anchor_offset_x: 0.5,
+            anchor_offset_y: 0.5,
+            strides: Vec::new(),
+            aspect_ratios: Vec::new(),
+            fixed_anchor_size: false,
+            reduce_boxes_in_lowest_layer: false,
+            interpolated_scale_aspect_ratio: 1.0,
+        }
+    }
+}
+
+impl SsdAnchorGenerator {
+    /// Generates the anchors, in the order used by MediaPipe: by layer (merging layers with the
+    /// same stride), then by row, column and anchor within the cell.
+    pub fn generate(&self) -> anyhow::Result<Vec<FixedAnchor>> {
+        ensure!(
+            self.input_size_width > 0 && self.input_size_height > 0,
+            "input size must be positive, but is {}x{}",
+            self.input_size_width,
+            self.input_size_height,
+        );
+        ensure!(!self.strides.is_empty(), "at least one stride is needed");
+        if let Some(stride) = self.strides.iter().find(|&&s| s == 0) {
+            bail!("strides must be positive, but one is {stride}");
+        }
+
+        let num_layers = self.strides.len();
+        let mut anchors = Vec::new();
+        let mut layer = 0;
+        while layer < num_layers {
+            let mut scales = Vec::new();
+            let mut aspect_ratios = Vec::new();
+            let mut last_same_stride_layer = layer;
+            while last_same_stride_layer < num_layers
+                && self.strides[last_same_stride_layer] == self.strides[layer]
+            {
+                let scale = self.scale(last_same_stride_layer);
+                if last_same_stride_layer == 0 && self.reduce_boxes_in_lowest_layer {
+                    scales.extend([0.1, scale, scale]);
+                    aspect_ratios.extend([1.0, 2.0, 0.5]);
+                } else {
+                    for &aspect_ratio in &self.aspect_ratios {
+                        scales.push(scale);
+                        aspect_ratios.push(aspect_ratio);
+                    }
+                    if self.interpolated_scale_aspect_ratio > 0.0 {
+                        let next = if last_same_stride_layer == num_layers - 1 {
+                            1.0
+                        } else {
+                            self.scale(last_same_stride_layer + 1)
+                        };
+                        scales.push((scale * next).sqrt());
+                        aspect_ratios.push(self.interpolated_scale_aspect_ratio);
+                    }
+                }
+                last_same_stride_layer += 1;
+            }
+
+            let stride = self.strides[layer];
+            let grid_height = self.input_size_height.div_ceil(stride);
+            let grid_width = self.input_size_width.div_ceil(stride);
+            for y in 0..grid_height {
+                for x in 0..grid_width {
+                    for (&scale, &aspect_ratio) in scales.iter().zip(&aspect_ratios) {
+                        let (width, height) = if self.fixed_anchor_size {
+                            (1.0, 1.0)
+                        } else {
+                            let ratio = aspect_ratio.sqrt();
+                            (scale * ratio, scale / ratio)
+                        };
+                        anchors.push(FixedAnchor {
+                            x_center: (x as f32 + self.anchor_offset_x) / grid_width as f32,
+                            y_center: (y as f32 + self.anchor_offset_y) / grid_height as f32,
+                            width,
+                            height,
+                        });
+                    }
+                }
+            }
+            layer = last_same_stride_layer;
+        }
+        Ok(anchors)
+    }
+
+    /// Returns the scale of `layer`.
+    fn scale(&self, layer: usize) -> f32 {
+        let num_layers = self.strides.len();
+        if num_layers == 1 {
+            (self.min_scale + self.max_scale) * 0.5
+        } else {
+            self.min_scale
+                + (self.max_scale - self.min_scale) * layer as f32 / (num_layers - 1) as f32
+        }
+    }
+}
+
+/// Result of [`fit_anchors`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnchorFit {
+    /// The parameters that reproduce the anchors or, if none do, those that reproduce the longest
+    /// prefix of them.
+    pub generator: SsdAnchorGenerator,
+    /// The first anchor that the generator doesn't reproduce, if any.
+    pub divergence: Option<AnchorDivergence>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnchorDivergence {
+    pub index: usize,
+    /// The anchor of the model, or [`None`] if the generator produces more anchors.
+    pub actual: Option<FixedAnchor>,
+    /// The generated anchor, or [`None`] if the generator produces fewer anchors.
+    pub generated: Option<FixedAnchor>,
+}
+
+/// A grid of anchors sharing a stride, as found in the anchor list.
+struct Grid {
+    width: u32,
+    height: u32,
+    /// The anchors of the first cell.
+    cell: Vec<FixedAnchor>,
+}
+
+/// Searches for [`SsdAnchorGenerator`] parameters that reproduce `anchors` for an input of
+/// `input_width` x `input_height`, comparing coordinates with an absolute `tolerance`.
+///
+/// The grids of the layers, and the number of anchors per cell, are read from the anchor centers.
+/// The scales and aspect ratios are then derived from the anchor sizes for each way of splitting
+/// the anchors of a cell into layers. Fails if the anchors don't form grids at all.
+///
+/// Scales and aspect ratios don't affect anchors with a fixed size, so for those only the number
+/// of anchors per layer is meaningful; aspect ratios of 1 are reported.
+pub fn fit_anchors(
+    anchors: &[FixedAnchor],
+    input_width: u32,
+    input_height: u32,
+    tolerance: f32,
+) -> anyhow::Result<AnchorFit> {
+    ensure!(!anchors.is_empty(), "no anchors to fit");
+    ensure!(
+        input_width > 0 && input_height > 0,
+        "input size must be positive, but is {input_width}x{input_height}",
+    );
+    let grids = grids(anchors, tolerance)?;
+    let first = &grids[0].cell[0];
+    let fixed_anchor_size = anchors.iter().all(|a| a.width == 1.0 && a.height == 1.0);
+
+    let mut strides = Vec::new();
+    for (i, grid) in grids.iter().enumerate() {
+        let Some(stride) = stride(input_width, input_height, grid) else {
+            bail!(
+                "no stride yields the {}x{} grid of layer group {i} for an input of \
+                 {input_width}x{input_height}",
+                grid.width,
+                grid.height,
+            );
+        };
+        strides.push(stride);
+    }
+
+    let base = SsdAnchorGenerator {
+        input_size_width: input_width,
+        input_size_height: input_height,
+        anchor_offset_x: snap(first.x_center * grids[0].width as f32),
+        anchor_offset_y: snap(first.y_center * grids[0].height as f32),
+        fixed_anchor_size,
+        ..SsdAnchorGenerator::default()
+    };
+
+    let mut best: Option<(usize, SsdAnchorGenerator)> = None;
+    for candidate in candidates(&grids, &strides, &base) {
+        let Ok(generated) = candidate.generate() else {
+            continue;
+        };
+        let matching = anchors
+            .iter()
+            .zip(&generated)
+            .take_while(|(a, b)| close(a, b, tolerance))
+            .count();
+        if matching == anchors.len() && generated.len() == anchors.len() {
+            return Ok(AnchorFit {
+                generator: candidate,
+                divergence: None,
+            });
+        }
+        if best.as_ref().is_none_or(|(m, _)| matching > *m) {
+            best = Some((matching, candidate));
+        }
+    }
+
+    // The structure-only candidate always generates, so there is a best one.
+    let (index, generator) = best.expect("no anchor generator candidates");
+    let generated = generator.generate()?;
+    Ok(AnchorFit {
+        divergence: Some(AnchorDivergence {
+            index,
+            actual: anchors.get(index).copied(),
+            generated: generated.get(index).copied(),
+        }),
+        generator,
+    })
+}
+
+/// Splits the anchors into grids of cells with the same center.
+fn grids(anchors: &[FixedAnchor], tolerance: f32) -> anyhow::Result<Vec<Grid>> {
+    let same = |a: f32, b: f32| (a - b).abs() <= tolerance;
+    let mut grids = Vec::new();
+    let mut start = 0;
+    while start < anchors.len() {
+        let first = anchors[start];
+        let per_cell = anchors[start..]
+            .iter()
+            .take_while(|a| same(a.x_center, first.x_center) && same(a.y_center, first.y_center))
+            .count();
+        let centers = anchors[start..]
+            .chunks(per_cell)
+            .map(|cell| cell[0])
+            .collect::<Vec<_>>();
+
+        // Cells are ordered by row, then column. Each row starts below the previous one, in the
+        // first column; the grid of the next layer starts in a different column.
+        let width = centers
+            .iter()
+            .take_while(|c| same(c.y_center, first.y_center))
+            .count();
+        let mut height = 1;
+        while let Some(row) = centers.get(height * width..(height + 1) * width) {
+            let above = centers[(height - 1) * width];
+            if !same(row[0].x_center, first.x_center) || row[0].y_center <= above.y_center {
+                break;
+            }
+            height += 1;
+        }
+        let end = start + width * height * per_cell;
+        if end > anchors.len() {
+            bail!(
+                "anchors {start}.. don't form a grid: {width}x{height} cells of {per_cell} \
+                 anchors need {} anchors, but there are only {}",
+                end - start,
+                anchors.len() - start,
+            );
+        }
+        grids.push(Grid {
+            width: width as u32,
+            height: height as u32,
+            cell: anchors[start..start + per_cell].to_vec(),
+        });
+        start = end;
+    }
+    Ok(grids)
+}
+
+/// Returns a stride that yields `grid` for the input size, preferring powers of two.
+fn stride(input_width: u32, input_height: u32, grid: &Grid) -> Option<u32> {
+    let fits =
+        |s: u32| input_width.div_ceil(s) == grid.width && input_height.div_ceil(s) == grid.height;
+    let max = input_width.max(input_height).next_power_of_two();
+    (0..=max.trailing_zeros())
+        .map(|i| 1 << i)
+        .find(|&s| fits(s))
+        .or_else(|| (1..=max).find(|&s| fits(s)))
+}
+
+/// Returns the generators to try, most conventional first: all ways of splitting the anchors of
+/// each cell into layers with the same number of anchors, with and without an interpolated
+/// anchor per layer and a reduced lowest layer.
+fn candidates(
+    grids: &[Grid],
+    strides: &[u32],
+    base: &SsdAnchorGenerator,
+) -> Vec<SsdAnchorGenerator> {
+    let mut candidates = Vec::new();
+    for reduce in [false, true] {
+        let mut counts = grids.iter().map(|g| g.cell.len()).collect::<Vec<_>>();
+        if reduce {
+            if counts[0] < 3 {
+                continue;
+            }
+            counts[0] -= 3;
+        }
+        // The number of anchors per layer must divide the number of anchors per cell of each
+        // grid (0 if the only layer is the reduced one).
+        let gcd = counts.iter().fold(0, |a, &b| gcd(a, b));
+        for n in (1..=gcd.max(1)).rev().filter(|n| gcd % n == 0) {
+            for interpolated in [true, false] {
+                if !interpolated || n >= 2 {
+                    candidates.push(derive(grids, strides, base, reduce, n, interpolated));
+                }
+            }
+        }
+    }
+    candidates
+}
+
+/// Derives the parameters of a generator with `n` anchors per layer (including the interpolated
+/// one), except for a reduced lowest layer.
+fn derive(
+    grids: &[Grid],
+    strides: &[u32],
+    base: &SsdAnchorGenerator,
+    reduce: bool,
+    n: usize,
+    interpolated: bool,
+) -> SsdAnchorGenerator {
+    // (stride, anchors) of each layer.
+    let mut layers = Vec::new();
+    for (i, (grid, &stride)) in grids.iter().zip(strides).enumerate() {
+        let mut cell = &grid.cell[..];
+        if i == 0 && reduce {
+            layers.push((stride, &cell[..3]));
+            cell = &cell[3..];
+        }
+        layers.extend(cell.chunks(n).map(|chunk| (stride, chunk)));
+    }
+
+    let scale = |a: &FixedAnchor| snap((a.width * a.height).sqrt());
+    let aspect_ratio = |a: &FixedAnchor| snap(a.width / a.height);
+    let regular = layers.iter().skip(reduce as usize).map(|(_, l)| *l).next();
+    let (aspect_ratios, interpolated_scale_aspect_ratio) = match regular {
+        Some(layer) if interpolated => (
+            layer[..n - 1].iter().map(aspect_ratio).collect(),
+            aspect_ratio(&layer[n - 1]),
+        ),
+        Some(layer) => (layer.iter().map(aspect_ratio).collect(), 0.0),
+        None => (vec![1.0], 0.0),
+    };
+    // The first anchor of a reduced layer has scale 0.1, the others the layer's scale.
+    let scales = layers
+        .iter()
+        .enumerate()
+        .map(|(i, (_, layer))| scale(&layer[(i == 0 && reduce) as usize]))
+        .collect::<Vec<_>>();
+
+    SsdAnchorGenerator {
+        min_scale: scales[0],
+        max_scale: scales[scales.len() - 1],
+        strides: layers.iter().map(|(stride, _)| *stride).collect(),
+        aspect_ratios,
+        reduce_boxes_in_lowest_layer: reduce,
+        interpolated_scale_aspect_ratio,
+        ..base.clone()
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Rounds values that are within float error of 4 decimal places, so that parameters like `2.0`
+/// aren't reported as `2.0000002`.
+fn snap(value: f32) -> f32 {
+    let rounded = (value * 1e4).round() / 1e4;
+    if (value - rounded).abs() <= 1e-6 * value.abs().max(1.0) {
+        rounded
+    } else {
+        value
+    }
+}
+
+fn close(a: &FixedAnchor, b: &FixedAnchor, tolerance: f32) -> bool {
+    (a.x_center - b.x_center).abs() <= tolerance
+        && (a.y_center - b.y_center).abs() <= tolerance
+        && (a.width - b.width).abs() <= tolerance
+        && (a.height - b.height).abs() <= tolerance
+}
+
+/// Column names of the CSV format of [`anchors_to_csv`].
+const CSV_HEADER: &str = "x_center,y_center,width,height";
+
+/// Magic string at the start of `.npy` files.
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Formats anchors as CSV with a header line and one `x_center,y_center,width,height` line per
+/// anchor.
+pub fn anchors_to_csv(anchors: &[FixedAnchor]) -> String {
+    let mut csv = format!("{CSV_HEADER}\n");
+    for a in anchors {
+        csv += &format!("{},{},{},{}\n", a.x_center, a.y_center, a.width, a.height);
+    }
+    csv
+}
+
+/// Parses anchors in the CSV format of [`anchors_to_csv`]; the header line is optional.
+pub fn anchors_from_csv(csv: &str) -> anyhow::Result<Vec<FixedAnchor>> {
+    let mut anchors = Vec::new();
+    for (i, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (i == 0 && line.replace(' ', "") == CSV_HEADER) {
+            continue;
+        }
+        let values = line
+            .split(',')
+            .map(|v| v.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("invalid anchor on line {}", i + 1))?;
+        let [x_center, y_center, width, height] = values[..] else {
+            bail!(
+                "expected 4 values on line {}, but there are {}",
+                i + 1,
+                values.len(),
+            );
+        };
+        anchors.push(FixedAnchor {
+            x_center,
+            y_center,
+            width,
+            height,
+        });
+    }
+    Ok(anchors)
+}
+
+/// Serializes anchors as a NumPy `.npy` file holding a float32 array of shape `[N, 4]`.
+pub fn anchors_to_npy(anchors: &[FixedAnchor]) -> Vec<u8> {
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, 4), }}",
+        anchors.len(),
+    );
+    // The header is padded with spaces and terminated by a newline, so that the data is aligned
+    // to 64 bytes.
+    let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+    header += &" ".repeat(unpadded.next_multiple_of(64) - unpadded);
+    header.push('\n');
+
+    let mut npy = NPY_MAGIC.to_vec();
+    npy.extend([1, 0]);
+    npy.extend((header.len() as u16).to_le_bytes());
+    npy.extend(header.as_bytes());
+    for a in anchors {
+        for v in [a.x_center, a.y_center, a.width, a.height] {
+            npy.extend(v.to_le_bytes());
+        }
+    }
+    npy
+}
+
+/// Parses a NumPy `.npy` file holding an array of shape `[N, 4]`.
+///
+/// Both float32 and float64 arrays are accepted, since NumPy creates the latter by default.
+pub fn anchors_from_npy(npy: &[u8]) -> anyhow::Result<Vec<FixedAnchor>> {
+    let Some(rest) = npy.strip_prefix(NPY_MAGIC) else {
+        bail!("not a .npy file");
+    };
+    let (header_len, rest) = match rest {
+        [1, _, a, b, rest @ ..] => (u16::from_le_bytes([*a, *b]) as usize, rest),
+        [2 | 3, _, a, b, c, d, rest @ ..] => (u32::from_le_bytes([*a, *b, *c, *d]) as usize, rest),
+        [major, ..] => bail!("unsupported .npy version {major}"),
+        [] => bail!("truncated .npy header"),
+    };
+    ensure!(rest.len() >= header_len, "truncated .npy header");
+    let (header, data) = rest.split_at(header_len);
+    let header = std::str::from_utf8(header).context("invalid .npy header")?;
+
+    let descr = npy_field(header, "descr")
+        .map(|v| v.trim_matches(|c| c == '\'' || c == '"'))
+        .context("missing `descr` in .npy header")?;
+    let fortran_order = match npy_field(header, "fortran_order") {
+        Some("True") => true,
+        Some("False") => false,
+        _ => bail!("missing `fortran_order` in .npy header"),
+    };
+    let shape = npy_field(header, "shape")
+        .and_then(|v| v.strip_prefix('(')?.strip_suffix(')'))
+        .context("missing `shape` in .npy header")?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(str::parse::<usize>)
+        .collect::<Result<Vec<_>, _>>()
+        .context("invalid `shape` in .npy header")?;
+    let [n, 4] = shape[..] else {
+        bail!("expected an array of shape [N, 4], but it has shape {shape:?}");
+    };
+
+    let values = match descr {
+        "<f4" => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect::<Vec<_>>(),
+        "<f8" => data
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()) as f32)
+            .collect(),
+        _ => bail!("expected a little-endian float32 or float64 array, but the type is `{descr}`"),
+    };
+    let expected = n
+        .checked_mul(4)
+        .with_context(|| format!("array of {n} anchors is too large"))?;
+    ensure!(
+        values.len() == expected,
+        "expected {expected} values, but the file holds {}",
+        values.len(),
+    );
+    let at = |i: usize, j: usize| {
+        if fortran_order {
+            values[j * n + i]
+        } else {
+            values[i * 4 + j]
+        }
+    };
+    Ok((0..n)
+        .map(|i| FixedAnchor {
+            x_center: at(i, 0),
+            y_center: at(i, 1),
+            width: at(i, 2),
+            height: at(i, 3),
+        })
+        .collect())
+}
+
+/// Returns the value of `key` in the Python dict literal of a `.npy` header.
+fn npy_field<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{key}'"))? + key.len() + 2;
+    let value = header[start..].trim_start().strip_prefix(':')?.trim_start();
+    let end = if value.starts_with('(') {
+        value.find(')')? + 1
+    } else {
+        value.find([',', '}'])?
+    };
+    Some(value[..end].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f32 = 1e-4;
+
+    /// MediaPipe's short-range BlazeFace face detector (`face_detection_short_range.pbtxt`).
+    fn blazeface() -> SsdAnchorGenerator {
+        SsdAnchorGenerator {
+            input_size_width: 128,
+            input_size_height: 128,
+            min_scale: 0.1484375,
+            max_scale: 0.75,
+            strides: vec![8, 16, 16, 16],
+            aspect_ratios: vec![1.0],
+            fixed_anchor_size: true,
+            ..Default::default()
+        }
+    }
+
+    /// MediaPipe's MobileNet-SSD object detector (`object_detection_mobile_cpu.pbtxt`).
+    fn mobilenet_ssd() -> SsdAnchorGenerator {
+        SsdAnchorGenerator {
+            input_size_width: 320,
+            input_size_height: 320,
+            min_scale: 0.2,
+            max_scale: 0.95,
+            strides: vec![16, 32, 64, 128, 256, 512],
+            aspect_ratios: vec![1.0, 2.0, 0.5, 3.0, 0.3333],
+            reduce_boxes_in_lowest_layer: true,
+            ..Default::default()
+        }
+    }
+
+    /// Fits the anchors of `generator`, and checks that the recovered parameters reproduce them.
+    fn round_trip(generator: &SsdAnchorGenerator) -> SsdAnchorGenerator {
+        let anchors = generator.generate().unwrap();
+        let fit = fit_anchors(
+            &anchors,
+            generator.input_size_width,
+            generator.input_size_height,
+            TOLERANCE,
+        )
+        .unwrap();
+        assert_eq!(fit.divergence, None);
+        let generated = fit.generator.generate().unwrap();
+        assert_eq!(generated.len(), anchors.len());
+        for (i, (a, b)) in anchors.iter().zip(&generated).enumerate() {
+            assert!(close(a, b, TOLERANCE), "anchor {i}: {a:?} != {b:?}");
+        }
+        fit.generator
+    }
+
+    #[test]
+    fn blazeface_anchors() {
+        let anchors = blazeface().generate().unwrap();
+        // 16x16 cells with 2 anchors, and 8x8 cells with 6 anchors.
+        assert_eq!(anchors.len(), 896);
+        assert!(anchors.iter().all(|a| a.width == 1.0 && a.height == 1.0));
+        assert_eq!(anchors[0].x_center, 0.5 / 16.0);
+        assert_eq!(anchors[512].x_center, 0.5 / 8.0);
+    }
+
+    #[test]
+    fn fit_blazeface() {
+        let fit = round_trip(&blazeface());
+        assert!(fit.fixed_anchor_size);
+        assert!(!fit.reduce_boxes_in_lowest_layer);
+        // Fixed-size anchors only determine the number of anchors per layer.
+        let anchors_per_cell = |g: &SsdAnchorGenerator| {
+            g.generate()
+                .unwrap()
+                .iter()
+                .take_while(|a| a.x_center == 0.5 / 16.0 && a.y_center == 0.5 / 16.0)
+                .count()
+        };
+        assert_eq!(anchors_per_cell(&fit), 2);
+    }
+
+    #[test]
+    fn fit_blazeface_with_sizes() {
+        let generator = SsdAnchorGenerator {
+            fixed_anchor_size: false,
+            ..blazeface()
+        };
+        let fit = round_trip(&generator);
+        assert_eq!(fit.strides, generator.strides);
+        assert_eq!(fit.aspect_ratios, generator.aspect_ratios);
+        assert_eq!(fit.interpolated_scale_aspect_ratio, 1.0);
+        assert!((fit.min_scale - generator.min_scale).abs() < TOLERANCE);
+        assert!((fit.max_scale - generator.max_scale).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn fit_mobilenet_ssd() {
+        let generator = mobilenet_ssd();
+        let anchors = generator.generate().unwrap();
+        // 20x20 cells with 3 anchors, then 10x10, 5x5, 3x3, 2x2 and 1x1 cells with 6 anchors.
+        assert_eq!(anchors.len(), 2034);
+
+        let fit = round_trip(&generator);
+        assert!(fit.reduce_boxes_in_lowest_layer);
+        assert!(!fit.fixed_anchor_size);
+        assert_eq!(fit.strides, generator.strides);
+        assert_eq!(fit.aspect_ratios, generator.aspect_ratios);
+        assert!((fit.min_scale - generator.min_scale).abs() < TOLERANCE);
+        assert!((fit.max_scale - generator.max_scale).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn fit_reports_divergence() {
+        let mut anchors = mobilenet_ssd().generate().unwrap();
+        anchors[1000].width += 0.01;
+        let fit = fit_anchors(&anchors, 320, 320, TOLERANCE).unwrap();
+        let divergence = fit.divergence.unwrap();
+        assert_eq!(divergence.index, 1000);
+        assert_eq!(divergence.actual, Some(anchors[1000]));
+    }
+
+    fn anchors() -> Vec<FixedAnchor> {
+        vec![
+            FixedAnchor {
+                x_center: 0.25,
+                y_center: 0.5,
+                width: 1.0,
+                height: 0.125,
+            },
+            FixedAnchor {
+                x_center: 0.1,
+                y_center: 0.2,
+                width: 0.3,
+                height: 0.4,
+            },
+        ]
+    }
+
+    /// Builds a version 1 `.npy` file.
+    fn npy(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut npy = NPY_MAGIC.to_vec();
+        npy.extend([1, 0]);
+        npy.extend((header.len() as u16).to_le_bytes());
+        npy.extend(header.as_bytes());
+        npy.extend(data);
+        npy
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let csv = anchors_to_csv(&anchors());
+        assert!(csv.starts_with("x_center,y_center,width,height\n"));
+        assert_eq!(anchors_from_csv(&csv).unwrap(), anchors());
+        // The header is optional, and whitespace and empty lines are ignored.
+        let csv = "0.25, 0.5, 1, 0.125\r\n\n0.1,0.2,0.3,0.4\n";
+        assert_eq!(anchors_from_csv(csv).unwrap(), anchors());
+
+        assert!(anchors_from_csv("0.1,0.2,0.3\n").is_err());
+        let e = anchors_from_csv("x_center,y_center,width,height\n0.1,a,0.3,0.4").unwrap_err();
+        assert!(format!("{e:#}").contains("line 2"), "{e:#}");
+    }
+
+    #[test]
+    fn npy_round_trip() {
+        let npy = anchors_to_npy(&anchors());
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        assert_eq!(npy.len(), 10 + header_len + 2 * 4 * 4);
+        assert_eq!(anchors_from_npy(&npy).unwrap(), anchors());
+    }
+
+    #[test]
+    fn npy_float64_and_fortran_order() {
+        let anchors = anchors();
+        let rows = anchors
+            .iter()
+            .map(|a| [a.x_center, a.y_center, a.width, a.height])
+            .collect::<Vec<_>>();
+
+        let f8 = rows
+            .iter()
+            .flatten()
+            .flat_map(|&v| f64::from(v).to_le_bytes())
+            .collect::<Vec<_>>();
+        let header = "{'descr': '<f8', 'fortran_order': False, 'shape': (2, 4), }\n";
+        assert_eq!(anchors_from_npy(&npy(header, &f8)).unwrap(), anchors);
+
+        // Fortran order stores the array column by column.
+        let columns = (0..4)
+            .flat_map(|j| rows.iter().map(move |row| row[j]))
+            .flat_map(f32::to_le_bytes)
+            .collect::<Vec<_>>();
+        let header = "{'descr': '<f4', 'fortran_order': True, 'shape': (2, 4), }\n";
+        assert_eq!(anchors_from_npy(&npy(header, &columns)).unwrap(), anchors);
+    }
+
+    #[test]
+    fn npy_errors() {
+        let data = [0; 32];
+        let header = |descr: &str, shape: &str| {
+            format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}\n")
+        };
+        assert!(anchors_from_npy(b"not numpy").is_err());
+        assert!(anchors_from_npy(&npy(&header(">f4", "(2, 4)"), &data)).is_err());
+        assert!(anchors_from_npy(&npy(&header("<i4", "(2, 4)"), &data)).is_err());
+        assert!(anchors_from_npy(&npy(&header("<f4", "(4, 2)"), &data)).is_err());
+        assert!(anchors_from_npy(&npy(&header("<f4", "(8,)"), &data)).is_err());
+        assert!(anchors_from_npy(&npy(&header("<f4", "(3, 4)"), &data)).is_err());
+        // The value count would overflow.
+        let huge = format!("({}, 4)", usize::MAX / 2);
+        assert!(anchors_from_npy(&npy(&header("<f4", &huge), &data)).is_err());
+        let mut truncated = anchors_to_npy(&anchors());
+        truncated.truncate(20);
+        assert!(anchors_from_npy(&truncated).is_err());
+    }
+}
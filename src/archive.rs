@@ -0,0 +1,151 @@
+//! Access to the zip archive of associated files that the metadata writer appends to a model.
+
+use std::io::{Cursor, Read};
+
+use anyhow::Context;
+use zip::ZipArchive;
+
+use crate::ModelInfo;
+
+/// Signature of the zip "end of central directory" record.
+const EOCD_SIGNATURE: &[u8] = b"PK\x05\x06";
+/// Size of the fixed part of the EOCD record; it may be followed by a comment of up to 64 KiB.
+const EOCD_SIZE: usize = 22;
+
+/// The zip archive holding the [`AssociatedFile`][crate::AssociatedFile]s of a model.
+///
+/// The metadata writers of `tflite-support` and MediaPipe append this archive to the model's
+/// flatbuffer.
+pub struct Archive<'a> {
+    zip: ZipArchive<Cursor<&'a [u8]>>,
+    entries: Vec<ArchiveEntry>,
+    start: u64,
+}
+
+/// Information about a file stored in an [`Archive`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+    pub compressed_size: u64,
+    pub crc32: u32,
+}
+
+impl<'a> Archive<'a> {
+    /// Opens the archive appended to the contents of a `.tflite` file.
+    ///
+    /// Returns [`None`] if the model does not contain an archive. The EOCD signature can also occur
+    /// by chance in model data, so a model whose tail can't be read as a zip archive is treated
+    /// as having none.
+    pub fn from_bytes(tflite: &'a [u8]) -> anyhow::Result<Option<Self>> {
+        let tail = &tflite[tflite
+            .len()
+            .saturating_sub(EOCD_SIZE + usize::from(u16::MAX))..];
+        if !tail.windows(4).any(|w| w == EOCD_SIGNATURE) {
+            return Ok(None);
+        }
+
+        let Ok(mut zip) = ZipArchive::new(Cursor::new(tflite)) else {
+            return Ok(None);
+        };
+        let mut entries = Vec::with_capacity(zip.len());
+        let mut start = zip.central_directory_start();
+        for i in 0..zip.len() {
+            let file = zip.by_index(i)?;
+            start = start.min(file.header_start());
+            if file.is_dir() {
+                continue;
+            }
+            entries.push(ArchiveEntry {
+                name: file.name().to_string(),
+                size: file.size(),
+                compressed_size: file.compressed_size(),
+                crc32: file.crc32(),
+            });
+        }
+
+        let start = start + zip.offset();
+        Ok(Some(Self {
+            zip,
+            entries,
+            start,
+        }))
+    }
+
+    /// Returns the byte offset at which the archive starts within the model file.
+    pub fn offset(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the files stored in the archive.
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// Returns the [`ArchiveEntry`] with the given name.
+    pub fn entry(&self, name: &str) -> Option<&ArchiveEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// Reads and decompresses the file with the given name.
+    pub fn read(&mut self, name: &str) -> anyhow::Result<Vec<u8>> {
+        let mut file = self
+            .zip
+            .by_name(name)
+            .with_context(|| format!("associated file `{name}` not found in archive"))?;
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)
+            .with_context(|| format!("failed to read associated file `{name}`"))?;
+        Ok(data)
+    }
+
+    /// Compares the archive contents against the [`AssociatedFile`][crate::AssociatedFile]s
+    /// declared in `meta`.
+    pub fn check(&self, meta: &ModelInfo) -> ArchiveCheck {
+        let declared = meta
+            .all_associated_files()
+            .into_iter()
+            .filter_map(|file| file.name.as_deref())
+            .collect::<Vec<_>>();
+
+        let mut missing = Vec::new();
+        for name in &declared {
+            if self.entry(name).is_none() && !missing.iter().any(|m| m == name) {
+                missing.push(name.to_string());
+            }
+        }
+        let undeclared = self
+            .entries
+            .iter()
+            .filter(|entry| !declared.contains(&&*entry.name))
+            .map(|entry| entry.name.clone())
+            .collect();
+
+        ArchiveCheck {
+            missing,
+            undeclared,
+        }
+    }
+}
+
+/// Result of [`Archive::check`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveCheck {
+    /// Names of associated files that are declared in the metadata but not in the archive.
+    pub missing: Vec<String>,
+    /// Names of archive entries that are not declared in the metadata.
+    pub undeclared: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stray_eocd_signature_is_not_an_archive() {
+        let mut tflite = vec![0xff; 64];
+        tflite[40..44].copy_from_slice(EOCD_SIGNATURE);
+        assert!(Archive::from_bytes(&tflite).unwrap().is_none());
+    }
+}
@@ -0,0 +1,75 @@
+//! Access to buffer data and operator custom options.
+//!
+//! Models larger than 2 GB can't store all of their data in the flatbuffer, since flatbuffer
+//! offsets are 32 bits wide. The converter then appends the data to the file, and references it
+//! with `Buffer.offset`/`Buffer.size` and `Operator.large_custom_options_offset`/`_size` instead of
+//! the inline `data` and `custom_options` vectors.
+
+use anyhow::bail;
+
+use crate::v3c::tflite as fb;
+
+/// Returns the data of buffer `index` of `model`.
+///
+/// `tflite` is the content of the `.tflite` file that `model` was read from; it is needed to
+/// resolve buffers stored outside of the flatbuffer.
+pub fn buffer_data<'a>(
+    tflite: &'a [u8],
+    model: &fb::Model<'a>,
+    index: u32,
+) -> anyhow::Result<&'a [u8]> {
+    let Some(buffers) = model.buffers() else {
+        bail!("model contains no buffer list")
+    };
+    if index as usize >= buffers.len() {
+        bail!(
+            "buffer {index} does not exist, the model only has {} buffers",
+            buffers.len()
+        );
+    }
+
+    let buffer = buffers.get(index as usize);
+    if is_external(buffer.offset()) {
+        return external(tflite, buffer.offset(), buffer.size_(), || {
+            format!("buffer {index}")
+        });
+    }
+    Ok(buffer.data().map_or(&[][..], |v| v.bytes()))
+}
+
+/// Returns the custom options of `op`.
+///
+/// `tflite` is the content of the `.tflite` file that `op` was read from.
+pub fn custom_options<'a>(tflite: &'a [u8], op: &fb::Operator<'a>) -> anyhow::Result<&'a [u8]> {
+    let offset = op.large_custom_options_offset();
+    if is_external(offset) {
+        return external(tflite, offset, op.large_custom_options_size(), || {
+            "custom options".into()
+        });
+    }
+    Ok(op.custom_options().map_or(&[][..], |v| v.bytes()))
+}
+
+/// Returns whether an offset refers to data outside of the flatbuffer.
+///
+/// The converter uses 1 as a placeholder while it writes the model, so only larger values are
+/// actual offsets.
+pub(crate) fn is_external(offset: u64) -> bool {
+    offset > 1
+}
+
+fn external(
+    tflite: &[u8],
+    offset: u64,
+    size: u64,
+    what: impl FnOnce() -> String,
+) -> anyhow::Result<&[u8]> {
+    match offset.checked_add(size) {
+        Some(end) if end <= tflite.len() as u64 => Ok(&tflite[offset as usize..end as usize]),
+        _ => bail!(
+            "{} at offset {offset} with size {size} exceeds the file size of {} bytes",
+            what(),
+            tflite.len(),
+        ),
+    }
+}
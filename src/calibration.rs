@@ -0,0 +1,290 @@
+//! Score calibration as described by [`ScoreCalibrationOptions`], implemented like in the TFLite
+//! Task Library (`score_calibration.cc`).
+
+use anyhow::{bail, Context};
+
+use crate::{
+    Archive, AssociatedFileType, ProcessUnitOptions, ScoreCalibrationOptions,
+    ScoreTransformationType, TensorMetadata,
+};
+
+/// Used to prevent `log(<= 0.0)` when transforming scores.
+const LOG_SCORE_MINIMUM: f32 = 1e-16;
+
+/// Per-class sigmoid calibration of the scores of an output tensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreCalibration {
+    pub score_transformation: ScoreTransformationType,
+    /// Score of classes without sigmoid, and of scores below a sigmoid's
+    /// [`min_uncalibrated_score`][Sigmoid::min_uncalibrated_score].
+    pub default_score: f32,
+    /// Sigmoids by class index; [`None`] for classes with an empty line in the calibration file.
+    pub sigmoids: Vec<Option<Sigmoid>>,
+}
+
+/// Calibration parameters of a single class.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sigmoid {
+    pub scale: f32,
+    pub slope: f32,
+    pub offset: f32,
+    pub min_uncalibrated_score: Option<f32>,
+}
+
+impl ScoreCalibration {
+    /// Parses a `TENSOR_AXIS_SCORE_CALIBRATION` file.
+    ///
+    /// Each line holds the `scale,slope,offset[,min_uncalibrated_score]` parameters of the class
+    /// at the same index. Empty lines denote classes without calibration.
+    pub fn parse(options: &ScoreCalibrationOptions, file: &str) -> anyhow::Result<Self> {
+        if file.is_empty() {
+            bail!("expected non-empty score calibration file");
+        }
+        if !ScoreTransformationType::ENUM_VALUES.contains(&options.score_transformation) {
+            bail!(
+                "unsupported score transformation {:?}",
+                options.score_transformation
+            );
+        }
+
+        let sigmoids = file
+            .split('\n')
+            .enumerate()
+            .map(|(i, line)| {
+                let line = line.strip_suffix('\r').unwrap_or(line);
+                (!line.is_empty())
+                    .then(|| Sigmoid::parse(line))
+                    .transpose()
+                    .with_context(|| format!("invalid score calibration on line {}", i + 1))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            score_transformation: options.score_transformation,
+            default_score: options.default_score,
+            sigmoids,
+        })
+    }
+
+    /// Loads the calibration of an output tensor.
+    ///
+    /// Returns [`None`] if the tensor has no [`ScoreCalibrationOptions`] process unit. The
+    /// calibration file is read from the model's associated files `archive`.
+    pub fn from_tensor(
+        meta: &TensorMetadata,
+        archive: Option<&mut Archive<'_>>,
+    ) -> anyhow::Result<Option<Self>> {
+        let Some(options) = meta
+            .process_units
+            .iter()
+            .find_map(|unit| match &unit.options {
+                Some(ProcessUnitOptions::ScoreCalibrationOptions(options)) => Some(options),
+                _ => None,
+            })
+        else {
+            return Ok(None);
+        };
+
+        let Some(name) = meta
+            .associated_files
+            .iter()
+            .find(|file| file.type_ == AssociatedFileType::TENSOR_AXIS_SCORE_CALIBRATION)
+            .and_then(|file| file.name.as_deref())
+        else {
+            bail!("tensor has score calibration options, but no score calibration file");
+        };
+        let Some(archive) = archive else {
+            bail!("score calibration file `{name}` can't be loaded: model has no archive");
+        };
+
+        let file = String::from_utf8(archive.read(name)?)
+            .with_context(|| format!("score calibration file `{name}` is not UTF-8"))?;
+        Self::parse(options, &file)
+            .with_context(|| format!("failed to parse score calibration file `{name}`"))
+            .map(Some)
+    }
+
+    /// Returns the calibrated score of `class`.
+    pub fn calibrate(&self, class: usize, score: f32) -> f32 {
+        let sigmoid = match self.sigmoids.get(class) {
+            Some(Some(sigmoid)) => sigmoid,
+            _ => return self.default_score,
+        };
+        if sigmoid
+            .min_uncalibrated_score
+            .is_some_and(|min| score < min)
+        {
+            return self.default_score;
+        }
+
+        let transformed = transform(score, self.score_transformation);
+        let shifted = transformed * sigmoid.slope + sigmoid.offset;
+        // For numerical stability, use 1 / (1 + exp(-x)) for x >= 0 and exp(x) / (1 + exp(x))
+        // otherwise.
+        if shifted >= 0.0 {
+            (f64::from(sigmoid.scale) / (1.0 + (-f64::from(shifted)).exp())) as f32
+        } else {
+            let exp = f64::from(shifted).exp() as f32;
+            sigmoid.scale * exp / (1.0 + exp)
+        }
+    }
+}
+
+impl Sigmoid {
+    fn parse(line: &str) -> anyhow::Result<Self> {
+        let params = line
+            .split(',')
+            .map(|param| {
+                param
+                    .trim()
+                    .parse::<f32>()
+                    .with_context(|| format!("could not parse parameter `{param}` as float"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        match params[..] {
+            [scale, slope, offset] => Ok(Self {
+                scale,
+                slope,
+                offset,
+                min_uncalibrated_score: None,
+            }),
+            [scale, slope, offset, min] => Ok(Self {
+                scale,
+                slope,
+                offset,
+                min_uncalibrated_score: Some(min),
+            }),
+            _ => bail!("expected 3 or 4 parameters, got {}", params.len()),
+        }
+    }
+}
+
+fn transform(score: f32, transformation: ScoreTransformationType) -> f32 {
+    match transformation {
+        ScoreTransformationType::LOG => clamped_log(score, LOG_SCORE_MINIMUM),
+        ScoreTransformationType::INVERSE_LOGISTIC => {
+            clamped_log(score, LOG_SCORE_MINIMUM) - clamped_log(1.0 - score, LOG_SCORE_MINIMUM)
+        }
+        _ => score,
+    }
+}
+
+/// Returns `log(x)` for `x >= threshold`, and `2 * log(threshold) - log(2 * threshold - x)`
+/// below it.
+///
+/// This is anti-symmetric about the threshold and has a continuous value and first derivative,
+/// which avoids floating point errors for values close to 0 while preserving the order of
+/// smaller values.
+fn clamped_log(x: f32, threshold: f32) -> f32 {
+    let (x, threshold) = (f64::from(x), f64::from(threshold));
+    if x < threshold {
+        (2.0 * threshold.ln() - (2.0 * threshold - x).ln()) as f32
+    } else {
+        x.ln() as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(score_transformation: ScoreTransformationType) -> ScoreCalibrationOptions {
+        ScoreCalibrationOptions {
+            score_transformation,
+            default_score: 0.25,
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn parse_lines() {
+        let file = "1.0,2.0,-1.0\r\n\n0.5, 1, 0, 0.3\n";
+        let calibration =
+            ScoreCalibration::parse(&options(ScoreTransformationType::IDENTITY), file).unwrap();
+        assert_eq!(calibration.default_score, 0.25);
+        assert_eq!(
+            calibration.sigmoids,
+            [
+                Some(Sigmoid {
+                    scale: 1.0,
+                    slope: 2.0,
+                    offset: -1.0,
+                    min_uncalibrated_score: None,
+                }),
+                None,
+                Some(Sigmoid {
+                    scale: 0.5,
+                    slope: 1.0,
+                    offset: 0.0,
+                    min_uncalibrated_score: Some(0.3),
+                }),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let identity = options(ScoreTransformationType::IDENTITY);
+        assert!(ScoreCalibration::parse(&identity, "").is_err());
+        assert!(ScoreCalibration::parse(&identity, "1,2").is_err());
+        assert!(ScoreCalibration::parse(&identity, "1,2,3,4,5").is_err());
+        let e = ScoreCalibration::parse(&identity, "1,2,3\n1,x,3").unwrap_err();
+        assert!(format!("{e:#}").contains("line 2"), "{e:#}");
+        let unknown = options(ScoreTransformationType(7));
+        assert!(ScoreCalibration::parse(&unknown, "1,2,3").is_err());
+    }
+
+    #[test]
+    fn identity() {
+        let options = options(ScoreTransformationType::IDENTITY);
+        let calibration = ScoreCalibration::parse(&options, "1,2,-1").unwrap();
+        assert_close(calibration.calibrate(0, 0.5), 0.5);
+        // 1 / (1 + exp(-0.5))
+        assert_close(calibration.calibrate(0, 0.75), 0.622_459_3);
+        // exp(-1) / (1 + exp(-1)), computed on the negative branch.
+        assert_close(calibration.calibrate(0, 0.0), 0.268_941_43);
+    }
+
+    #[test]
+    fn log() {
+        let options = options(ScoreTransformationType::LOG);
+        let calibration = ScoreCalibration::parse(&options, "0.9,1,0").unwrap();
+        // 0.9 * exp(log(0.5)) / (1 + exp(log(0.5))) = 0.9 / 3
+        assert_close(calibration.calibrate(0, 0.5), 0.3);
+        // The clamped log keeps scores of 0 finite, and below those just above 0.
+        let zero = calibration.calibrate(0, 0.0);
+        assert!(zero.is_finite() && zero <= calibration.calibrate(0, 1e-10));
+    }
+
+    #[test]
+    fn inverse_logistic() {
+        let options = options(ScoreTransformationType::INVERSE_LOGISTIC);
+        // The sigmoid of the logit of a score is the score itself.
+        let calibration = ScoreCalibration::parse(&options, "1,1,0").unwrap();
+        for score in [0.1, 0.5, 0.8] {
+            assert_close(calibration.calibrate(0, score), score);
+        }
+        assert!(calibration.calibrate(0, 1.0).is_finite());
+    }
+
+    #[test]
+    fn default_score() {
+        let options = options(ScoreTransformationType::IDENTITY);
+        let calibration = ScoreCalibration::parse(&options, "1,1,0,0.3\n\n1,1,0").unwrap();
+        // Below `min_uncalibrated_score`.
+        assert_eq!(calibration.calibrate(0, 0.2), 0.25);
+        assert_ne!(calibration.calibrate(0, 0.4), 0.25);
+        // Empty line: no calibration for the class.
+        assert_eq!(calibration.calibrate(1, 0.9), 0.25);
+        assert_ne!(calibration.calibrate(2, 0.9), 0.25);
+        // Class without a line.
+        assert_eq!(calibration.calibrate(3, 0.9), 0.25);
+    }
+}
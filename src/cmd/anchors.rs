@@ -0,0 +1,236 @@
+use std::{ffi::OsString, fs, io, path::Path};
+
+use anyhow::{anyhow, bail, Context};
+use tflite_metadump::{
+    FixedAnchor, ModelFile, ModelGraph, ModelInfo, ObjectDetectorOptions, SsdAnchorGenerator,
+};
+
+use super::{Arg, Args, Format};
+
+const USAGE: &str = "\
+usage: tflite-metadump anchors fit [--format text|json] [--subgraph <n>] [--input-size <w>x<h>]
+                                   [--tolerance <t>] <model.tflite>
+       tflite-metadump anchors export [--subgraph <n>] [-o <anchors.csv|npy>] <model.tflite>
+       tflite-metadump anchors import [--subgraph <n>] <model.tflite> <anchors.csv|npy> -o <out.tflite>";
+
+pub fn run(mut args: Vec<OsString>) -> anyhow::Result<()> {
+    match args.first().and_then(|arg| arg.to_str()) {
+        Some("fit") => fit(args.split_off(1)),
+        Some("export") => export(args.split_off(1)),
+        Some("import") => import(args.split_off(1)),
+        _ => Err(Args::new(USAGE, args).usage()),
+    }
+}
+
+fn fit(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut subgraph = 0;
+    let mut input_size = None;
+    let mut tolerance = 1e-5;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => format = Format::parse(&args.value_str()?)?,
+            Arg::Option(opt) if opt == "--subgraph" => subgraph = args.value_str()?.parse()?,
+            Arg::Option(opt) if opt == "--input-size" => {
+                let size = args.value_str()?;
+                let parsed = size
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+                input_size = Some(parsed.ok_or_else(|| {
+                    anyhow!("invalid input size `{size}`, expected <width>x<height>")
+                })?);
+            }
+            Arg::Option(opt) if opt == "--tolerance" => tolerance = args.value_str()?.parse()?,
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+    let tflite = match &*paths {
+        [path] => ModelFile::open(path)?,
+        _ => return Err(args.usage()),
+    };
+
+    let anchors = model_anchors(&tflite, subgraph)?;
+    let (width, height) = match input_size {
+        Some(size) => size,
+        None => image_input_size(&ModelGraph::from_bytes(&tflite)?, subgraph)
+            .context("pass the input size of the detector with --input-size")?,
+    };
+    let fit = tflite_metadump::fit_anchors(&anchors, width, height, tolerance)?;
+
+    match format {
+        Format::Text => {
+            match &fit.divergence {
+                None => println!(
+                    "the {} anchors are reproduced by these SsdAnchorsCalculatorOptions:",
+                    anchors.len(),
+                ),
+                Some(_) => println!(
+                    "no SsdAnchorsCalculatorOptions reproduce the {} anchors; the closest are:",
+                    anchors.len(),
+                ),
+            }
+            print_options(&fit.generator);
+            if fit.generator.fixed_anchor_size {
+                println!(
+                    "(the anchors have a fixed size, so the scales and aspect ratios can't be \
+                     recovered; only the number of anchors per layer is significant)"
+                );
+            }
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &fit)?;
+            println!();
+        }
+    }
+
+    if let Some(divergence) = &fit.divergence {
+        let describe = |anchor: Option<FixedAnchor>| match anchor {
+            Some(a) => format!(
+                "x_center {}, y_center {}, width {}, height {}",
+                a.x_center, a.y_center, a.width, a.height,
+            ),
+            None => "none".to_string(),
+        };
+        eprintln!("first diverging anchor: {}", divergence.index);
+        eprintln!("  model:     {}", describe(divergence.actual));
+        eprintln!("  generated: {}", describe(divergence.generated));
+        bail!(
+            "anchors diverge from the generator at anchor {}",
+            divergence.index
+        );
+    }
+    Ok(())
+}
+
+/// Prints the generator parameters in the text format of `SsdAnchorsCalculatorOptions`.
+fn print_options(generator: &SsdAnchorGenerator) {
+    println!("  num_layers: {}", generator.strides.len());
+    println!("  input_size_width: {}", generator.input_size_width);
+    println!("  input_size_height: {}", generator.input_size_height);
+    println!("  min_scale: {:?}", generator.min_scale);
+    println!("  max_scale: {:?}", generator.max_scale);
+    println!("  anchor_offset_x: {:?}", generator.anchor_offset_x);
+    println!("  anchor_offset_y: {:?}", generator.anchor_offset_y);
+    for stride in &generator.strides {
+        println!("  strides: {stride}");
+    }
+    for aspect_ratio in &generator.aspect_ratios {
+        println!("  aspect_ratios: {aspect_ratio:?}");
+    }
+    println!("  fixed_anchor_size: {}", generator.fixed_anchor_size);
+    println!(
+        "  reduce_boxes_in_lowest_layer: {}",
+        generator.reduce_boxes_in_lowest_layer,
+    );
+    println!(
+        "  interpolated_scale_aspect_ratio: {:?}",
+        generator.interpolated_scale_aspect_ratio,
+    );
+}
+
+/// Writes all anchors of a model as CSV or, for `.npy` output files, as a NumPy array.
+fn export(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut subgraph = 0;
+    let mut output = None;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--subgraph" => subgraph = args.value_str()?.parse()?,
+            Arg::Option(opt) if opt == "-o" || opt == "--output" => output = Some(args.value()?),
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+    let tflite = match &*paths {
+        [path] => ModelFile::open(path)?,
+        _ => return Err(args.usage()),
+    };
+
+    let anchors = model_anchors(&tflite, subgraph)?;
+    let Some(output) = output else {
+        print!("{}", tflite_metadump::anchors_to_csv(&anchors));
+        return Ok(());
+    };
+    let output = Path::new(&output);
+    let data = match output.extension() {
+        Some(ext) if ext == "npy" => tflite_metadump::anchors_to_npy(&anchors),
+        _ => tflite_metadump::anchors_to_csv(&anchors).into_bytes(),
+    };
+    fs::write(output, &data)?;
+    println!("wrote {} anchors to {}", anchors.len(), output.display());
+    Ok(())
+}
+
+/// Replaces the anchors of a model with those of a CSV or `.npy` file.
+fn import(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut subgraph = 0;
+    let mut output = None;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--subgraph" => subgraph = args.value_str()?.parse()?,
+            Arg::Option(opt) if opt == "-o" || opt == "--output" => output = Some(args.value()?),
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+    let (Some(output), [model, anchors]) = (output, &*paths) else {
+        return Err(args.usage());
+    };
+
+    let tflite = ModelFile::open(model)?;
+    let data = fs::read(anchors)
+        .with_context(|| format!("failed to read `{}`", anchors.to_string_lossy()))?;
+    let anchors = if data.starts_with(b"\x93NUMPY") {
+        tflite_metadump::anchors_from_npy(&data)
+    } else {
+        String::from_utf8(data)
+            .context("expected a CSV or .npy file")
+            .and_then(|csv| tflite_metadump::anchors_from_csv(&csv))
+    }
+    .with_context(|| format!("invalid anchors file `{}`", anchors.to_string_lossy()))?;
+
+    let count = anchors.len();
+    let out = tflite_metadump::write_anchors(&tflite, subgraph, anchors)?;
+    fs::write(&output, &out)?;
+    println!(
+        "wrote {} bytes with {count} anchors to {}",
+        out.len(),
+        Path::new(&output).display(),
+    );
+    Ok(())
+}
+
+/// Returns the fixed anchors of the `DETECTOR_METADATA` of subgraph `subgraph`.
+fn model_anchors(tflite: &[u8], subgraph: usize) -> anyhow::Result<Vec<FixedAnchor>> {
+    let meta = ModelInfo::from_bytes(tflite)?;
+    match ObjectDetectorOptions::from_model(&meta, subgraph)?.anchors() {
+        Some(anchors) => Ok(anchors.to_vec()),
+        None => bail!("detector metadata contains no fixed anchors"),
+    }
+}
+
+/// Returns the width and height of the `[1, height, width, channels]` first input of `subgraph`.
+fn image_input_size(graph: &ModelGraph, subgraph: usize) -> anyhow::Result<(u32, u32)> {
+    let sub = graph
+        .subgraphs
+        .get(subgraph)
+        .ok_or_else(|| anyhow!("model has no subgraph {subgraph}"))?;
+    let tensor = sub
+        .inputs
+        .first()
+        .and_then(|&input| sub.tensor(input))
+        .ok_or_else(|| anyhow!("subgraph {subgraph} has no input tensor"))?;
+    match tensor.shape[..] {
+        [1, height, width, _] if height > 0 && width > 0 => Ok((width as u32, height as u32)),
+        _ => bail!(
+            "expected an input of shape [1, height, width, channels], but it is {:?}",
+            tensor.shape,
+        ),
+    }
+}
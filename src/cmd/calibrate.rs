@@ -0,0 +1,92 @@
+use std::{ffi::OsString, fs};
+
+use anyhow::{anyhow, bail, Context};
+use tflite_metadump::{Archive, ModelFile, ModelInfo, ScoreCalibration};
+
+use super::{find_tensor, Arg, Args};
+
+const USAGE: &str = "\
+usage: tflite-metadump calibrate <model.tflite> <scores.csv> [--subgraph <n>] [--tensor <name|n>]";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut subgraph = 0;
+    let mut tensor = None;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--subgraph" => {
+                subgraph = args
+                    .value_str()?
+                    .parse()
+                    .context("invalid subgraph index")?;
+            }
+            Arg::Option(opt) if opt == "--tensor" => tensor = Some(args.value_str()?),
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+    let [model, scores] = &*paths else {
+        return Err(args.usage());
+    };
+
+    let tflite = ModelFile::open(model)?;
+    let meta = ModelInfo::from_bytes(&tflite)?;
+    let mut archive = Archive::from_bytes(&tflite)?;
+    let Some(sub) = meta.subgraph_metadata.get(subgraph) else {
+        bail!("model has no metadata for subgraph {subgraph}");
+    };
+    let outputs = &sub.output_tensor_metadata;
+
+    // Without `--tensor`, use the first output tensor with a score calibration.
+    let (index, calibration) = match &tensor {
+        Some(tensor) => {
+            let index = find_tensor(outputs, tensor)
+                .ok_or_else(|| anyhow!("no output tensor `{tensor}` in subgraph {subgraph}"))?;
+            let calibration = ScoreCalibration::from_tensor(&outputs[index], archive.as_mut())?
+                .ok_or_else(|| anyhow!("output tensor `{tensor}` has no score calibration"))?;
+            (index, calibration)
+        }
+        None => {
+            let mut found = None;
+            for (i, tensor) in outputs.iter().enumerate() {
+                if let Some(calibration) = ScoreCalibration::from_tensor(tensor, archive.as_mut())?
+                {
+                    found = Some((i, calibration));
+                    break;
+                }
+            }
+            found.ok_or_else(|| {
+                anyhow!("no output tensor in subgraph {subgraph} has a score calibration")
+            })?
+        }
+    };
+    eprintln!(
+        "calibrating output tensor {index} ({}) with {:?}, default score {}",
+        outputs[index].name.as_deref().unwrap_or("<unnamed>"),
+        calibration.score_transformation,
+        calibration.default_score,
+    );
+
+    // Each row of the CSV file holds the raw scores of one inference, indexed by class.
+    let csv = fs::read_to_string(scores)
+        .with_context(|| format!("failed to read `{}`", scores.to_string_lossy()))?;
+    for (line_no, line) in csv.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let calibrated =
+            line.split(',')
+                .enumerate()
+                .map(|(class, score)| {
+                    let score = score.trim().parse::<f32>().with_context(|| {
+                        format!("line {}: invalid score `{score}`", line_no + 1)
+                    })?;
+                    Ok(calibration.calibrate(class, score).to_string())
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+        println!("{}", calibrated.join(","));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::{ffi::OsString, io};
+
+use anyhow::{bail, Context};
+use tflite_metadump::{Archive, ArchiveEntry, ModelFile, ModelInfo};
+
+use super::{Arg, Args, Format};
+
+const USAGE: &str = "\
+usage: tflite-metadump diff [--format text|json] [--tolerance <t>] <a.tflite> <b.tflite>";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut tolerance = 1e-6;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => format = Format::parse(&args.value_str()?)?,
+            Arg::Option(opt) if opt == "--tolerance" => {
+                tolerance = args.value_str()?.parse().context("invalid tolerance")?;
+            }
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+    let [old, new] = &*paths else {
+        return Err(args.usage());
+    };
+
+    let (old, new) = (ModelFile::open(old)?, ModelFile::open(new)?);
+    let mut changes = tflite_metadump::diff(
+        &ModelInfo::from_bytes(&old)?,
+        &ModelInfo::from_bytes(&new)?,
+        tolerance,
+    );
+    changes.extend(tflite_metadump::diff_archives(
+        &archive_entries(&old)?,
+        &archive_entries(&new)?,
+    ));
+
+    match format {
+        Format::Text => {
+            for change in &changes {
+                println!("{change}");
+            }
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &changes)?;
+            println!();
+        }
+    }
+    if !changes.is_empty() {
+        bail!("found {} difference(s)", changes.len());
+    }
+
+    if format == Format::Text {
+        println!("metadata is identical");
+    }
+    Ok(())
+}
+
+fn archive_entries(tflite: &[u8]) -> anyhow::Result<Vec<ArchiveEntry>> {
+    Ok(Archive::from_bytes(tflite)?.map_or(Vec::new(), |archive| archive.entries().to_vec()))
+}
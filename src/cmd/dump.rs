@@ -0,0 +1,184 @@
+use std::{ffi::OsString, io};
+
+use tflite_metadump::{AssociatedFile, ModelFile, ModelInfo, ProcessUnit, TensorMetadata};
+
+use super::{Arg, Args, Format, Indent};
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut paths = Vec::new();
+    let mut args = Args::new(crate::USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => format = Format::parse(&args.value_str()?)?,
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+
+    let tflite = match &*paths {
+        [path] => ModelFile::open(path)?,
+        _ => return Err(args.usage()),
+    };
+
+    let meta = ModelInfo::from_bytes(&tflite)?;
+    match format {
+        Format::Text => print_text(&meta),
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &meta)?;
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+fn print_text(meta: &ModelInfo) {
+    if let Some(name) = &meta.name {
+        println!("name: {name}");
+    }
+    if let Some(description) = &meta.description {
+        println!("description: {description}");
+    }
+    if let Some(version) = &meta.version {
+        println!("version: {version}");
+    }
+    if let Some(author) = &meta.author {
+        println!("author: {author}");
+    }
+    if let Some(license) = &meta.license {
+        println!("license: {license}");
+    }
+    if let Some(min_parser_version) = &meta.min_parser_version {
+        println!("min_parser_version: {min_parser_version}");
+    }
+    if !meta.associated_files.is_empty() {
+        println!("{} associated file(s):", meta.associated_files.len());
+        print_assoc_files(Indent(0), &meta.associated_files);
+    }
+    if !meta.subgraph_metadata.is_empty() {
+        println!("{} subgraph(s)", meta.subgraph_metadata.len());
+    }
+    for sub in &meta.subgraph_metadata {
+        println!("- name: {}", sub.name.as_deref().unwrap_or("<unnamed>"));
+        if let Some(desc) = &sub.description {
+            println!("  description: {desc}");
+        }
+        if !sub.associated_files.is_empty() {
+            println!("  {} associated file(s):", sub.associated_files.len());
+            print_assoc_files(Indent(1), &sub.associated_files);
+        }
+        if !sub.input_tensor_metadata.is_empty() {
+            let inp = &sub.input_tensor_metadata;
+            println!("  - {} input tensor(s) with metadata", inp.len());
+            print_tensor_meta(Indent(2), inp);
+        }
+        if !sub.output_tensor_metadata.is_empty() {
+            let outp = &sub.output_tensor_metadata;
+            println!("  - {} output tensor(s) with metadata", outp.len());
+            print_tensor_meta(Indent(2), outp);
+        }
+        if !sub.input_process_units.is_empty() {
+            let inp = &sub.input_process_units;
+            println!("  - {} input tensor process units", inp.len());
+            print_proc(Indent(2), inp);
+        }
+        if !sub.output_process_units.is_empty() {
+            let outp = &sub.output_process_units;
+            println!("  - {} output tensor process units", outp.len());
+            print_proc(Indent(2), outp);
+        }
+        if !sub.input_tensor_groups.is_empty() {
+            println!("  - {} input tensor groups", sub.input_tensor_groups.len());
+            for group in &sub.input_tensor_groups {
+                println!("    - {group:?}");
+            }
+        }
+        if !sub.output_tensor_groups.is_empty() {
+            println!(
+                "  - {} output tensor groups",
+                sub.output_tensor_groups.len()
+            );
+            for group in &sub.output_tensor_groups {
+                println!("    - {group:?}");
+            }
+        }
+        if !sub.custom_metadata.is_empty() {
+            println!("  - {} custom metadata entries", sub.custom_metadata.len());
+            for custom in &sub.custom_metadata {
+                println!(
+                    "    - name: {}",
+                    custom.name.as_deref().unwrap_or("<unnamed>")
+                );
+                println!("      {} bytes", custom.data.len());
+
+                match custom.decode() {
+                    Ok(decoded) => {
+                        println!("      format: {}", decoded.format);
+                        for line in decoded.text.lines() {
+                            println!("{}{line}", Indent(3));
+                        }
+                    }
+                    Err(e) => println!("      decoding error: {e}"),
+                }
+            }
+        }
+    }
+}
+
+fn print_tensor_meta(indent: Indent, meta: &[TensorMetadata]) {
+    for tens in meta {
+        println!(
+            "{indent}- name: {}",
+            tens.name.as_deref().unwrap_or("<unnamed>")
+        );
+        if let Some(desc) = &tens.description {
+            println!("{indent}  description: {desc}");
+        }
+        if !tens.dimension_names.is_empty() {
+            println!("{indent}  dimension names: {:?}", tens.dimension_names);
+        }
+        if let Some(cont) = &tens.content {
+            println!("{indent}  content: {cont:?}");
+        }
+        if let Some(stats) = &tens.stats {
+            println!("{indent}  stats: {stats:?}");
+        }
+        if !tens.associated_files.is_empty() {
+            println!(
+                "{indent}  {} associated file(s):",
+                tens.associated_files.len()
+            );
+            print_assoc_files(indent + 1, &tens.associated_files);
+        }
+        if !tens.process_units.is_empty() {
+            println!("{indent}  {} process unit(s):", tens.process_units.len());
+            print_proc(indent + 1, &tens.process_units);
+        }
+    }
+}
+
+fn print_assoc_files(indent: Indent, assoc: &[AssociatedFile]) {
+    for file in assoc {
+        println!(
+            "{indent}- name: {}",
+            file.name.as_deref().unwrap_or("<unnamed>")
+        );
+        if let Some(desc) = &file.description {
+            println!("{indent}  description: {desc}");
+        }
+        println!("{indent}  type: {:?}", file.type_);
+        if let Some(locale) = &file.locale {
+            println!("{indent}  locale: {locale}");
+        }
+        if let Some(version) = &file.version {
+            println!("{indent}  version: {version}");
+        }
+    }
+}
+
+fn print_proc(indent: Indent, proc: &[ProcessUnit]) {
+    for pu in proc {
+        println!("{indent}- {pu:?}");
+    }
+}
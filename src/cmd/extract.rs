@@ -0,0 +1,135 @@
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use tflite_metadump::{Archive, ModelFile, ModelInfo};
+
+use super::{Arg, Args};
+
+const USAGE: &str = "usage: tflite-metadump extract <model.tflite> [-o <dir>] [<file>...]";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut out_dir = None;
+    let mut positional = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "-o" || opt == "--output" => {
+                out_dir = Some(PathBuf::from(args.value()?));
+            }
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(arg) => positional.push(arg),
+        }
+    }
+    if positional.is_empty() {
+        return Err(args.usage());
+    }
+    let path = positional.remove(0);
+    let selected = positional
+        .into_iter()
+        .map(|name| name.into_string())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|name| anyhow::anyhow!("invalid file name `{}`", name.to_string_lossy()))?;
+
+    let tflite = ModelFile::open(&path)?;
+    let meta = match ModelInfo::from_bytes(&tflite) {
+        Ok(meta) => Some(meta),
+        Err(e) => {
+            println!("could not read metadata, skipping consistency check: {e}");
+            None
+        }
+    };
+
+    let Some(mut archive) = Archive::from_bytes(&tflite)? else {
+        println!("model contains no associated files archive");
+        if let Some(meta) = &meta {
+            print_list(
+                "declared in metadata but missing from archive",
+                meta.all_associated_files()
+                    .iter()
+                    .filter_map(|file| file.name.as_deref()),
+            );
+        }
+        if out_dir.is_some() {
+            bail!("nothing to extract");
+        }
+        return Ok(());
+    };
+
+    let entries = archive.entries().to_vec();
+    println!(
+        "{} file(s) in associated files archive at offset {}:",
+        entries.len(),
+        archive.offset(),
+    );
+    for entry in &entries {
+        println!(
+            "- {}: {} bytes ({} compressed), crc32 {:08x}",
+            entry.name, entry.size, entry.compressed_size, entry.crc32,
+        );
+    }
+
+    if let Some(meta) = &meta {
+        let check = archive.check(meta);
+        print_list(
+            "declared in metadata but missing from archive",
+            check.missing.iter().map(String::as_str),
+        );
+        print_list(
+            "not declared in metadata",
+            check.undeclared.iter().map(String::as_str),
+        );
+    }
+
+    let Some(out_dir) = out_dir else {
+        if !selected.is_empty() {
+            bail!("no output directory given (use `-o <dir>`)");
+        }
+        return Ok(());
+    };
+
+    let names = if selected.is_empty() {
+        entries.iter().map(|entry| entry.name.clone()).collect()
+    } else {
+        for name in &selected {
+            if archive.entry(name).is_none() {
+                bail!("`{name}` is not in the associated files archive");
+            }
+        }
+        selected
+    };
+
+    for name in &names {
+        let dest = out_dir.join(safe_path(name)?);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = archive.read(name)?;
+        fs::write(&dest, data).with_context(|| format!("failed to write {}", dest.display()))?;
+    }
+    println!("wrote {} file(s) to {}", names.len(), out_dir.display());
+
+    Ok(())
+}
+
+/// Turns an archive entry name into a relative path that cannot escape the output directory.
+fn safe_path(name: &str) -> anyhow::Result<&Path> {
+    let path = Path::new(name);
+    if name.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        bail!("refusing to extract `{name}`: not a plain relative path");
+    }
+    Ok(path)
+}
+
+fn print_list<'a>(title: &str, names: impl Iterator<Item = &'a str>) {
+    let names = names.collect::<Vec<_>>();
+    if !names.is_empty() {
+        println!("{} file(s) {title}:", names.len());
+        for name in names {
+            println!("- {name}");
+        }
+    }
+}
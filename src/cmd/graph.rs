@@ -0,0 +1,127 @@
+use std::{ffi::OsString, io};
+
+use tflite_metadump::{ModelFile, ModelGraph, SubGraph, Tensor};
+
+use super::{Arg, Args, Format};
+
+const USAGE: &str = "usage: tflite-metadump graph [--format text|json] <model.tflite>";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => format = Format::parse(&args.value_str()?)?,
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+
+    let tflite = match &*paths {
+        [path] => ModelFile::open(path)?,
+        _ => return Err(args.usage()),
+    };
+
+    let graph = ModelGraph::from_bytes(&tflite)?;
+    match format {
+        Format::Text => print_text(&graph),
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &graph)?;
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+fn print_text(graph: &ModelGraph) {
+    println!("schema version: {}", graph.version);
+    if let Some(desc) = &graph.description {
+        println!("description: {desc}");
+    }
+    println!("{} subgraph(s)", graph.subgraphs.len());
+    for (i, sub) in graph.subgraphs.iter().enumerate() {
+        println!(
+            "- subgraph {i}: {}",
+            sub.name.as_deref().unwrap_or("<unnamed>")
+        );
+        println!("  inputs: {}", tensor_list(sub, &sub.inputs));
+        println!("  outputs: {}", tensor_list(sub, &sub.outputs));
+        println!("  {} tensor(s):", sub.tensors.len());
+        for (i, tensor) in sub.tensors.iter().enumerate() {
+            println!("  - {i}: {}", describe_tensor(tensor));
+        }
+        println!("  {} operator(s):", sub.operators.len());
+        for (i, op) in sub.operators.iter().enumerate() {
+            let custom_options = match op.custom_options_size {
+                0 => String::new(),
+                size => format!(", {size} bytes of custom options"),
+            };
+            match graph.operator_code(op) {
+                Some(code) => {
+                    println!("  - {i}: {} v{}{custom_options}", code.name(), code.version)
+                }
+                None => println!(
+                    "  - {i}: <invalid opcode index {}>{custom_options}",
+                    op.opcode_index
+                ),
+            }
+            println!("      inputs: {}", tensor_names(sub, &op.inputs));
+            println!("      outputs: {}", tensor_names(sub, &op.outputs));
+        }
+    }
+}
+
+fn describe_tensor(tensor: &Tensor) -> String {
+    let mut s = format!(
+        "{} {:?} {:?}",
+        tensor.name.as_deref().unwrap_or("<unnamed>"),
+        tensor.type_,
+        tensor.shape,
+    );
+    if let Some(sig) = &tensor.shape_signature {
+        if *sig != tensor.shape {
+            s += &format!(" (signature {sig:?})");
+        }
+    }
+    s += &format!(", buffer {}", tensor.buffer);
+    if tensor.is_constant {
+        s += ", constant";
+    }
+    if tensor.is_variable {
+        s += ", variable";
+    }
+    if let Some(quant) = &tensor.quantization {
+        s += &format!(", {}", quant.summary());
+    }
+    if let Some(sparsity) = &tensor.sparsity {
+        s += &format!(", {}", sparsity.describe(&tensor.shape));
+    }
+    s
+}
+
+/// Formats tensor indices along with the referenced tensor names.
+fn tensor_list(sub: &SubGraph, indices: &[i32]) -> String {
+    let list = indices
+        .iter()
+        .map(|&i| format!("{i} ({})", tensor_name(sub, i)))
+        .collect::<Vec<_>>();
+    format!("[{}]", list.join(", "))
+}
+
+fn tensor_names(sub: &SubGraph, indices: &[i32]) -> String {
+    let names = indices
+        .iter()
+        .map(|&i| tensor_name(sub, i))
+        .collect::<Vec<_>>();
+    names.join(", ")
+}
+
+fn tensor_name(sub: &SubGraph, index: i32) -> &str {
+    match sub.tensor(index) {
+        Some(tensor) => tensor.name.as_deref().unwrap_or("<unnamed>"),
+        None if index < 0 => "<none>",
+        None => "<invalid>",
+    }
+}
@@ -0,0 +1,116 @@
+use std::{ffi::OsString, io};
+
+use anyhow::{anyhow, bail, Context};
+use tflite_metadump::{Archive, AssociatedFileType, LabelMap, ModelFile, ModelInfo};
+
+use super::{find_tensor, Arg, Args, Format};
+
+const USAGE: &str = "\
+usage: tflite-metadump labels [--format text|json] [--subgraph <n>] [--tensor <name|n>]
+                              [--type axis|value] [--locale <locale>] <model.tflite>";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut subgraph = 0;
+    let mut tensor = None;
+    let mut types = vec![
+        AssociatedFileType::TENSOR_AXIS_LABELS,
+        AssociatedFileType::TENSOR_VALUE_LABELS,
+    ];
+    let mut locale = None;
+    let mut path = None;
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => format = Format::parse(&args.value_str()?)?,
+            Arg::Option(opt) if opt == "--subgraph" => {
+                subgraph = args
+                    .value_str()?
+                    .parse()
+                    .context("invalid subgraph index")?;
+            }
+            Arg::Option(opt) if opt == "--tensor" => tensor = Some(args.value_str()?),
+            Arg::Option(opt) if opt == "--type" => {
+                types = match &*args.value_str()? {
+                    "axis" => vec![AssociatedFileType::TENSOR_AXIS_LABELS],
+                    "value" => vec![AssociatedFileType::TENSOR_VALUE_LABELS],
+                    s => bail!("unknown label type `{s}` (expected `axis` or `value`)"),
+                }
+            }
+            Arg::Option(opt) if opt == "--locale" => locale = Some(args.value_str()?),
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(arg) if path.is_none() => path = Some(arg),
+            Arg::Positional(_) => return Err(args.usage()),
+        }
+    }
+    let Some(path) = path else {
+        return Err(args.usage());
+    };
+
+    let tflite = ModelFile::open(&path)?;
+    let meta = ModelInfo::from_bytes(&tflite)?;
+    let mut archive = Archive::from_bytes(&tflite)?;
+    let Some(sub) = meta.subgraph_metadata.get(subgraph) else {
+        bail!("model has no metadata for subgraph {subgraph}");
+    };
+    let outputs = &sub.output_tensor_metadata;
+    let candidates = match &tensor {
+        Some(tensor) => {
+            let index = find_tensor(outputs, tensor)
+                .ok_or_else(|| anyhow!("no output tensor `{tensor}` in subgraph {subgraph}"))?;
+            vec![index]
+        }
+        None => (0..outputs.len()).collect(),
+    };
+
+    // Without `--tensor`, use the first output tensor with labels, preferring axis labels.
+    let mut found = None;
+    'search: for index in candidates {
+        for &type_ in &types {
+            let labels =
+                LabelMap::from_tensor(&outputs[index], archive.as_mut(), type_, locale.as_deref())
+                    .with_context(|| {
+                        format!("failed to load the labels of output tensor {index}")
+                    })?;
+            if let Some(labels) = labels {
+                found = Some((index, labels));
+                break 'search;
+            }
+        }
+    }
+    let Some((index, labels)) = found else {
+        match &tensor {
+            Some(tensor) => bail!("output tensor `{tensor}` has no labels"),
+            None => bail!("no output tensor in subgraph {subgraph} has labels"),
+        }
+    };
+    if let (Some(locale), None) = (&locale, &labels.display_names_file) {
+        eprintln!("warning: no display names for locale `{locale}`");
+    }
+
+    match format {
+        Format::Text => {
+            eprintln!(
+                "{} of output tensor {index} ({}) from `{}`{}",
+                labels.type_.variant_name().unwrap_or_default(),
+                outputs[index].name.as_deref().unwrap_or("<unnamed>"),
+                labels.file,
+                match &labels.display_names_file {
+                    Some(file) => format!(", display names from `{file}`"),
+                    None => String::new(),
+                },
+            );
+            for label in &labels.labels {
+                match &label.display_name {
+                    Some(display_name) => println!("{}\t{display_name}", label.name),
+                    None => println!("{}", label.name),
+                }
+            }
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &labels)?;
+            println!();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+//! Implementations of the CLI subcommands.
+
+use core::fmt;
+use std::{ffi::OsString, ops::Add, vec};
+
+use anyhow::{anyhow, bail};
+use tflite_metadump::TensorMetadata;
+
+pub mod anchors;
+pub mod calibrate;
+pub mod diff;
+pub mod dump;
+pub mod extract;
+pub mod graph;
+pub mod labels;
+pub mod ops;
+pub mod preprocess;
+pub mod quant;
+pub mod scan;
+pub mod signatures;
+pub mod size;
+pub mod tokenize;
+pub mod validate;
+pub mod write;
+
+/// Minimal command line parser shared by the subcommands.
+///
+/// Arguments starting with `-` are returned as [`Arg::Option`]s, whose value (if any) can be
+/// fetched with [`Args::value`]. Both `--opt value` and `--opt=value` are supported.
+pub struct Args {
+    usage: &'static str,
+    args: vec::IntoIter<OsString>,
+    pending: Option<OsString>,
+}
+
+pub enum Arg {
+    Option(String),
+    Positional(OsString),
+}
+
+impl Args {
+    pub fn new(usage: &'static str, args: Vec<OsString>) -> Self {
+        Self {
+            usage,
+            args: args.into_iter(),
+            pending: None,
+        }
+    }
+
+    pub fn next(&mut self) -> anyhow::Result<Option<Arg>> {
+        if let Some(value) = self.pending.take() {
+            bail!(
+                "unexpected option value `{}`\n{}",
+                value.to_string_lossy(),
+                self.usage
+            );
+        }
+        let Some(arg) = self.args.next() else {
+            return Ok(None);
+        };
+        match arg.to_str() {
+            Some(s) if s.starts_with('-') && s.len() > 1 => match s.split_once('=') {
+                Some((opt, value)) => {
+                    self.pending = Some(value.into());
+                    Ok(Some(Arg::Option(opt.into())))
+                }
+                None => Ok(Some(Arg::Option(s.into()))),
+            },
+            _ => Ok(Some(Arg::Positional(arg))),
+        }
+    }
+
+    /// Returns the value of the last returned [`Arg::Option`].
+    pub fn value(&mut self) -> anyhow::Result<OsString> {
+        self.pending
+            .take()
+            .or_else(|| self.args.next())
+            .ok_or_else(|| anyhow!("missing option value\n{}", self.usage))
+    }
+
+    /// Returns the value of the last returned [`Arg::Option`] as a string.
+    pub fn value_str(&mut self) -> anyhow::Result<String> {
+        self.value()?
+            .into_string()
+            .map_err(|v| anyhow!("invalid UTF-8 in `{}`", v.to_string_lossy()))
+    }
+
+    /// Creates the error for an unrecognized option.
+    pub fn unknown(&self, opt: &str) -> anyhow::Error {
+        anyhow!("unknown option `{opt}`\n{}", self.usage)
+    }
+
+    /// Creates the error for invalid usage.
+    pub fn usage(&self) -> anyhow::Error {
+        anyhow!("{}", self.usage)
+    }
+}
+
+/// Returns the index of the tensor selected with `--tensor`, by name or index.
+pub fn find_tensor(tensors: &[TensorMetadata], tensor: &str) -> Option<usize> {
+    tensors
+        .iter()
+        .position(|t| t.name.as_deref() == Some(tensor))
+        .or_else(|| tensor.parse().ok().filter(|&i| i < tensors.len()))
+}
+
+/// Output format selected with `--format`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => bail!("unknown output format `{s}` (expected `text` or `json`)"),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Indent(pub usize);
+
+impl Add<usize> for Indent {
+    type Output = Self;
+
+    fn add(self, rhs: usize) -> Self::Output {
+        Self(self.0 + rhs)
+    }
+}
+
+impl fmt::Display for Indent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&"  ".repeat(self.0))
+    }
+}
@@ -0,0 +1,81 @@
+use std::{ffi::OsString, fs, io};
+
+use anyhow::{bail, Context};
+use tflite_metadump::{ModelFile, ModelGraph, OpAllowlist};
+
+use super::{Arg, Args, Format};
+
+const USAGE: &str = "usage: tflite-metadump ops [--format text|json] [--allowlist <ops.txt>] \
+                     <model.tflite>";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut allowlist = None;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => format = Format::parse(&args.value_str()?)?,
+            Arg::Option(opt) if opt == "--allowlist" => allowlist = Some(args.value()?),
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+    let tflite = match &*paths {
+        [path] => ModelFile::open(path)?,
+        _ => return Err(args.usage()),
+    };
+    let allowlist = allowlist
+        .map(|path| {
+            let file = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read `{}`", path.to_string_lossy()))?;
+            OpAllowlist::parse(&file)
+                .with_context(|| format!("invalid allowlist `{}`", path.to_string_lossy()))
+        })
+        .transpose()?;
+
+    let graph = ModelGraph::from_bytes(&tflite)?;
+    let ops = tflite_metadump::op_inventory(&graph);
+    match format {
+        Format::Text => {
+            for op in &ops {
+                let mut line = format!("{:>6}  {} v{}", op.count(), op.name, op.version);
+                if op.is_custom {
+                    line += " (custom)";
+                }
+                if i32::from(op.deprecated_builtin_code) != op.builtin_code.0 {
+                    line += &format!(
+                        " (deprecated_builtin_code {}, builtin_code {})",
+                        op.deprecated_builtin_code, op.builtin_code.0,
+                    );
+                }
+                if graph.subgraphs.len() > 1 {
+                    let counts = op
+                        .counts
+                        .iter()
+                        .map(|(s, count)| format!("{count} in subgraph {s}"))
+                        .collect::<Vec<_>>();
+                    if !counts.is_empty() {
+                        line += &format!(": {}", counts.join(", "));
+                    }
+                }
+                println!("{line}");
+            }
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &ops)?;
+            println!();
+        }
+    }
+
+    if let Some(allowlist) = allowlist {
+        let diags = allowlist.check(&ops);
+        for diag in &diags {
+            eprintln!("{diag}");
+        }
+        if !diags.is_empty() {
+            bail!("found {} unsupported operator(s)", diags.len());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use std::{ffi::OsString, fs, path::PathBuf};
+
+use anyhow::Context;
+use tflite_metadump::{Image, ImagePreprocessor, ModelFile};
+
+use super::{Arg, Args};
+
+const USAGE: &str = "\
+usage: tflite-metadump preprocess <model.tflite> <image.ppm|png> -o <out.raw> [--subgraph <n>] [--input <n>]";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut subgraph = 0;
+    let mut input = 0;
+    let mut out = None;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "-o" || opt == "--output" => {
+                out = Some(PathBuf::from(args.value()?));
+            }
+            Arg::Option(opt) if opt == "--subgraph" => {
+                subgraph = args
+                    .value_str()?
+                    .parse()
+                    .context("invalid subgraph index")?;
+            }
+            Arg::Option(opt) if opt == "--input" => {
+                input = args
+                    .value_str()?
+                    .parse()
+                    .context("invalid input tensor index")?;
+            }
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+    let ([model, image], Some(out)) = (&*paths, out) else {
+        return Err(args.usage());
+    };
+
+    let tflite = ModelFile::open(model)?;
+    let preprocessor = ImagePreprocessor::from_model(&tflite, subgraph, input)?;
+    let data =
+        fs::read(image).with_context(|| format!("failed to read `{}`", image.to_string_lossy()))?;
+    let image = Image::decode(&data)
+        .with_context(|| format!("failed to decode `{}`", image.to_string_lossy()))?;
+
+    let tensor = preprocessor.process(&image)?;
+    fs::write(&out, &tensor).with_context(|| format!("failed to write `{}`", out.display()))?;
+    println!(
+        "wrote {} bytes to {} ({:?} {}x{} {:?}{})",
+        tensor.len(),
+        out.display(),
+        preprocessor.type_,
+        preprocessor.width,
+        preprocessor.height,
+        preprocessor.color_space,
+        if preprocessor.normalization.is_some() {
+            ", normalized"
+        } else {
+            ""
+        },
+    );
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::{ffi::OsString, io};
+
+use anyhow::bail;
+use tflite_metadump::{ModelFile, ModelGraph, ModelInfo};
+
+use super::{Arg, Args, Format};
+
+const USAGE: &str = "usage: tflite-metadump quant [--format text|json] <model.tflite>";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => format = Format::parse(&args.value_str()?)?,
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+    let tflite = match &*paths {
+        [path] => ModelFile::open(path)?,
+        _ => return Err(args.usage()),
+    };
+
+    let graph = ModelGraph::from_bytes(&tflite)?;
+    // The report is still useful for models without (valid) metadata.
+    let meta = ModelInfo::from_bytes(&tflite)
+        .inspect_err(|e| eprintln!("warning: {e:#}; skipping the metadata checks"))
+        .ok();
+    let report = tflite_metadump::quant_report(meta.as_ref(), &graph);
+
+    match format {
+        Format::Text => {
+            for sub in &report.subgraphs {
+                let activations = sub
+                    .activation_types
+                    .iter()
+                    .map(|(type_, count)| format!("{count} {type_}"))
+                    .collect::<Vec<_>>();
+                println!(
+                    "subgraph {} ({}): activations {}{}",
+                    sub.index,
+                    sub.name.as_deref().unwrap_or("<unnamed>"),
+                    activations.join(", "),
+                    if sub.mixed_precision {
+                        " (mixed float and quantized)"
+                    } else {
+                        ""
+                    },
+                );
+                for tensor in &sub.tensors {
+                    let mut line = format!(
+                        "  {}: {} {:?} {:?}",
+                        tensor.index,
+                        tensor.name.as_deref().unwrap_or("<unnamed>"),
+                        tensor.type_,
+                        tensor.shape,
+                    );
+                    if tensor.is_constant {
+                        line += ", constant";
+                    }
+                    if let Some(quant) = &tensor.quantization {
+                        line += &format!(", {quant}");
+                    }
+                    if let Some(sparsity) = &tensor.sparsity {
+                        line += &format!(", {sparsity}");
+                    }
+                    println!("{line}");
+                }
+            }
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &report)?;
+            println!();
+        }
+    }
+
+    for note in report.subgraphs.iter().flat_map(|sub| &sub.notes) {
+        eprintln!("note: {note}");
+    }
+    let diags = report
+        .subgraphs
+        .iter()
+        .flat_map(|sub| &sub.diagnostics)
+        .collect::<Vec<_>>();
+    for diag in &diags {
+        eprintln!("{diag}");
+    }
+    if !diags.is_empty() {
+        bail!("found {} problem(s)", diags.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,284 @@
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use anyhow::{bail, Context};
+use serde::Serialize;
+use tflite_metadump::{ModelFile, ModelInfo, DETECTOR_METADATA_NAME};
+
+use super::{Arg, Args};
+
+const USAGE: &str = "\
+usage: tflite-metadump scan [--recursive] [--format text|csv|json] [-j <threads>] <path>...";
+
+/// Column headers of the summary table.
+const COLUMNS: [&str; 10] = [
+    "path",
+    "name",
+    "version",
+    "author",
+    "license",
+    "min_parser_version",
+    "metadata_subgraphs",
+    "detector_metadata",
+    "associated_files",
+    "error",
+];
+
+#[derive(Clone, Copy)]
+enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+/// One row of the summary table.
+///
+/// The counts are `None` (`null` in JSON) for paths that could not be read.
+#[derive(Clone, Default, Serialize)]
+struct Summary {
+    path: PathBuf,
+    name: Option<String>,
+    version: Option<String>,
+    author: Option<String>,
+    license: Option<String>,
+    min_parser_version: Option<String>,
+    /// Number of `SubGraphMetadata` entries, which may differ from the number of subgraphs.
+    metadata_subgraphs: Option<usize>,
+    detector_metadata: Option<bool>,
+    associated_files: Option<usize>,
+    error: Option<String>,
+}
+
+impl Summary {
+    fn new(path: PathBuf) -> Self {
+        let meta = ModelFile::open(&path).and_then(|tflite| ModelInfo::from_bytes(&tflite));
+        match meta {
+            Ok(meta) => Self {
+                detector_metadata: Some(meta.subgraph_metadata.iter().any(|sub| {
+                    sub.custom_metadata
+                        .iter()
+                        .any(|custom| custom.name.as_deref() == Some(DETECTOR_METADATA_NAME))
+                })),
+                associated_files: Some(meta.all_associated_files().len()),
+                metadata_subgraphs: Some(meta.subgraph_metadata.len()),
+                name: meta.name,
+                version: meta.version,
+                author: meta.author,
+                license: meta.license,
+                min_parser_version: meta.min_parser_version,
+                path,
+                error: None,
+            },
+            Err(e) => Self::error(path, &e),
+        }
+    }
+
+    /// Returns the row of a path that could not be read.
+    fn error(path: PathBuf, e: &anyhow::Error) -> Self {
+        Self {
+            path,
+            // Flatbuffer verification errors span several lines.
+            error: Some(
+                format!("{e:#}")
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn cells(&self) -> [String; 10] {
+        let opt = |s: &Option<String>| s.clone().unwrap_or_default();
+        let count = |n: Option<usize>| n.map_or(String::new(), |n| n.to_string());
+        [
+            self.path.display().to_string(),
+            opt(&self.name),
+            opt(&self.version),
+            opt(&self.author),
+            opt(&self.license),
+            opt(&self.min_parser_version),
+            count(self.metadata_subgraphs),
+            match self.detector_metadata {
+                Some(true) => "yes".into(),
+                Some(false) => "no".into(),
+                None => String::new(),
+            },
+            count(self.associated_files),
+            opt(&self.error),
+        ]
+    }
+}
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut recursive = false;
+    let mut threads = thread::available_parallelism().map_or(1, usize::from);
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => {
+                format = match &*args.value_str()? {
+                    "text" => Format::Text,
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    s => bail!("unknown output format `{s}` (expected `text`, `csv` or `json`)"),
+                }
+            }
+            Arg::Option(opt) if opt == "-r" || opt == "--recursive" => recursive = true,
+            Arg::Option(opt) if opt == "-j" || opt == "--jobs" => {
+                threads = args.value_str()?.parse().context("invalid thread count")?;
+            }
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(PathBuf::from(path)),
+        }
+    }
+    if paths.is_empty() {
+        return Err(args.usage());
+    }
+
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            find_models(&path, recursive, &mut files);
+        } else {
+            files.push(Ok(path));
+        }
+    }
+
+    let summaries = summarize(files, threads.max(1));
+    match format {
+        Format::Text => print_table(&summaries),
+        Format::Csv => {
+            println!("{}", COLUMNS.join(","));
+            for summary in &summaries {
+                let cells = summary.cells().map(|cell| csv_field(&cell));
+                println!("{}", cells.join(","));
+            }
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &summaries)?;
+            println!();
+        }
+    }
+
+    let failed = summaries.iter().filter(|s| s.error.is_some()).count();
+    if failed > 0 {
+        bail!("{failed} of {} path(s) could not be read", summaries.len());
+    }
+    Ok(())
+}
+
+/// Collects the `.tflite` files in `dir`, descending into subdirectories if `recursive` is set.
+///
+/// Symbolic links to directories are not followed, so that link cycles can't recurse forever.
+/// Directories and entries that can't be read are collected as error rows, so that they don't
+/// abort the scan.
+fn find_models(dir: &Path, recursive: bool, files: &mut Vec<Result<PathBuf, Summary>>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            let e = anyhow::Error::new(e).context("failed to read directory");
+            files.push(Err(Summary::error(dir.into(), &e)));
+            return;
+        }
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(entry) => match entry.file_type() {
+                Ok(file_type) => paths.push((entry.path(), file_type.is_dir())),
+                Err(e) => {
+                    let e = anyhow::Error::new(e).context("failed to read file type");
+                    files.push(Err(Summary::error(entry.path(), &e)));
+                }
+            },
+            Err(e) => {
+                let e = anyhow::Error::new(e).context("failed to read directory entry");
+                files.push(Err(Summary::error(dir.into(), &e)));
+            }
+        }
+    }
+    paths.sort();
+    for (path, is_dir) in paths {
+        if is_dir {
+            if recursive {
+                find_models(&path, recursive, files);
+            }
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("tflite"))
+        {
+            files.push(Ok(path));
+        }
+    }
+}
+
+/// Summarizes `files` on `threads` threads, keeping their order; errors are passed through.
+fn summarize(files: Vec<Result<PathBuf, Summary>>, threads: usize) -> Vec<Summary> {
+    let next = AtomicUsize::new(0);
+    let mut summaries = thread::scope(|s| {
+        let workers = (0..threads.min(files.len()))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let summary = match files.get(i) {
+                            Some(Ok(path)) => Summary::new(path.clone()),
+                            Some(Err(summary)) => summary.clone(),
+                            None => return done,
+                        };
+                        done.push((i, summary));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    summaries.sort_by_key(|&(i, _)| i);
+    summaries.into_iter().map(|(_, summary)| summary).collect()
+}
+
+fn print_table(summaries: &[Summary]) {
+    let rows = summaries.iter().map(Summary::cells).collect::<Vec<_>>();
+    let mut widths = COLUMNS.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let print_row = |cells: &[&str]| {
+        let line = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>();
+        println!("{}", line.join("  ").trim_end());
+    };
+    print_row(&COLUMNS);
+    for row in &rows {
+        print_row(&row.each_ref().map(String::as_str));
+    }
+}
+
+/// Quotes a CSV field if necessary (RFC 4180).
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
@@ -0,0 +1,85 @@
+use std::{ffi::OsString, io};
+
+use tflite_metadump::{ModelFile, ModelGraph, ModelInfo, SignatureTensor};
+
+use super::{Arg, Args, Format};
+
+const USAGE: &str = "usage: tflite-metadump signatures [--format text|json] <model.tflite>";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => format = Format::parse(&args.value_str()?)?,
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+    let tflite = match &*paths {
+        [path] => ModelFile::open(path)?,
+        _ => return Err(args.usage()),
+    };
+
+    let graph = ModelGraph::from_bytes(&tflite)?;
+    // Signatures are still listed for models without (valid) metadata.
+    let meta = ModelInfo::from_bytes(&tflite)
+        .inspect_err(|e| eprintln!("warning: {e:#}; signatures are listed without metadata"))
+        .ok();
+    let signatures = tflite_metadump::signatures(meta.as_ref(), &graph);
+
+    match format {
+        Format::Text => {
+            if signatures.is_empty() {
+                println!("model has no signatures");
+            }
+            for sig in &signatures {
+                println!(
+                    "signature {:?} (subgraph {}, {}):",
+                    sig.signature_key.as_deref().unwrap_or("<unnamed>"),
+                    sig.subgraph_index,
+                    sig.subgraph_name.as_deref().unwrap_or("<unnamed>"),
+                );
+                for (kind, tensors) in [("input", &sig.inputs), ("output", &sig.outputs)] {
+                    println!("  {kind}s:");
+                    for tensor in tensors {
+                        println!("    {}", describe(tensor, kind));
+                    }
+                }
+            }
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &signatures)?;
+            println!();
+        }
+    }
+    Ok(())
+}
+
+fn describe(tensor: &SignatureTensor, kind: &str) -> String {
+    let mut s = format!(
+        "{} -> tensor {}",
+        tensor.alias.as_deref().unwrap_or("<unnamed>"),
+        tensor.tensor_index,
+    );
+    match (&tensor.tensor_name, tensor.type_, &tensor.shape) {
+        (name, Some(type_), Some(shape)) => {
+            s += &format!(
+                " ({} {type_:?} {shape:?})",
+                name.as_deref().unwrap_or("<unnamed>")
+            );
+        }
+        _ => s += " (out of range)",
+    }
+    match tensor.metadata_index {
+        Some(index) => {
+            s += &format!(
+                ", metadata {kind} {index} ({:?})",
+                tensor.metadata_name.as_deref().unwrap_or("<unnamed>"),
+            );
+        }
+        None => s += ", no metadata",
+    }
+    s
+}
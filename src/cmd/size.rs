@@ -0,0 +1,140 @@
+use std::{cmp::Reverse, collections::BTreeMap, ffi::OsString, io};
+
+use tflite_metadump::{ModelFile, SizeReport};
+
+use super::{Arg, Args, Format};
+
+const USAGE: &str = "usage: tflite-metadump size [--format text|json] [--top <n>] <model.tflite>";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut top = 10;
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => format = Format::parse(&args.value_str()?)?,
+            Arg::Option(opt) if opt == "--top" => top = args.value_str()?.parse()?,
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+    let tflite = match &*paths {
+        [path] => ModelFile::open(path)?,
+        _ => return Err(args.usage()),
+    };
+
+    let report = tflite_metadump::size_report(&tflite)?;
+    match format {
+        Format::Text => print_text(&report, top),
+        Format::Json => {
+            serde_json::to_writer_pretty(io::stdout().lock(), &report)?;
+            println!();
+        }
+    }
+    Ok(())
+}
+
+fn print_text(report: &SizeReport, top: usize) {
+    let total = report.file_size;
+    let line = |name: &str, size: u64| {
+        println!("  {name:<32} {size:>12} {:>6}", percent(size, total));
+    };
+
+    println!("file: {total} bytes");
+    line("flatbuffer", report.flatbuffer_size);
+    line("  overhead", report.flatbuffer_overhead);
+    if report.external_size > 0 {
+        line("external data", report.external_size);
+    }
+    if report.unaccounted_size > 0 {
+        line("padding", report.unaccounted_size);
+    }
+    if let Some(archive) = &report.archive {
+        line("associated files archive", archive.size);
+        for file in &archive.files {
+            line(
+                &format!("  {} ({} uncompressed)", file.name, file.size),
+                file.compressed_size,
+            );
+        }
+    }
+
+    println!("metadata:");
+    if report.metadata.is_empty() {
+        println!("  none");
+    }
+    for meta in &report.metadata {
+        line(
+            &format!(
+                "{} (buffer {})",
+                meta.name.as_deref().unwrap_or("<unnamed>"),
+                meta.buffer,
+            ),
+            meta.size,
+        );
+    }
+
+    println!("constant tensor data: {} bytes", report.constant_size());
+    println!("by type:");
+    for (type_, size) in sorted(&report.types) {
+        line(type_, size);
+    }
+    println!("by operator:");
+    for (op, size) in sorted(&report.operators) {
+        line(op, size);
+    }
+    println!("largest tensors:");
+    for tensor in report.tensors.iter().take(top) {
+        line(
+            &format!(
+                "{}:{} {} (buffer {})",
+                tensor.subgraph,
+                tensor.index,
+                tensor.name.as_deref().unwrap_or("<unnamed>"),
+                tensor.buffer,
+            ),
+            tensor.size,
+        );
+    }
+    if report.tensors.len() > top {
+        println!("  ({} more)", report.tensors.len() - top);
+    }
+
+    if !report.unreferenced.is_empty() {
+        println!("unreferenced buffers:");
+        for &buffer in &report.unreferenced {
+            line(&format!("buffer {buffer}"), report.buffer_size(buffer));
+        }
+    }
+    if !report.duplicates.is_empty() {
+        println!(
+            "duplicate buffers ({} bytes could be saved):",
+            report.duplicate_size()
+        );
+        for group in &report.duplicates {
+            let buffers = group.iter().map(|b| b.to_string()).collect::<Vec<_>>();
+            line(
+                &format!("buffers {}", buffers.join(", ")),
+                report.buffer_size(group[0]),
+            );
+        }
+    }
+}
+
+/// Returns the entries of `sizes`, largest first.
+fn sorted(sizes: &BTreeMap<String, u64>) -> Vec<(&str, u64)> {
+    let mut sizes = sizes
+        .iter()
+        .map(|(name, &size)| (&**name, size))
+        .collect::<Vec<_>>();
+    sizes.sort_by_key(|&(_, size)| Reverse(size));
+    sizes
+}
+
+fn percent(size: u64, total: u64) -> String {
+    if total == 0 {
+        return String::new();
+    }
+    format!("{:.1}%", size as f64 * 100.0 / total as f64)
+}
@@ -0,0 +1,74 @@
+use std::{ffi::OsString, io};
+
+use anyhow::Context;
+use serde::Serialize;
+use tflite_metadump::{Encoding, ModelFile, Tokenizer};
+
+use super::{Arg, Args, Format};
+
+const USAGE: &str = "\
+usage: tflite-metadump tokenize [--format text|json] [--subgraph <n>] <model.tflite> <text>...";
+
+#[derive(Serialize)]
+struct Tokenized {
+    text: String,
+    #[serde(flatten)]
+    encoding: Encoding,
+}
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut format = Format::Text;
+    let mut subgraph = 0;
+    let mut positional = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "--format" => format = Format::parse(&args.value_str()?)?,
+            Arg::Option(opt) if opt == "--subgraph" => {
+                subgraph = args
+                    .value_str()?
+                    .parse()
+                    .context("invalid subgraph index")?;
+            }
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(arg) => positional.push(arg),
+        }
+    }
+    let Some((model, texts)) = positional.split_first() else {
+        return Err(args.usage());
+    };
+    if texts.is_empty() {
+        return Err(args.usage());
+    }
+
+    let tflite = ModelFile::open(model)?;
+    let tokenizer = Tokenizer::from_model(&tflite, subgraph)?;
+
+    let mut encodings = Vec::new();
+    for (i, text) in texts.iter().enumerate() {
+        let text = text.to_string_lossy().into_owned();
+        let encoding = tokenizer.encode(&text);
+        match format {
+            Format::Text => {
+                if i > 0 {
+                    println!();
+                }
+                let join =
+                    |ids: &[i32]| ids.iter().map(i32::to_string).collect::<Vec<_>>().join(" ");
+                println!("text:        {text}");
+                println!("tokens:      {}", encoding.tokens.join(" "));
+                println!("ids:         {}", join(&encoding.ids));
+                if !encoding.mask.is_empty() {
+                    println!("mask:        {}", join(&encoding.mask));
+                    println!("segment_ids: {}", join(&encoding.segment_ids));
+                }
+            }
+            Format::Json => encodings.push(Tokenized { text, encoding }),
+        }
+    }
+    if format == Format::Json {
+        serde_json::to_writer_pretty(io::stdout().lock(), &encodings)?;
+        println!();
+    }
+    Ok(())
+}
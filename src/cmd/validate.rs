@@ -0,0 +1,37 @@
+use std::ffi::OsString;
+
+use anyhow::bail;
+use tflite_metadump::{ModelFile, ModelGraph, ModelInfo};
+
+use super::{Arg, Args};
+
+const USAGE: &str = "usage: tflite-metadump validate <model.tflite>";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut paths = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(path) => paths.push(path),
+        }
+    }
+
+    let tflite = match &*paths {
+        [path] => ModelFile::open(path)?,
+        _ => return Err(args.usage()),
+    };
+
+    let meta = ModelInfo::from_bytes(&tflite)?;
+    let graph = ModelGraph::from_bytes(&tflite)?;
+    let diags = tflite_metadump::validate(&meta, &graph);
+    for diag in &diags {
+        println!("{diag}");
+    }
+    if !diags.is_empty() {
+        bail!("found {} problem(s)", diags.len());
+    }
+
+    println!("metadata is consistent with the model graph");
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use std::{ffi::OsString, fs, path::Path};
+
+use anyhow::{anyhow, Context};
+use tflite_metadump::{ModelFile, ModelInfo};
+
+use super::{Arg, Args};
+
+const USAGE: &str = "\
+usage: tflite-metadump write <model.tflite> <metadata.json> -o <out.tflite> [<file>...]";
+
+pub fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let mut output = None;
+    let mut positional = Vec::new();
+    let mut args = Args::new(USAGE, args);
+    while let Some(arg) = args.next()? {
+        match arg {
+            Arg::Option(opt) if opt == "-o" || opt == "--output" => output = Some(args.value()?),
+            Arg::Option(opt) => return Err(args.unknown(&opt)),
+            Arg::Positional(arg) => positional.push(arg),
+        }
+    }
+    let (Some(output), [model, json, files @ ..]) = (output, &*positional) else {
+        return Err(args.usage());
+    };
+
+    let tflite = ModelFile::open(model)?;
+    let json = fs::read(json)?;
+    let meta: ModelInfo = serde_json::from_slice(&json).context("invalid metadata JSON")?;
+
+    // Files are packed under their file name, which is how `AssociatedFile.name` refers to them.
+    let files = files
+        .iter()
+        .map(|path| {
+            let name = Path::new(path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| anyhow!("invalid file name `{}`", path.to_string_lossy()))?;
+            let data = fs::read(path)
+                .with_context(|| format!("failed to read `{}`", path.to_string_lossy()))?;
+            Ok((name.to_string(), data))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let out = tflite_metadump::write_metadata(&tflite, &meta, &files)?;
+    fs::write(&output, &out)?;
+    println!(
+        "wrote {} bytes to {}",
+        out.len(),
+        Path::new(&output).display(),
+    );
+    Ok(())
+}
@@ -0,0 +1,408 @@
+//! Decoding of [`CustomMetadata`][crate::CustomMetadata] entries.
+//!
+//! Entries are decoded by the first decoder that handles them: the decoders added with
+//! [`register_decoder`] in the order of registration, then the built-in decoders. Entries that no
+//! decoder handles are described by their flatbuffer `file_identifier`, as JSON or text, or by a
+//! hexdump of their first bytes.
+
+use core::fmt;
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    ImageSegmenterOptions, ObjectDetectorOptions, DETECTOR_METADATA_NAME, SEGMENTER_METADATA_NAME,
+};
+
+/// Number of bytes shown by the hexdump fallback.
+const HEXDUMP_PREVIEW: usize = 64;
+/// Number of lines shown by the text fallback.
+const TEXT_PREVIEW: usize = 16;
+
+static DECODERS: RwLock<Vec<Arc<dyn CustomMetadataDecoder>>> = RwLock::new(Vec::new());
+
+/// Decodes the data of [`CustomMetadata`][crate::CustomMetadata] entries.
+///
+/// Closures with the signature of [`CustomMetadataDecoder::decode`] implement this trait.
+pub trait CustomMetadataDecoder: Send + Sync {
+    /// Decodes the `data` of the entry named `name`.
+    ///
+    /// Returns [`None`] if the decoder doesn't handle the entry, so that the next decoder is tried.
+    fn decode(&self, name: Option<&str>, data: &[u8]) -> Option<anyhow::Result<DecodedMetadata>>;
+}
+
+impl<F> CustomMetadataDecoder for F
+where
+    F: Fn(Option<&str>, &[u8]) -> Option<anyhow::Result<DecodedMetadata>> + Send + Sync,
+{
+    fn decode(&self, name: Option<&str>, data: &[u8]) -> Option<anyhow::Result<DecodedMetadata>> {
+        self(name, data)
+    }
+}
+
+/// A decoded [`CustomMetadata`][crate::CustomMetadata] entry.
+///
+/// Serializes as its `format` and `value`; the text rendering is what the `Display`
+/// implementation prints.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedMetadata {
+    /// Name of the format, e.g. `ObjectDetectorOptions` or `JSON`.
+    pub format: String,
+    pub value: Value,
+    /// Human-readable rendering of the value, possibly spanning several lines.
+    #[serde(skip)]
+    pub text: String,
+}
+
+impl DecodedMetadata {
+    /// Creates a decoded entry from a serializable value, rendered as pretty-printed JSON.
+    pub fn new(format: impl Into<String>, value: &impl Serialize) -> anyhow::Result<Self> {
+        let value = serde_json::to_value(value)?;
+        Ok(Self {
+            format: format.into(),
+            text: serde_json::to_string_pretty(&value)?,
+            value,
+        })
+    }
+
+    /// Replaces the text rendering of the entry.
+    pub fn with_text(self, text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for DecodedMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Adds a decoder that is tried before the built-in ones, and after the decoders registered
+/// before it.
+pub fn register_decoder(decoder: impl CustomMetadataDecoder + 'static) {
+    DECODERS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Arc::new(decoder));
+}
+
+/// Decodes the `data` of the custom metadata entry named `name`.
+///
+/// Fails if the decoder handling the entry fails; entries that no decoder handles are described
+/// by [`fallback`].
+pub fn decode_custom_metadata(name: Option<&str>, data: &[u8]) -> anyhow::Result<DecodedMetadata> {
+    // Decoders are cloned out of the lock, so that they may register further decoders.
+    let registered = DECODERS.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let builtins: [&dyn CustomMetadataDecoder; 2] =
+        [&decode_detector_metadata, &decode_segmenter_metadata];
+    let decoded = registered
+        .iter()
+        .map(|decoder| &**decoder)
+        .chain(builtins)
+        .find_map(|decoder| decoder.decode(name, data));
+    decoded.unwrap_or_else(|| Ok(fallback(data)))
+}
+
+/// Describes data of an unknown format: flatbuffers by their `file_identifier`, UTF-8 by its
+/// contents (parsed if it is a JSON object or array), and other data by a hexdump of its first
+/// bytes.
+pub fn fallback(data: &[u8]) -> DecodedMetadata {
+    if let Some(identifier) = file_identifier(data) {
+        let text = format!(
+            "flatbuffer with file_identifier {identifier:?}, {} bytes",
+            data.len(),
+        );
+        let value = serde_json::json!({ "file_identifier": identifier, "size": data.len() });
+        return DecodedMetadata {
+            format: "flatbuffer".into(),
+            value,
+            text,
+        };
+    }
+
+    if let Ok(text) = std::str::from_utf8(data) {
+        if text
+            .chars()
+            .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+        {
+            if let Ok(value @ (Value::Object(_) | Value::Array(_))) = serde_json::from_str(text) {
+                let text = serde_json::to_string_pretty(&value).unwrap_or_default();
+                return DecodedMetadata {
+                    format: "JSON".into(),
+                    value,
+                    text,
+                };
+            }
+            let mut preview = text
+                .lines()
+                .take(TEXT_PREVIEW)
+                .collect::<Vec<_>>()
+                .join("\n");
+            let lines = text.lines().count();
+            if lines > TEXT_PREVIEW {
+                preview += &format!("\n...{} more lines", lines - TEXT_PREVIEW);
+            }
+            return DecodedMetadata {
+                format: "text".into(),
+                value: text.into(),
+                text: preview,
+            };
+        }
+    }
+
+    let preview = &data[..data.len().min(HEXDUMP_PREVIEW)];
+    let mut text = hexdump(preview);
+    if data.len() > preview.len() {
+        text += &format!("\n...{} more bytes", data.len() - preview.len());
+    }
+    let hex = preview
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    DecodedMetadata {
+        format: "binary".into(),
+        value: serde_json::json!({ "size": data.len(), "preview": hex }),
+        text,
+    }
+}
+
+/// Returns the `file_identifier` of data that looks like a flatbuffer: a root table offset within
+/// the data, followed by 4 alphanumeric ASCII characters.
+fn file_identifier(data: &[u8]) -> Option<&str> {
+    let root = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let identifier = data.get(4..8)?;
+    let plausible = root >= 8
+        && root < data.len()
+        && root.is_multiple_of(4)
+        && identifier.iter().all(u8::is_ascii_alphanumeric);
+    plausible.then(|| std::str::from_utf8(identifier).ok())?
+}
+
+/// Formats `data` like `xxd`: offsets, 16 bytes per line in hex, and the printable characters.
+fn hexdump(data: &[u8]) -> String {
+    data.chunks(16)
+        .enumerate()
+        .map(|(i, line)| {
+            let hex = line
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = line
+                .iter()
+                .map(|&b| match b {
+                    0x20..=0x7e => b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            format!("{:08x}  {hex:<47}  |{ascii}|", i * 16)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn decode_detector_metadata(
+    name: Option<&str>,
+    data: &[u8],
+) -> Option<anyhow::Result<DecodedMetadata>> {
+    if name != Some(DETECTOR_METADATA_NAME) {
+        return None;
+    }
+    let decoded = ObjectDetectorOptions::from_bytes(data).and_then(|options| {
+        Ok(DecodedMetadata::new("ObjectDetectorOptions", &options)?
+            .with_text(detector_text(&options)))
+    });
+    Some(decoded)
+}
+
+fn decode_segmenter_metadata(
+    name: Option<&str>,
+    data: &[u8],
+) -> Option<anyhow::Result<DecodedMetadata>> {
+    if name != Some(SEGMENTER_METADATA_NAME) {
+        return None;
+    }
+    let decoded = ImageSegmenterOptions::from_bytes(data).and_then(|options| {
+        let mut lines = vec![format!("activation: {:?}", options.activation)];
+        if let Some(min) = &options.min_parser_version {
+            lines.push(format!("min_parser_version: {min}"));
+        }
+        Ok(DecodedMetadata::new("ImageSegmenterOptions", &options)?.with_text(lines.join("\n")))
+    });
+    Some(decoded)
+}
+
+fn detector_text(options: &ObjectDetectorOptions) -> String {
+    let mut lines = Vec::new();
+    if let Some(min) = &options.min_parser_version {
+        lines.push(format!("min_parser_version: {min}"));
+    }
+    if let Some(dec) = &options.tensors_decoding_options {
+        lines.push(format!("tensors_decoding_options: {dec:?}"));
+    }
+    if let Some(ssd) = &options.ssd_anchors_options {
+        lines.push("ssd_anchor_options:".into());
+        if let Some(fixed) = &ssd.fixed_anchors_schema {
+            lines.push("  fixed_anchors_schema:".into());
+            let anchors = &fixed.anchors;
+            // (this can contain thousands of anchors, so don't print all of them)
+            const PRINT: usize = 24;
+            lines.push(format!("    {} anchors", anchors.len()));
+            if anchors.len() <= PRINT {
+                for anchor in anchors {
+                    lines.push(format!("    - {anchor:?}"));
+                }
+            } else {
+                for anchor in &anchors[..PRINT / 2] {
+                    lines.push(format!("    - {anchor:?}"));
+                }
+                lines.push(format!("    - ...{} more", anchors.len() - PRINT));
+                for anchor in &anchors[anchors.len() - PRINT / 2..] {
+                    lines.push(format!("    - {anchor:?}"));
+                }
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use flatbuffers::FlatBufferBuilder;
+
+    use super::*;
+
+    /// A flatbuffer with an empty root table and the given file identifier.
+    fn flatbuffer(identifier: &str) -> Vec<u8> {
+        let mut fbb = FlatBufferBuilder::new();
+        let start = fbb.start_table();
+        let root = fbb.end_table(start);
+        fbb.finish(root, Some(identifier));
+        fbb.finished_data().to_vec()
+    }
+
+    #[test]
+    fn identifier_heuristic() {
+        let data = flatbuffer("SEG1");
+        assert_eq!(file_identifier(&data), Some("SEG1"));
+        let decoded = fallback(&data);
+        assert_eq!(decoded.format, "flatbuffer");
+        assert_eq!(
+            decoded.value,
+            serde_json::json!({ "file_identifier": "SEG1", "size": data.len() })
+        );
+
+        let with_root = |root: u32, identifier: &[u8; 4]| {
+            let mut data = root.to_le_bytes().to_vec();
+            data.extend_from_slice(identifier);
+            data.resize(32, 0);
+            data
+        };
+        assert_eq!(file_identifier(&with_root(12, b"ab12")), Some("ab12"));
+        // The root table can't overlap the header, must be aligned and inside the data, and the
+        // identifier must be alphanumeric.
+        assert_eq!(file_identifier(&with_root(4, b"ab12")), None);
+        assert_eq!(file_identifier(&with_root(14, b"ab12")), None);
+        assert_eq!(file_identifier(&with_root(32, b"ab12")), None);
+        assert_eq!(file_identifier(&with_root(12, b"ab 1")), None);
+        assert_eq!(file_identifier(&[12, 0, 0, 0, b'a']), None);
+    }
+
+    #[test]
+    fn json_and_text() {
+        let decoded = fallback(br#"{"labels": ["cat", "dog"]}"#);
+        assert_eq!(decoded.format, "JSON");
+        assert_eq!(
+            decoded.value,
+            serde_json::json!({ "labels": ["cat", "dog"] })
+        );
+
+        // JSON scalars are shown as text.
+        let decoded = fallback(b"42");
+        assert_eq!((&*decoded.format, &*decoded.text), ("text", "42"));
+
+        let lines = (0..20).map(|i| format!("line {i}")).collect::<Vec<_>>();
+        let decoded = fallback(lines.join("\n").as_bytes());
+        assert_eq!(decoded.format, "text");
+        assert_eq!(decoded.value, lines.join("\n"));
+        assert_eq!(
+            decoded.text,
+            format!("{}\n...4 more lines", lines[..16].join("\n"))
+        );
+    }
+
+    #[test]
+    fn hexdump_preview() {
+        // Control characters make UTF-8 data binary.
+        let decoded = fallback(b"a\x00b");
+        assert_eq!(decoded.format, "binary");
+        assert_eq!(
+            decoded.text,
+            "00000000  61 00 62                                         |a.b|"
+        );
+
+        let data = (0..100).collect::<Vec<u8>>();
+        let decoded = fallback(&data);
+        assert_eq!(decoded.format, "binary");
+        assert_eq!(decoded.value["size"], 100);
+        let preview = decoded.value["preview"].as_str().unwrap();
+        assert_eq!(preview.len(), 2 * HEXDUMP_PREVIEW);
+        assert!(preview.starts_with("000102"));
+        let lines = decoded.text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[3].starts_with("00000030  30 31 32"));
+        assert_eq!(lines[4], "...36 more bytes");
+    }
+
+    #[test]
+    fn builtin_decoders() {
+        let options = ObjectDetectorOptions {
+            min_parser_version: Some("1.0.0".into()),
+            ssd_anchors_options: None,
+            tensors_decoding_options: None,
+        };
+        let decoded =
+            decode_custom_metadata(Some(DETECTOR_METADATA_NAME), &options.to_flatbuffer()).unwrap();
+        assert_eq!(decoded.format, "ObjectDetectorOptions");
+        assert_eq!(decoded.value["min_parser_version"], "1.0.0");
+        assert_eq!(decoded.text, "min_parser_version: 1.0.0");
+
+        // Malformed data of a known entry is an error, not a fallback.
+        assert!(decode_custom_metadata(Some(DETECTOR_METADATA_NAME), b"\xff\xff").is_err());
+        // Unknown entries use the fallback.
+        let decoded = decode_custom_metadata(Some("notes"), b"hello").unwrap();
+        assert_eq!(decoded.format, "text");
+    }
+
+    #[test]
+    fn registered_decoders_run_first() {
+        // The registry is global, so these decoders only handle data that no other test uses.
+        const OVERRIDE: &[u8] = b"registry test: override";
+        const ORDER: &[u8] = b"registry test: order";
+        register_decoder(|name: Option<&str>, data: &[u8]| {
+            (name == Some(DETECTOR_METADATA_NAME) && data == OVERRIDE)
+                .then(|| DecodedMetadata::new("override", &"registered"))
+        });
+        register_decoder(|_: Option<&str>, data: &[u8]| {
+            (data == ORDER).then(|| DecodedMetadata::new("first", &1))
+        });
+        register_decoder(|_: Option<&str>, data: &[u8]| {
+            (data == ORDER || data == OVERRIDE).then(|| DecodedMetadata::new("second", &2))
+        });
+
+        // Runs before the built-in decoder, which would fail on this data.
+        let decoded = decode_custom_metadata(Some(DETECTOR_METADATA_NAME), OVERRIDE).unwrap();
+        assert_eq!(decoded.format, "override");
+        // Registered decoders run in the order of registration.
+        assert_eq!(decode_custom_metadata(None, ORDER).unwrap().format, "first");
+        // Decoders returning `None` pass the entry on.
+        assert_eq!(
+            decode_custom_metadata(None, OVERRIDE).unwrap().format,
+            "second"
+        );
+    }
+}
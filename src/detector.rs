@@ -0,0 +1,219 @@
+//! Owned mirror of the types in `object_detector_metadata_schema.fbs`.
+
+use anyhow::{bail, ensure, Context};
+use flatbuffers::FlatBufferBuilder;
+use serde::Serialize;
+
+use crate::object_detector::mediapipe::tasks as fb;
+use crate::{write_metadata, ModelInfo};
+
+/// Name of the [`CustomMetadata`][crate::CustomMetadata] entry that holds an
+/// [`ObjectDetectorOptions`] flatbuffer.
+pub const DETECTOR_METADATA_NAME: &str = "DETECTOR_METADATA";
+
+/// Options of a MediaPipe object detector, stored as `DETECTOR_METADATA` custom metadata.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ObjectDetectorOptions {
+    pub min_parser_version: Option<String>,
+    pub ssd_anchors_options: Option<SsdAnchorsOptions>,
+    pub tensors_decoding_options: Option<TensorsDecodingOptions>,
+}
+
+impl ObjectDetectorOptions {
+    /// Parses a serialized `ObjectDetectorOptions` flatbuffer.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let options = flatbuffers::root::<fb::ObjectDetectorOptions>(bytes)?;
+        Ok(options.into())
+    }
+
+    /// Decodes the `DETECTOR_METADATA` custom metadata of subgraph `subgraph`.
+    pub fn from_model(meta: &ModelInfo, subgraph: usize) -> anyhow::Result<Self> {
+        let Some(sub) = meta.subgraph_metadata.get(subgraph) else {
+            bail!("model has no metadata for subgraph {subgraph}");
+        };
+        let Some(custom) = sub
+            .custom_metadata
+            .iter()
+            .find(|custom| custom.name.as_deref() == Some(DETECTOR_METADATA_NAME))
+        else {
+            bail!("subgraph {subgraph} has no `{DETECTOR_METADATA_NAME}` custom metadata");
+        };
+        Self::from_bytes(&custom.data)
+            .with_context(|| format!("failed to decode `{DETECTOR_METADATA_NAME}`"))
+    }
+
+    /// Returns the fixed anchors, if there are any.
+    pub fn anchors(&self) -> Option<&[FixedAnchor]> {
+        let ssd = self.ssd_anchors_options.as_ref()?;
+        Some(&ssd.fixed_anchors_schema.as_ref()?.anchors)
+    }
+
+    /// Serializes the options as an `ObjectDetectorOptions` flatbuffer, suitable for the
+    /// `DETECTOR_METADATA` custom metadata.
+    pub fn to_flatbuffer(&self) -> Vec<u8> {
+        let mut fbb = FlatBufferBuilder::new();
+        let min_parser_version = self
+            .min_parser_version
+            .as_ref()
+            .map(|v| fbb.create_string(v));
+        let ssd_anchors_options = self.ssd_anchors_options.as_ref().map(|ssd| {
+            let fixed_anchors_schema = ssd.fixed_anchors_schema.as_ref().map(|fixed| {
+                let anchors = fixed
+                    .anchors
+                    .iter()
+                    .map(|anchor| {
+                        let args = fb::FixedAnchorArgs {
+                            x_center: anchor.x_center,
+                            y_center: anchor.y_center,
+                            width: anchor.width,
+                            height: anchor.height,
+                        };
+                        fb::FixedAnchor::create(&mut fbb, &args)
+                    })
+                    .collect::<Vec<_>>();
+                let args = fb::FixedAnchorsSchemaArgs {
+                    anchors: Some(fbb.create_vector(&anchors)),
+                };
+                fb::FixedAnchorsSchema::create(&mut fbb, &args)
+            });
+            let args = fb::SsdAnchorsOptionsArgs {
+                fixed_anchors_schema,
+            };
+            fb::SsdAnchorsOptions::create(&mut fbb, &args)
+        });
+        let tensors_decoding_options = self.tensors_decoding_options.map(|dec| {
+            let args = fb::TensorsDecodingOptionsArgs {
+                num_classes: dec.num_classes,
+                num_boxes: dec.num_boxes,
+                num_coords: dec.num_coords,
+                keypoint_coord_offset: dec.keypoint_coord_offset,
+                num_keypoints: dec.num_keypoints,
+                num_values_per_keypoint: dec.num_values_per_keypoint,
+                x_scale: dec.x_scale,
+                y_scale: dec.y_scale,
+                w_scale: dec.w_scale,
+                h_scale: dec.h_scale,
+                apply_exponential_on_box_size: dec.apply_exponential_on_box_size,
+                sigmoid_score: dec.sigmoid_score,
+            };
+            fb::TensorsDecodingOptions::create(&mut fbb, &args)
+        });
+        let args = fb::ObjectDetectorOptionsArgs {
+            min_parser_version,
+            ssd_anchors_options,
+            tensors_decoding_options,
+        };
+        let root = fb::ObjectDetectorOptions::create(&mut fbb, &args);
+        fb::finish_object_detector_options_buffer(&mut fbb, root);
+        fbb.finished_data().to_vec()
+    }
+}
+
+/// Replaces the fixed anchors in the `DETECTOR_METADATA` of subgraph `subgraph` of a model.
+///
+/// `tflite` is the content of a `.tflite` file. The other detector options, the rest of the
+/// metadata and the associated files are kept. Fails if the number of anchors doesn't match the
+/// `num_boxes` of the decoding options, since it is fixed by the shape of the model's outputs.
+pub fn write_anchors(
+    tflite: &[u8],
+    subgraph: usize,
+    anchors: Vec<FixedAnchor>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut meta = ModelInfo::from_bytes(tflite)?;
+    let mut options = ObjectDetectorOptions::from_model(&meta, subgraph)?;
+    if let Some(decoding) = &options.tensors_decoding_options {
+        ensure!(
+            anchors.len() == decoding.num_boxes as usize,
+            "{} anchors given, but the model predicts {} boxes",
+            anchors.len(),
+            decoding.num_boxes,
+        );
+    }
+    options
+        .ssd_anchors_options
+        .get_or_insert(SsdAnchorsOptions {
+            fixed_anchors_schema: None,
+        })
+        .fixed_anchors_schema = Some(FixedAnchorsSchema { anchors });
+
+    let custom = meta.subgraph_metadata[subgraph]
+        .custom_metadata
+        .iter_mut()
+        .find(|custom| custom.name.as_deref() == Some(DETECTOR_METADATA_NAME))
+        .expect("checked by ObjectDetectorOptions::from_model");
+    custom.data = options.to_flatbuffer();
+    write_metadata(tflite, &meta, &[])
+}
+
+impl From<fb::ObjectDetectorOptions<'_>> for ObjectDetectorOptions {
+    fn from(options: fb::ObjectDetectorOptions<'_>) -> Self {
+        Self {
+            min_parser_version: options.min_parser_version().map(Into::into),
+            ssd_anchors_options: options.ssd_anchors_options().map(|ssd| SsdAnchorsOptions {
+                fixed_anchors_schema: ssd.fixed_anchors_schema().map(|fixed| FixedAnchorsSchema {
+                    anchors: fixed.anchors().map_or(Vec::new(), |anchors| {
+                        anchors
+                            .iter()
+                            .map(|anchor| FixedAnchor {
+                                x_center: anchor.x_center(),
+                                y_center: anchor.y_center(),
+                                width: anchor.width(),
+                                height: anchor.height(),
+                            })
+                            .collect()
+                    }),
+                }),
+            }),
+            tensors_decoding_options: options.tensors_decoding_options().map(|dec| {
+                TensorsDecodingOptions {
+                    num_classes: dec.num_classes(),
+                    num_boxes: dec.num_boxes(),
+                    num_coords: dec.num_coords(),
+                    keypoint_coord_offset: dec.keypoint_coord_offset(),
+                    num_keypoints: dec.num_keypoints(),
+                    num_values_per_keypoint: dec.num_values_per_keypoint(),
+                    x_scale: dec.x_scale(),
+                    y_scale: dec.y_scale(),
+                    w_scale: dec.w_scale(),
+                    h_scale: dec.h_scale(),
+                    apply_exponential_on_box_size: dec.apply_exponential_on_box_size(),
+                    sigmoid_score: dec.sigmoid_score(),
+                }
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SsdAnchorsOptions {
+    pub fixed_anchors_schema: Option<FixedAnchorsSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FixedAnchorsSchema {
+    pub anchors: Vec<FixedAnchor>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FixedAnchor {
+    pub x_center: f32,
+    pub y_center: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TensorsDecodingOptions {
+    pub num_classes: i32,
+    pub num_boxes: i32,
+    pub num_coords: i32,
+    pub keypoint_coord_offset: i32,
+    pub num_keypoints: i32,
+    pub num_values_per_keypoint: i32,
+    pub x_scale: f32,
+    pub y_scale: f32,
+    pub w_scale: f32,
+    pub h_scale: f32,
+    pub apply_exponential_on_box_size: bool,
+    pub sigmoid_score: bool,
+}
@@ -0,0 +1,604 @@
+//! Semantic comparison of the metadata of two models.
+
+use core::fmt;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{ArchiveEntry, ModelInfo};
+
+/// A single difference found by [`diff`] or [`diff_archives`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    /// Location of the difference, e.g. `subgraph_metadata[0].input_tensor_metadata["image"]`.
+    ///
+    /// Lists of named entries are indexed by name, other lists by position.
+    pub path: String,
+    #[serde(flatten)]
+    pub kind: ChangeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// The entry only exists in the second model.
+    Added {
+        new: Value,
+    },
+    /// The entry only exists in the first model.
+    Removed {
+        old: Value,
+    },
+    Changed {
+        old: Value,
+        new: Value,
+    },
+    /// A list of `FixedAnchor`s changed.
+    Anchors {
+        old_count: usize,
+        new_count: usize,
+        /// Number of anchors (among the first `min(old_count, new_count)`) with a coordinate that
+        /// differs by more than the tolerance.
+        differing: usize,
+        /// Index of the first differing anchor.
+        first_differing: Option<usize>,
+        /// Largest difference of a coordinate.
+        max_difference: f64,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ChangeKind::Added { new } => write!(f, "+ {}: {}", self.path, Short(new)),
+            ChangeKind::Removed { old } => write!(f, "- {}: {}", self.path, Short(old)),
+            ChangeKind::Changed { old, new } => {
+                write!(f, "~ {}: {} -> {}", self.path, Short(old), Short(new))
+            }
+            ChangeKind::Anchors {
+                old_count,
+                new_count,
+                differing,
+                first_differing,
+                max_difference,
+            } => {
+                write!(f, "~ {}:", self.path)?;
+                if old_count != new_count {
+                    write!(f, " {old_count} -> {new_count} anchors,")?;
+                }
+                write!(
+                    f,
+                    " {differing} of {} anchor(s) differ",
+                    old_count.min(new_count)
+                )?;
+                if let Some(first) = first_differing {
+                    write!(
+                        f,
+                        " (first at index {first}, max difference {max_difference})"
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Compact JSON rendering of a value, truncated for display.
+struct Short<'a>(&'a Value);
+
+impl fmt::Display for Short<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MAX_LEN: usize = 100;
+
+        let s = self.0.to_string();
+        match s.char_indices().nth(MAX_LEN) {
+            Some((end, _)) => write!(f, "{}...", &s[..end]),
+            None => f.write_str(&s),
+        }
+    }
+}
+
+/// Compares the metadata of two models field by field.
+///
+/// Numbers are considered equal if they differ by at most `tolerance`. Custom metadata in a known
+/// format (like `DETECTOR_METADATA`) is compared in its decoded form, and anchor lists are
+/// summarized as a single [`ChangeKind::Anchors`].
+pub fn diff(old: &ModelInfo, new: &ModelInfo, tolerance: f64) -> Vec<Change> {
+    let old = serde_json::to_value(old).expect("metadata is always serializable");
+    let new = serde_json::to_value(new).expect("metadata is always serializable");
+    let mut differ = Differ {
+        tolerance,
+        changes: Vec::new(),
+    };
+    differ.value(String::new(), &old, &new);
+    differ.changes
+}
+
+/// Compares the contents of two associated files archives by file name, size and CRC-32.
+pub fn diff_archives(old: &[ArchiveEntry], new: &[ArchiveEntry]) -> Vec<Change> {
+    let summary = |entry: &ArchiveEntry| {
+        json!({
+            "size": entry.size,
+            "crc32": format!("{:08x}", entry.crc32),
+        })
+    };
+    let mut changes = Vec::new();
+    for entry in old {
+        let path = format!("archive[{:?}]", entry.name);
+        match new.iter().find(|e| e.name == entry.name) {
+            None => changes.push(Change {
+                path,
+                kind: ChangeKind::Removed {
+                    old: summary(entry),
+                },
+            }),
+            Some(other) if (other.size, other.crc32) != (entry.size, entry.crc32) => {
+                changes.push(Change {
+                    path,
+                    kind: ChangeKind::Changed {
+                        old: summary(entry),
+                        new: summary(other),
+                    },
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for entry in new {
+        if !old.iter().any(|e| e.name == entry.name) {
+            changes.push(Change {
+                path: format!("archive[{:?}]", entry.name),
+                kind: ChangeKind::Added {
+                    new: summary(entry),
+                },
+            });
+        }
+    }
+    changes
+}
+
+struct Differ {
+    tolerance: f64,
+    changes: Vec<Change>,
+}
+
+impl Differ {
+    fn push(&mut self, path: String, kind: ChangeKind) {
+        self.changes.push(Change { path, kind });
+    }
+
+    fn value(&mut self, path: String, old: &Value, new: &Value) {
+        match (old, new) {
+            (Value::Object(old), Value::Object(new)) => self.object(path, old, new),
+            (Value::Array(old), Value::Array(new)) => self.array(path, old, new),
+            (Value::Number(a), Value::Number(b)) if self.close(a.as_f64(), b.as_f64()) => {}
+            _ if old != new => self.changed(path, old, new),
+            _ => {}
+        }
+    }
+
+    /// Returns whether two numbers differ by at most the tolerance.
+    fn close(&self, a: Option<f64>, b: Option<f64>) -> bool {
+        a.zip(b)
+            .is_some_and(|(a, b)| (a - b).abs() <= self.tolerance)
+    }
+
+    fn changed(&mut self, path: String, old: &Value, new: &Value) {
+        let kind = match (old, new) {
+            (old, Value::Null) => ChangeKind::Removed { old: old.clone() },
+            (Value::Null, new) => ChangeKind::Added { new: new.clone() },
+            _ => ChangeKind::Changed {
+                old: old.clone(),
+                new: new.clone(),
+            },
+        };
+        self.push(path, kind);
+    }
+
+    fn object(&mut self, path: String, old: &Map<String, Value>, new: &Map<String, Value>) {
+        let decoded = old.contains_key("decoded") && new.contains_key("decoded");
+        let mut keys = old.keys().collect::<Vec<_>>();
+        keys.extend(new.keys().filter(|key| !old.contains_key(*key)));
+        for key in keys {
+            let path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+            let (old, new) = (
+                old.get(key).unwrap_or(&Value::Null),
+                new.get(key).unwrap_or(&Value::Null),
+            );
+            match &**key {
+                // The raw bytes of custom metadata are only compared if they can't be decoded.
+                "data" if decoded => {}
+                "data" if old != new => {
+                    let size = |v: &Value| match v {
+                        Value::Array(bytes) => json!(format!("<{} bytes>", bytes.len())),
+                        v => v.clone(),
+                    };
+                    self.changed(path, &size(old), &size(new));
+                }
+                "anchors" => self.anchors(path, old, new),
+                _ => self.value(path, old, new),
+            }
+        }
+    }
+
+    fn array(&mut self, path: String, old: &[Value], new: &[Value]) {
+        // Lists of numbers (normalization constants, stats, ...) are reported as a whole.
+        if old.iter().chain(new).all(Value::is_number) {
+            let equal = old.len() == new.len()
+                && old
+                    .iter()
+                    .zip(new)
+                    .all(|(a, b)| self.close(a.as_f64(), b.as_f64()));
+            if !equal {
+                self.changed(path, &Value::from(old), &Value::from(new));
+            }
+            return;
+        }
+
+        match (names(old), names(new)) {
+            (Some(old_names), Some(new_names)) => {
+                for (name, old) in old_names.iter().zip(old) {
+                    let path = format!("{path}[{name:?}]");
+                    match new_names.iter().position(|n| n == name) {
+                        Some(i) => self.value(path, old, &new[i]),
+                        None => self.push(path, ChangeKind::Removed { old: old.clone() }),
+                    }
+                }
+                for (name, new) in new_names.iter().zip(new) {
+                    if !old_names.contains(name) {
+                        let path = format!("{path}[{name:?}]");
+                        self.push(path, ChangeKind::Added { new: new.clone() });
+                    }
+                }
+            }
+            _ => {
+                for (i, (old, new)) in old.iter().zip(new).enumerate() {
+                    self.value(format!("{path}[{i}]"), old, new);
+                }
+                for (i, old) in old.iter().enumerate().skip(new.len()) {
+                    self.push(
+                        format!("{path}[{i}]"),
+                        ChangeKind::Removed { old: old.clone() },
+                    );
+                }
+                for (i, new) in new.iter().enumerate().skip(old.len()) {
+                    self.push(
+                        format!("{path}[{i}]"),
+                        ChangeKind::Added { new: new.clone() },
+                    );
+                }
+            }
+        }
+    }
+
+    fn anchors(&mut self, path: String, old: &Value, new: &Value) {
+        let (Some(old), Some(new)) = (old.as_array(), new.as_array()) else {
+            return self.value(path, old, new);
+        };
+
+        let mut differing = 0;
+        let mut first_differing = None;
+        let mut max_difference = 0.0f64;
+        for (i, (a, b)) in old.iter().zip(new).enumerate() {
+            let mut differs = false;
+            for key in ["x_center", "y_center", "width", "height"] {
+                let coord = |v: &Value| v.get(key).and_then(Value::as_f64);
+                let (a, b) = (coord(a), coord(b));
+                if !self.close(a, b) {
+                    differs = true;
+                    let difference = a.zip(b).map_or(f64::NAN, |(a, b)| (a - b).abs());
+                    max_difference = max_difference.max(difference);
+                }
+            }
+            if differs {
+                differing += 1;
+                first_differing.get_or_insert(i);
+            }
+        }
+
+        if differing > 0 || old.len() != new.len() {
+            self.push(
+                path,
+                ChangeKind::Anchors {
+                    old_count: old.len(),
+                    new_count: new.len(),
+                    differing,
+                    first_differing,
+                    max_difference,
+                },
+            );
+        }
+    }
+}
+
+/// Returns the `name`s of a list of objects, if they all have a distinct one.
+fn names(list: &[Value]) -> Option<Vec<&str>> {
+    let names = list
+        .iter()
+        .map(|v| v.get("name").and_then(Value::as_str))
+        .collect::<Option<Vec<_>>>()?;
+    let distinct = names
+        .iter()
+        .enumerate()
+        .all(|(i, name)| !names[..i].contains(name));
+    distinct.then_some(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CustomMetadata, FixedAnchor, FixedAnchorsSchema, NormalizationOptions,
+        ObjectDetectorOptions, ProcessUnit, ProcessUnitOptions, SsdAnchorsOptions,
+        SubGraphMetadata, TensorMetadata, TensorsDecodingOptions, DETECTOR_METADATA_NAME,
+    };
+
+    fn tensor(name: &str, mean: f32) -> TensorMetadata {
+        TensorMetadata {
+            name: Some(name.into()),
+            process_units: vec![ProcessUnit {
+                options: Some(ProcessUnitOptions::NormalizationOptions(
+                    NormalizationOptions {
+                        mean: vec![mean],
+                        std: vec![127.5],
+                    },
+                )),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn detector(num_classes: i32, anchors: &[f32]) -> CustomMetadata {
+        let options = ObjectDetectorOptions {
+            min_parser_version: None,
+            ssd_anchors_options: Some(SsdAnchorsOptions {
+                fixed_anchors_schema: Some(FixedAnchorsSchema {
+                    anchors: anchors
+                        .iter()
+                        .map(|&center| FixedAnchor {
+                            x_center: center,
+                            y_center: center,
+                            width: 1.0,
+                            height: 1.0,
+                        })
+                        .collect(),
+                }),
+            }),
+            tensors_decoding_options: Some(TensorsDecodingOptions {
+                num_classes,
+                num_boxes: anchors.len() as i32,
+                num_coords: 4,
+                keypoint_coord_offset: 0,
+                num_keypoints: 0,
+                num_values_per_keypoint: 0,
+                x_scale: 10.0,
+                y_scale: 10.0,
+                w_scale: 5.0,
+                h_scale: 5.0,
+                apply_exponential_on_box_size: false,
+                sigmoid_score: true,
+            }),
+        };
+        CustomMetadata {
+            name: Some(DETECTOR_METADATA_NAME.into()),
+            data: options.to_flatbuffer(),
+        }
+    }
+
+    fn model() -> ModelInfo {
+        ModelInfo {
+            name: Some("detector".into()),
+            subgraph_metadata: vec![SubGraphMetadata {
+                input_tensor_metadata: vec![tensor("image", 127.5), tensor("mask", 0.0)],
+                custom_metadata: vec![detector(90, &[0.25, 0.75])],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn entry(name: &str, size: u64, crc32: u32) -> ArchiveEntry {
+        ArchiveEntry {
+            name: name.into(),
+            size,
+            compressed_size: size,
+            crc32,
+        }
+    }
+
+    #[test]
+    fn identical() {
+        assert_eq!(diff(&model(), &model(), 0.0), []);
+    }
+
+    #[test]
+    fn added_removed_changed() {
+        let mut old = model();
+        old.version = Some("v1".into());
+        let mut new = model();
+        new.name = Some("ssd".into());
+        new.description = Some("SSD".into());
+        assert_eq!(
+            diff(&old, &new, 0.0),
+            [
+                Change {
+                    path: "description".into(),
+                    kind: ChangeKind::Added { new: json!("SSD") },
+                },
+                Change {
+                    path: "name".into(),
+                    kind: ChangeKind::Changed {
+                        old: json!("detector"),
+                        new: json!("ssd"),
+                    },
+                },
+                Change {
+                    path: "version".into(),
+                    kind: ChangeKind::Removed { old: json!("v1") },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_matched_by_name() {
+        let old = model();
+        let mut new = model();
+        new.subgraph_metadata[0].input_tensor_metadata.reverse();
+        assert_eq!(diff(&old, &new, 0.0), []);
+
+        new.subgraph_metadata[0].input_tensor_metadata[0].name = Some("depth".into());
+        let changes = diff(&old, &new, 0.0);
+        let paths = changes
+            .iter()
+            .map(|change| (&*change.path, &change.kind))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            paths[..],
+            [
+                (
+                    r#"subgraph_metadata[0].input_tensor_metadata["mask"]"#,
+                    ChangeKind::Removed { .. }
+                ),
+                (
+                    r#"subgraph_metadata[0].input_tensor_metadata["depth"]"#,
+                    ChangeKind::Added { .. }
+                ),
+            ]
+        ));
+    }
+
+    #[test]
+    fn tolerance() {
+        let old = model();
+        let mut new = model();
+        new.subgraph_metadata[0].input_tensor_metadata[0] = tensor("image", 127.625);
+        assert_eq!(diff(&old, &new, 0.125), []);
+        assert_eq!(
+            diff(&old, &new, 0.0625),
+            [Change {
+                path: r#"subgraph_metadata[0].input_tensor_metadata["image"].process_units[0].options.mean"#
+                    .into(),
+                kind: ChangeKind::Changed {
+                    old: json!([127.5]),
+                    new: json!([127.625]),
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn decoded_detector_metadata() {
+        let old = model();
+        let mut new = model();
+        new.subgraph_metadata[0].custom_metadata = vec![detector(91, &[0.25, 0.75])];
+        // Only the decoded field is reported, not the raw bytes.
+        assert_eq!(
+            diff(&old, &new, 0.0),
+            [Change {
+                path: r#"subgraph_metadata[0].custom_metadata["DETECTOR_METADATA"].decoded.value.tensors_decoding_options.num_classes"#
+                    .into(),
+                kind: ChangeKind::Changed {
+                    old: json!(90),
+                    new: json!(91),
+                },
+            }]
+        );
+
+        // Entries that fail to decode are compared by their bytes.
+        let mut old = model();
+        old.subgraph_metadata[0].custom_metadata = vec![CustomMetadata {
+            name: Some(DETECTOR_METADATA_NAME.into()),
+            data: vec![0xff; 3],
+        }];
+        let mut new = old.clone();
+        new.subgraph_metadata[0].custom_metadata[0].data = vec![0xff; 4];
+        assert!(diff(&old, &new, 0.0).contains(&Change {
+            path: r#"subgraph_metadata[0].custom_metadata["DETECTOR_METADATA"].data"#.into(),
+            kind: ChangeKind::Changed {
+                old: json!("<3 bytes>"),
+                new: json!("<4 bytes>"),
+            },
+        }));
+    }
+
+    #[test]
+    fn anchors() {
+        const PATH: &str = r#"subgraph_metadata[0].custom_metadata["DETECTOR_METADATA"].decoded.value.ssd_anchors_options.fixed_anchors_schema.anchors"#;
+        let old = model();
+        let with_anchors = |anchors: &[f32]| {
+            let mut new = model();
+            new.subgraph_metadata[0].custom_metadata = vec![detector(90, anchors)];
+            new
+        };
+
+        // Just inside and just outside the tolerance.
+        let new = with_anchors(&[0.25, 0.8125]);
+        assert_eq!(diff(&old, &new, 0.0625), []);
+        assert_eq!(
+            diff(&old, &new, 0.03125),
+            [Change {
+                path: PATH.into(),
+                kind: ChangeKind::Anchors {
+                    old_count: 2,
+                    new_count: 2,
+                    differing: 1,
+                    first_differing: Some(1),
+                    max_difference: 0.0625,
+                },
+            }]
+        );
+
+        let new = with_anchors(&[0.25, 0.75, 0.5]);
+        let changes = diff(&old, &new, 0.0);
+        assert!(changes.contains(&Change {
+            path: PATH.into(),
+            kind: ChangeKind::Anchors {
+                old_count: 2,
+                new_count: 3,
+                differing: 0,
+                first_differing: None,
+                max_difference: 0.0,
+            },
+        }));
+        assert_eq!(
+            changes[0].to_string(),
+            format!("~ {PATH}: 2 -> 3 anchors, 0 of 2 anchor(s) differ")
+        );
+    }
+
+    #[test]
+    fn archives() {
+        let old = [entry("labels.txt", 10, 1), entry("vocab.txt", 20, 2)];
+        let new = [entry("labels.txt", 10, 3), entry("scores.csv", 5, 4)];
+        assert_eq!(diff_archives(&old, &old), []);
+        assert_eq!(
+            diff_archives(&old, &new),
+            [
+                Change {
+                    path: r#"archive["labels.txt"]"#.into(),
+                    kind: ChangeKind::Changed {
+                        old: json!({ "size": 10, "crc32": "00000001" }),
+                        new: json!({ "size": 10, "crc32": "00000003" }),
+                    },
+                },
+                Change {
+                    path: r#"archive["vocab.txt"]"#.into(),
+                    kind: ChangeKind::Removed {
+                        old: json!({ "size": 20, "crc32": "00000002" }),
+                    },
+                },
+                Change {
+                    path: r#"archive["scores.csv"]"#.into(),
+                    kind: ChangeKind::Added {
+                        new: json!({ "size": 5, "crc32": "00000004" }),
+                    },
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! Reading model files without loading them into memory.
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    ops::Deref,
+    path::Path,
+};
+
+use anyhow::Context;
+use memmap2::Mmap;
+
+/// The contents of a model file.
+///
+/// Regular files are memory-mapped, so that only the parts of the model that are actually
+/// accessed (usually the flatbuffer tables and the metadata buffer, but not the weights) are
+/// loaded. Other inputs like pipes are read into memory.
+pub struct ModelFile {
+    data: Data,
+}
+
+enum Data {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+impl ModelFile {
+    /// Opens the model file at `path`, or reads standard input if `path` is `-`.
+    ///
+    /// The file must not be modified while the returned value is alive.
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if path == Path::new("-") {
+            return Self::read(io::stdin().lock()).context("failed to read standard input");
+        }
+
+        let open = || -> io::Result<Self> {
+            let file = File::open(path)?;
+            let metadata = file.metadata()?;
+            // Mapping an empty file fails on some platforms.
+            if !metadata.is_file() || metadata.len() == 0 {
+                return Self::read(file);
+            }
+            // SAFETY: the caller promises not to modify the file while it is mapped. Like other
+            // tools that map their inputs, we can't protect against other processes doing so.
+            match unsafe { Mmap::map(&file) } {
+                Ok(mmap) => Ok(Self {
+                    data: Data::Mapped(mmap),
+                }),
+                // Some file systems don't support mapping.
+                Err(_) => Self::read(file),
+            }
+        };
+        open().with_context(|| format!("failed to read `{}`", path.display()))
+    }
+
+    fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(Self {
+            data: Data::Read(data),
+        })
+    }
+
+    /// Returns whether the file is memory-mapped.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.data, Data::Mapped(_))
+    }
+}
+
+impl Deref for ModelFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.data {
+            Data::Mapped(mmap) => mmap,
+            Data::Read(data) => data,
+        }
+    }
+}
@@ -0,0 +1,513 @@
+//! Owned view of the model graph described by `schema_v3c.fbs`.
+
+use core::fmt;
+
+use anyhow::Context;
+use flatbuffers::{ForwardsUOffset, Vector};
+use serde::Serialize;
+
+use crate::v3c::tflite as fb;
+use crate::{buffer_data, custom_options};
+
+pub use crate::v3c::tflite::{BuiltinOperator, TensorType};
+
+serde_by_name!(BuiltinOperator, TensorType);
+
+/// The subgraphs and operator codes of a `.tflite` model.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelGraph {
+    pub version: u32,
+    pub description: Option<String>,
+    pub operator_codes: Vec<OperatorCode>,
+    pub subgraphs: Vec<SubGraph>,
+    pub signature_defs: Vec<SignatureDef>,
+}
+
+impl ModelGraph {
+    /// Reads the graph from the contents of a `.tflite` file.
+    ///
+    /// Fails if a tensor or operator references data outside of the file.
+    pub fn from_bytes(tflite: &[u8]) -> anyhow::Result<Self> {
+        let model = flatbuffers::root::<fb::Model>(tflite)?;
+
+        let operator_codes = model.operator_codes().map_or(Vec::new(), |codes| {
+            codes
+                .iter()
+                .map(|code| OperatorCode {
+                    deprecated_builtin_code: code.deprecated_builtin_code(),
+                    builtin_code: code.builtin_code(),
+                    custom_code: code.custom_code().map(Into::into),
+                    version: code.version(),
+                })
+                .collect()
+        });
+
+        let mut subgraphs = Vec::new();
+        for (index, sub) in model.subgraphs().into_iter().flatten().enumerate() {
+            let mut tensors = Vec::new();
+            for tensor in sub.tensors().into_iter().flatten() {
+                // Buffer 0 is the empty sentinel buffer referenced by all non-constant tensors.
+                let is_constant = tensor.buffer() != 0
+                    && !buffer_data(tflite, &model, tensor.buffer())
+                        .with_context(|| {
+                            format!(
+                                "tensor {:?} of subgraph {index} has invalid data",
+                                tensor.name().unwrap_or("<unnamed>"),
+                            )
+                        })?
+                        .is_empty();
+                tensors.push(Tensor {
+                    name: tensor.name().map(Into::into),
+                    shape: ints(tensor.shape()),
+                    shape_signature: tensor.shape_signature().map(|v| v.iter().collect()),
+                    type_: tensor.type_(),
+                    buffer: tensor.buffer(),
+                    is_constant,
+                    is_variable: tensor.is_variable(),
+                    quantization: tensor.quantization().and_then(Quantization::new),
+                    sparsity: tensor.sparsity().map(Sparsity::new),
+                });
+            }
+
+            let mut operators = Vec::new();
+            for (i, op) in sub.operators().into_iter().flatten().enumerate() {
+                let custom_options = custom_options(tflite, &op).with_context(|| {
+                    format!("operator {i} of subgraph {index} has invalid custom options")
+                })?;
+                operators.push(Operator {
+                    opcode_index: op.opcode_index(),
+                    inputs: ints(op.inputs()),
+                    outputs: ints(op.outputs()),
+                    custom_options_size: custom_options.len(),
+                });
+            }
+
+            subgraphs.push(SubGraph {
+                name: sub.name().map(Into::into),
+                tensors,
+                inputs: ints(sub.inputs()),
+                outputs: ints(sub.outputs()),
+                operators,
+            });
+        }
+
+        let tensor_maps = |maps: Option<Vector<'_, ForwardsUOffset<fb::TensorMap<'_>>>>| {
+            maps.into_iter()
+                .flatten()
+                .map(|map| TensorMap {
+                    name: map.name().map(Into::into),
+                    tensor_index: map.tensor_index(),
+                })
+                .collect()
+        };
+        let signature_defs = model.signature_defs().map_or(Vec::new(), |defs| {
+            defs.iter()
+                .map(|def| SignatureDef {
+                    signature_key: def.signature_key().map(Into::into),
+                    subgraph_index: def.subgraph_index(),
+                    inputs: tensor_maps(def.inputs()),
+                    outputs: tensor_maps(def.outputs()),
+                })
+                .collect()
+        });
+
+        Ok(Self {
+            version: model.version(),
+            description: model.description().map(Into::into),
+            operator_codes,
+            subgraphs,
+            signature_defs,
+        })
+    }
+
+    /// Returns the [`OperatorCode`] used by `op`.
+    pub fn operator_code(&self, op: &Operator) -> Option<&OperatorCode> {
+        self.operator_codes.get(op.opcode_index as usize)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OperatorCode {
+    /// Builtin operator code used by schema versions before 3a (only covers codes up to 127).
+    pub deprecated_builtin_code: i8,
+    pub builtin_code: BuiltinOperator,
+    pub custom_code: Option<String>,
+    pub version: i32,
+}
+
+impl OperatorCode {
+    /// Returns the builtin operator, taking `deprecated_builtin_code` into account.
+    ///
+    /// Like the TFLite runtime, this uses the larger of `builtin_code` and
+    /// `deprecated_builtin_code`, since older converters only set the latter.
+    pub fn builtin(&self) -> BuiltinOperator {
+        BuiltinOperator(self.builtin_code.0.max(self.deprecated_builtin_code.into()))
+    }
+
+    /// Returns the name of the operator: the custom code for custom operators, the builtin
+    /// operator name otherwise.
+    pub fn name(&self) -> String {
+        let builtin = self.builtin();
+        match (builtin, &self.custom_code) {
+            (BuiltinOperator::CUSTOM, Some(custom)) => custom.clone(),
+            _ => match builtin.variant_name() {
+                Some(name) => name.to_string(),
+                None => format!("<builtin {}>", builtin.0),
+            },
+        }
+    }
+}
+
+/// A named entry point of the model, mapping aliases to the tensors of a subgraph.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SignatureDef {
+    pub signature_key: Option<String>,
+    pub subgraph_index: u32,
+    pub inputs: Vec<TensorMap>,
+    pub outputs: Vec<TensorMap>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TensorMap {
+    /// The alias of the tensor in the signature.
+    pub name: Option<String>,
+    /// Index into [`SubGraph::tensors`].
+    pub tensor_index: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubGraph {
+    pub name: Option<String>,
+    pub tensors: Vec<Tensor>,
+    pub inputs: Vec<i32>,
+    pub outputs: Vec<i32>,
+    /// Operators in execution order.
+    pub operators: Vec<Operator>,
+}
+
+impl SubGraph {
+    /// Returns the tensor at `index`, or [`None`] if `index` is out of range or negative (which
+    /// denotes an omitted optional operator input).
+    pub fn tensor(&self, index: i32) -> Option<&Tensor> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.tensors.get(index))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Tensor {
+    pub name: Option<String>,
+    pub shape: Vec<i32>,
+    /// Shape with `-1` for dynamic dimensions, if the model has any.
+    pub shape_signature: Option<Vec<i32>>,
+    #[serde(rename = "type")]
+    pub type_: TensorType,
+    pub buffer: u32,
+    /// Whether the tensor's [`buffer`][Self::buffer] holds constant data.
+    pub is_constant: bool,
+    pub is_variable: bool,
+    pub quantization: Option<Quantization>,
+    pub sparsity: Option<Sparsity>,
+}
+
+/// Quantization parameters of a tensor.
+///
+/// Tensors with a [`scale`][Self::scale] use affine quantization:
+/// `real = scale * (quantized - zero_point)`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Quantization {
+    /// One value per tensor, or per slice along [`quantized_dimension`][Self::quantized_dimension].
+    pub scale: Vec<f32>,
+    pub zero_point: Vec<i64>,
+    /// Range of the real values, recorded by some converters.
+    pub min: Vec<f32>,
+    pub max: Vec<f32>,
+    pub quantized_dimension: i32,
+    /// Data of a `CustomQuantization`, if the tensor uses one.
+    pub custom: Option<Vec<u8>>,
+}
+
+impl Quantization {
+    /// Returns [`None`] if all parameters are empty.
+    fn new(params: fb::QuantizationParameters<'_>) -> Option<Self> {
+        let quant = Self {
+            scale: params.scale().map_or(Vec::new(), |v| v.iter().collect()),
+            zero_point: params
+                .zero_point()
+                .map_or(Vec::new(), |v| v.iter().collect()),
+            min: params.min().map_or(Vec::new(), |v| v.iter().collect()),
+            max: params.max().map_or(Vec::new(), |v| v.iter().collect()),
+            quantized_dimension: params.quantized_dimension(),
+            custom: params
+                .details_as_custom_quantization()
+                .map(|custom| custom.custom().map_or(Vec::new(), |v| v.bytes().to_vec())),
+        };
+        let empty = quant.scale.is_empty()
+            && quant.zero_point.is_empty()
+            && quant.min.is_empty()
+            && quant.max.is_empty()
+            && quant.custom.is_none();
+        (!empty).then_some(quant)
+    }
+
+    /// Returns whether the tensor is quantized, with affine or custom quantization.
+    pub fn is_quantized(&self) -> bool {
+        !self.scale.is_empty() || self.custom.is_some()
+    }
+
+    /// Returns whether the tensor has a different scale or zero point per slice.
+    pub fn is_per_channel(&self) -> bool {
+        self.scale.len() > 1 || self.zero_point.len() > 1
+    }
+
+    /// Describes the parameters in a single line, summarizing per-channel values by their range.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(custom) = &self.custom {
+            parts.push(format!("custom quantization ({} bytes)", custom.len()));
+        }
+        if self.is_per_channel() {
+            parts.push(format!(
+                "per-channel along dimension {} ({} channels)",
+                self.quantized_dimension,
+                self.scale.len().max(self.zero_point.len()),
+            ));
+        }
+        if !self.scale.is_empty() {
+            parts.push(format!("scale {}", range(&self.scale)));
+        }
+        if !self.zero_point.is_empty() {
+            parts.push(format!("zero point {}", range(&self.zero_point)));
+        }
+        if !self.min.is_empty() || !self.max.is_empty() {
+            parts.push(format!(
+                "range [{}, {}]",
+                range(&self.min),
+                range(&self.max)
+            ));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Formats a single value, or the range of several values.
+fn range<T: PartialOrd + Copy + fmt::Display>(values: &[T]) -> String {
+    let Some(&first) = values.first() else {
+        return "-".into();
+    };
+    let (min, max) = values.iter().fold((first, first), |(min, max), &v| {
+        (if v < min { v } else { min }, if v > max { v } else { max })
+    });
+    if min == max {
+        min.to_string()
+    } else {
+        format!("{min}..{max}")
+    }
+}
+
+/// Sparse storage of a constant tensor (`SparsityParameters`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Sparsity {
+    /// Order in which the dimensions, followed by the block dimensions, are stored.
+    pub traversal_order: Vec<i32>,
+    /// The tensor dimension of each block dimension.
+    pub block_map: Vec<i32>,
+    /// Storage of each dimension, in traversal order.
+    pub dim_metadata: Vec<DimensionMetadata>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "format")]
+pub enum DimensionMetadata {
+    /// All `size` entries of the dimension are stored.
+    #[serde(rename = "DENSE")]
+    Dense { size: i32 },
+    /// Only the entries listed in the indices are stored (compressed sparse row).
+    #[serde(rename = "SPARSE_CSR")]
+    SparseCsr {
+        /// Number of segments, one more than the number of stored entries of the parent dimension.
+        segments: usize,
+        /// Number of stored entries of the dimension.
+        indices: usize,
+    },
+}
+
+impl Sparsity {
+    fn new(params: fb::SparsityParameters<'_>) -> Self {
+        let dim_metadata = params
+            .dim_metadata()
+            .into_iter()
+            .flatten()
+            .map(|dim| match dim.format() {
+                fb::DimensionType::SPARSE_CSR => DimensionMetadata::SparseCsr {
+                    segments: index_vector_len(
+                        dim.array_segments_as_int_32_vector(),
+                        dim.array_segments_as_uint_16_vector(),
+                        dim.array_segments_as_uint_8_vector(),
+                    ),
+                    indices: index_vector_len(
+                        dim.array_indices_as_int_32_vector(),
+                        dim.array_indices_as_uint_16_vector(),
+                        dim.array_indices_as_uint_8_vector(),
+                    ),
+                },
+                _ => DimensionMetadata::Dense {
+                    size: dim.dense_size(),
+                },
+            })
+            .collect();
+        Self {
+            traversal_order: ints(params.traversal_order()),
+            block_map: ints(params.block_map()),
+            dim_metadata,
+        }
+    }
+
+    /// Describes the storage format of a tensor of shape `shape`, e.g.
+    /// `block-sparse 1x4: d0 dense(8), d1 sparse(3), b1 dense(4); 12 of 64 values (18.8%)`.
+    ///
+    /// Tensor dimensions are named `d<n>`, and block dimensions `b<n>` after the tensor dimension
+    /// they divide.
+    pub fn describe(&self, shape: &[i32]) -> String {
+        let Some(rank) = self.traversal_order.len().checked_sub(self.block_map.len()) else {
+            return format!(
+                "invalid sparsity: {} traversal order entries for {} block dimensions",
+                self.traversal_order.len(),
+                self.block_map.len()
+            );
+        };
+        let block_dim = |dim: usize| dim.checked_sub(rank).and_then(|i| self.block_map.get(i));
+
+        let mut stored = 1usize;
+        let mut levels = Vec::new();
+        for (&dim, meta) in self.traversal_order.iter().zip(&self.dim_metadata) {
+            let name = match block_dim(dim as usize) {
+                Some(orig) => format!("b{orig}"),
+                None => format!("d{dim}"),
+            };
+            match meta {
+                DimensionMetadata::Dense { size } => {
+                    stored = stored.saturating_mul(*size as usize);
+                    levels.push(format!("{name} dense({size})"));
+                }
+                DimensionMetadata::SparseCsr { indices, .. } => {
+                    stored = *indices;
+                    levels.push(format!("{name} sparse({indices})"));
+                }
+            }
+        }
+
+        let mut blocks = vec![1; rank];
+        for (&dim, meta) in self.traversal_order.iter().zip(&self.dim_metadata) {
+            if let (Some(&orig), DimensionMetadata::Dense { size }) =
+                (block_dim(dim as usize), meta)
+            {
+                if let Some(block) = blocks.get_mut(orig as usize) {
+                    *block = *size;
+                }
+            }
+        }
+        let kind = if self.block_map.is_empty() {
+            "sparse".to_string()
+        } else {
+            let blocks = blocks.iter().map(i32::to_string).collect::<Vec<_>>();
+            format!("block-sparse {}", blocks.join("x"))
+        };
+
+        // An empty shape is unknown rather than a scalar, which can't be sparse.
+        let total = match shape {
+            [] => 0,
+            _ => shape.iter().map(|&d| d.max(0) as usize).product::<usize>(),
+        };
+        let density = match total {
+            0 => String::new(),
+            _ => format!(
+                "; {stored} of {total} values ({:.1}%)",
+                stored as f64 * 100.0 / total as f64
+            ),
+        };
+        format!("{kind}: {}{density}", levels.join(", "))
+    }
+}
+
+fn index_vector_len(
+    i32s: Option<fb::Int32Vector<'_>>,
+    u16s: Option<fb::Uint16Vector<'_>>,
+    u8s: Option<fb::Uint8Vector<'_>>,
+) -> usize {
+    i32s.and_then(|v| v.values())
+        .map(|v| v.len())
+        .or_else(|| u16s.and_then(|v| v.values()).map(|v| v.len()))
+        .or_else(|| u8s.and_then(|v| v.values()).map(|v| v.len()))
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Operator {
+    /// Index into [`ModelGraph::operator_codes`].
+    pub opcode_index: u32,
+    pub inputs: Vec<i32>,
+    pub outputs: Vec<i32>,
+    /// Size of the operator's custom options in bytes.
+    pub custom_options_size: usize,
+}
+
+fn ints(v: Option<Vector<'_, i32>>) -> Vec<i32> {
+    v.map_or(Vec::new(), |v| v.iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_sparsity() {
+        let sparsity = Sparsity {
+            traversal_order: vec![0, 1],
+            block_map: Vec::new(),
+            dim_metadata: vec![
+                DimensionMetadata::Dense { size: 4 },
+                DimensionMetadata::SparseCsr {
+                    segments: 5,
+                    indices: 6,
+                },
+            ],
+        };
+        assert_eq!(
+            sparsity.describe(&[4, 8]),
+            "sparse: d0 dense(4), d1 sparse(6); 6 of 32 values (18.8%)"
+        );
+
+        let block_sparse = Sparsity {
+            traversal_order: vec![0, 1, 2],
+            block_map: vec![1],
+            dim_metadata: vec![
+                DimensionMetadata::Dense { size: 8 },
+                DimensionMetadata::SparseCsr {
+                    segments: 9,
+                    indices: 3,
+                },
+                DimensionMetadata::Dense { size: 4 },
+            ],
+        };
+        assert_eq!(
+            block_sparse.describe(&[8, 8]),
+            "block-sparse 1x4: d0 dense(8), d1 sparse(3), b1 dense(4); 12 of 64 values (18.8%)"
+        );
+        assert_eq!(
+            block_sparse.describe(&[]),
+            "block-sparse 1x4: d0 dense(8), d1 sparse(3), b1 dense(4)"
+        );
+
+        let invalid = Sparsity {
+            traversal_order: vec![0],
+            block_map: vec![0, 1],
+            dim_metadata: Vec::new(),
+        };
+        assert_eq!(
+            invalid.describe(&[4, 8]),
+            "invalid sparsity: 1 traversal order entries for 2 block dimensions"
+        );
+    }
+}
@@ -0,0 +1,655 @@
+//! Image preprocessing as described by the `ImageProperties` and `NormalizationOptions` of an input
+//! tensor, producing the raw data of the tensor.
+
+use anyhow::{anyhow, bail, ensure, Context};
+
+use crate::{
+    ColorSpaceType, ContentProperties, ModelGraph, ModelInfo, NormalizationOptions,
+    ProcessUnitOptions, Quantization, TensorType,
+};
+
+/// An 8-bit image with interleaved channels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// [`RGB`][ColorSpaceType::RGB] or [`GRAYSCALE`][ColorSpaceType::GRAYSCALE].
+    pub color_space: ColorSpaceType,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(
+        width: u32,
+        height: u32,
+        color_space: ColorSpaceType,
+        pixels: Vec<u8>,
+    ) -> anyhow::Result<Self> {
+        let channels = channels(color_space)?;
+        ensure!(width > 0 && height > 0, "image is empty");
+        let len = sample_count(width, height, channels)?;
+        ensure!(
+            pixels.len() == len,
+            "expected {len} bytes for a {width}x{height} {color_space:?} image, got {}",
+            pixels.len()
+        );
+        Ok(Self {
+            width,
+            height,
+            color_space,
+            pixels,
+        })
+    }
+
+    /// Decodes a binary PPM/PGM (`P6`/`P5`) or PNG image.
+    ///
+    /// Alpha channels are dropped, and 16-bit images are reduced to 8 bits.
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        match data {
+            [b'P', b'5' | b'6', ..] => Self::from_pnm(data).context("invalid PPM/PGM image"),
+            [0x89, b'P', b'N', b'G', ..] => Self::from_png(data).context("invalid PNG image"),
+            _ => bail!("unsupported image format (expected binary PPM/PGM or PNG)"),
+        }
+    }
+
+    fn from_pnm(data: &[u8]) -> anyhow::Result<Self> {
+        let color_space = match data[1] {
+            b'6' => ColorSpaceType::RGB,
+            _ => ColorSpaceType::GRAYSCALE,
+        };
+
+        // The header holds the width, height and maximum value, separated by whitespace and
+        // comments, and is followed by a single whitespace character.
+        let mut pos = 2;
+        let mut fields = [0u32; 3];
+        for field in &mut fields {
+            loop {
+                match data.get(pos) {
+                    Some(b'#') => {
+                        while data.get(pos).is_some_and(|&b| b != b'\n') {
+                            pos += 1;
+                        }
+                    }
+                    Some(b) if b.is_ascii_whitespace() => pos += 1,
+                    _ => break,
+                }
+            }
+            let start = pos;
+            while data.get(pos).is_some_and(u8::is_ascii_digit) {
+                pos += 1;
+            }
+            *field = std::str::from_utf8(&data[start..pos])
+                .unwrap()
+                .parse()
+                .context("invalid header")?;
+        }
+        ensure!(
+            data.get(pos).is_some_and(u8::is_ascii_whitespace),
+            "invalid header"
+        );
+        let [width, height, max] = fields;
+        ensure!((1..=65535).contains(&max), "invalid maximum value {max}");
+
+        let samples = sample_count(width, height, channels(color_space)?)?;
+        let raster = &data[pos + 1..];
+        let pixels = if max < 256 {
+            ensure!(raster.len() >= samples, "truncated image data");
+            raster[..samples]
+                .iter()
+                .map(|&v| scale_sample(u32::from(v), max))
+                .collect()
+        } else {
+            let bytes = samples
+                .checked_mul(2)
+                .with_context(|| format!("{width}x{height} image is too large"))?;
+            ensure!(raster.len() >= bytes, "truncated image data");
+            raster[..bytes]
+                .chunks_exact(2)
+                .map(|v| scale_sample(u32::from(u16::from_be_bytes([v[0], v[1]])), max))
+                .collect()
+        };
+        Self::new(width, height, color_space, pixels)
+    }
+
+    fn from_png(data: &[u8]) -> anyhow::Result<Self> {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        buf.truncate(info.buffer_size());
+
+        let (color_space, pixels) = match info.color_type {
+            png::ColorType::Rgb => (ColorSpaceType::RGB, buf),
+            png::ColorType::Rgba => (ColorSpaceType::RGB, drop_alpha(&buf, 4)),
+            png::ColorType::Grayscale => (ColorSpaceType::GRAYSCALE, buf),
+            png::ColorType::GrayscaleAlpha => (ColorSpaceType::GRAYSCALE, drop_alpha(&buf, 2)),
+            png::ColorType::Indexed => bail!("palette was not expanded"),
+        };
+        Self::new(info.width, info.height, color_space, pixels)
+    }
+
+    pub fn channels(&self) -> anyhow::Result<usize> {
+        channels(self.color_space)
+    }
+
+    /// Converts the image to another color space, using the BT.601 luma for grayscale.
+    pub fn convert(&self, color_space: ColorSpaceType) -> anyhow::Result<Self> {
+        let pixels = match (self.color_space, color_space) {
+            (from, to) if from == to => self.pixels.clone(),
+            (ColorSpaceType::RGB, ColorSpaceType::GRAYSCALE) => self
+                .pixels
+                .chunks_exact(3)
+                .map(|rgb| {
+                    let [r, g, b] = [rgb[0], rgb[1], rgb[2]].map(f32::from);
+                    (0.299 * r + 0.587 * g + 0.114 * b).round() as u8
+                })
+                .collect(),
+            (ColorSpaceType::GRAYSCALE, ColorSpaceType::RGB) => {
+                self.pixels.iter().flat_map(|&v| [v; 3]).collect()
+            }
+            (_, to) => bail!("unsupported color space {to:?}"),
+        };
+        Self::new(self.width, self.height, color_space, pixels)
+    }
+
+    /// Resizes the image with bilinear interpolation, sampling at pixel centers.
+    pub fn resize(&self, width: u32, height: u32) -> anyhow::Result<Self> {
+        ensure!(width > 0 && height > 0, "can't resize to {width}x{height}");
+        if (width, height) == (self.width, self.height) {
+            return Ok(self.clone());
+        }
+        let channels = self.channels()?;
+        ensure!(
+            self.pixels.len() == sample_count(self.width, self.height, channels)?,
+            "image data doesn't match its size"
+        );
+        let (src_w, src_h) = (self.width as usize, self.height as usize);
+        // Source coordinates and interpolation weights of each destination row or column.
+        let samples = |dst: u32, src: usize| {
+            let scale = src as f32 / dst as f32;
+            (0..dst)
+                .map(|i| {
+                    let x = ((i as f32 + 0.5) * scale - 0.5).clamp(0.0, (src - 1) as f32);
+                    let x0 = x.floor() as usize;
+                    (x0, (x0 + 1).min(src - 1), x - x0 as f32)
+                })
+                .collect::<Vec<_>>()
+        };
+        let xs = samples(width, src_w);
+        let ys = samples(height, src_h);
+
+        let mut pixels = Vec::with_capacity(sample_count(width, height, channels)?);
+        for &(y0, y1, wy) in &ys {
+            for &(x0, x1, wx) in &xs {
+                for c in 0..channels {
+                    let at =
+                        |x: usize, y: usize| f32::from(self.pixels[(y * src_w + x) * channels + c]);
+                    let top = at(x0, y0) * (1.0 - wx) + at(x1, y0) * wx;
+                    let bottom = at(x0, y1) * (1.0 - wx) + at(x1, y1) * wx;
+                    pixels.push((top * (1.0 - wy) + bottom * wy).round() as u8);
+                }
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            color_space: self.color_space,
+            pixels,
+        })
+    }
+}
+
+/// Returns the number of samples of a `width`x`height` image, failing if it overflows.
+fn sample_count(width: u32, height: u32, channels: usize) -> anyhow::Result<usize> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .with_context(|| format!("{width}x{height} image is too large"))
+}
+
+fn channels(color_space: ColorSpaceType) -> anyhow::Result<usize> {
+    match color_space {
+        ColorSpaceType::RGB => Ok(3),
+        ColorSpaceType::GRAYSCALE => Ok(1),
+        _ => bail!("unsupported color space {color_space:?}"),
+    }
+}
+
+/// Scales a sample with maximum value `max` to 8 bits.
+fn scale_sample(v: u32, max: u32) -> u8 {
+    ((v.min(max) * 255 + max / 2) / max) as u8
+}
+
+fn drop_alpha(pixels: &[u8], channels: usize) -> Vec<u8> {
+    pixels
+        .chunks_exact(channels)
+        .flat_map(|pixel| &pixel[..channels - 1])
+        .copied()
+        .collect()
+}
+
+/// Converts images to the data of an image input tensor.
+///
+/// Like the TFLite Task Library, float tensors require `NormalizationOptions`, and `UINT8`
+/// tensors without them are fed the pixel values as they are. With `NormalizationOptions`,
+/// quantized tensors are fed the normalized values, quantized with the tensor's quantization
+/// parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImagePreprocessor {
+    pub width: u32,
+    pub height: u32,
+    pub color_space: ColorSpaceType,
+    pub normalization: Option<NormalizationOptions>,
+    pub type_: TensorType,
+    pub quantization: Option<Quantization>,
+}
+
+impl ImagePreprocessor {
+    /// Creates the preprocessor of input tensor `input` of a subgraph.
+    ///
+    /// The image size and number of channels are taken from the tensor's `[1, height, width,
+    /// channels]` shape, and must match its `ImageProperties`, if any. Dynamic dimensions (-1 in
+    /// the shape signature) are taken from the `default_size` of the `ImageProperties`.
+    pub fn from_model(tflite: &[u8], subgraph: usize, input: usize) -> anyhow::Result<Self> {
+        let graph = ModelGraph::from_bytes(tflite)?;
+        let tensor = graph
+            .subgraphs
+            .get(subgraph)
+            .ok_or_else(|| anyhow!("model has no subgraph {subgraph}"))
+            .and_then(|sub| {
+                sub.inputs
+                    .get(input)
+                    .and_then(|&index| sub.tensor(index))
+                    .ok_or_else(|| anyhow!("subgraph {subgraph} has no input tensor {input}"))
+            })?;
+        let &[1, height, width, channels] = &tensor.shape[..] else {
+            bail!(
+                "expected image input tensor of shape [1, height, width, channels], got {:?}",
+                tensor.shape
+            );
+        };
+        let color_space = match channels {
+            1 => ColorSpaceType::GRAYSCALE,
+            3 => ColorSpaceType::RGB,
+            _ => bail!("expected 1 or 3 channels, got {channels}"),
+        };
+        // Dynamic dimensions are -1 in the shape signature, and usually 1 in the shape.
+        let dynamic = match tensor.shape_signature.as_deref() {
+            Some(&[_, height, width, _]) => [height < 0, width < 0],
+            _ => [false; 2],
+        };
+
+        let meta = ModelInfo::from_bytes(tflite)?;
+        let tensor_meta = meta
+            .subgraph_metadata
+            .get(subgraph)
+            .and_then(|sub| sub.input_tensor_metadata.get(input));
+        let properties = tensor_meta
+            .and_then(|meta| meta.content.as_ref()?.content_properties.as_ref())
+            .and_then(|props| match props {
+                ContentProperties::ImageProperties(props) => Some(props),
+                _ => None,
+            });
+        if let Some(props) = properties {
+            if props.color_space != ColorSpaceType::UNKNOWN && props.color_space != color_space {
+                bail!(
+                    "tensor has {channels} channel(s), but its metadata specifies {:?}",
+                    props.color_space
+                );
+            }
+        }
+        let normalization = tensor_meta.and_then(|meta| {
+            meta.process_units
+                .iter()
+                .find_map(|unit| match &unit.options {
+                    Some(ProcessUnitOptions::NormalizationOptions(options)) => {
+                        Some(options.clone())
+                    }
+                    _ => None,
+                })
+        });
+
+        let default_size = properties.and_then(|props| props.default_size);
+        let size = |name: &str, size: i32, dynamic: bool, default: Option<u32>| {
+            if size > 0 && !dynamic {
+                return Ok(size as u32);
+            }
+            default.filter(|&size| size > 0).ok_or_else(|| {
+                anyhow!(
+                    "input tensor has no fixed {name} (got {size}), and its ImageProperties \
+                     have no default_size"
+                )
+            })
+        };
+        let height = size("height", height, dynamic[0], default_size.map(|s| s.height))?;
+        let width = size("width", width, dynamic[1], default_size.map(|s| s.width))?;
+
+        let preprocessor = Self {
+            width,
+            height,
+            color_space,
+            normalization,
+            type_: tensor.type_,
+            quantization: tensor.quantization.clone(),
+        };
+        preprocessor.check()?;
+        Ok(preprocessor)
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        let channels = channels(self.color_space)?;
+        let per_channel = |name, len| {
+            ensure!(
+                len == 1 || len == channels,
+                "expected 1 or {channels} {name} value(s), got {len}"
+            );
+            Ok(())
+        };
+        if let Some(norm) = &self.normalization {
+            per_channel("normalization mean", norm.mean.len())?;
+            per_channel("normalization std", norm.std.len())?;
+            ensure!(
+                norm.std.iter().all(|&std| std != 0.0),
+                "normalization std must not be 0"
+            );
+        }
+        match self.type_ {
+            TensorType::FLOAT32 if self.normalization.is_none() => {
+                bail!("float input tensor has no NormalizationOptions")
+            }
+            TensorType::FLOAT32 | TensorType::UINT8 => {}
+            TensorType::INT8 if self.normalization.is_none() => {
+                bail!("INT8 input tensor has no NormalizationOptions")
+            }
+            TensorType::INT8 => {}
+            type_ => bail!("unsupported input tensor type {type_:?}"),
+        }
+        if self.type_ != TensorType::FLOAT32 && self.normalization.is_some() {
+            let quant = self
+                .quantization
+                .as_ref()
+                .ok_or_else(|| anyhow!("{:?} input tensor is not quantized", self.type_))?;
+            per_channel("quantization scale", quant.scale.len())?;
+            per_channel("quantization zero point", quant.zero_point.len().max(1))?;
+        }
+        Ok(())
+    }
+
+    /// Converts and resizes `image`, and returns the data of the input tensor (in little-endian
+    /// byte order).
+    pub fn process(&self, image: &Image) -> anyhow::Result<Vec<u8>> {
+        let image = image
+            .convert(self.color_space)?
+            .resize(self.width, self.height)?;
+        let channels = image.channels()?;
+        // Per-channel values are given for each channel, or once for all channels.
+        let at = |values: &[f32], c: usize| values[if values.len() == 1 { 0 } else { c }];
+
+        let Some(norm) = &self.normalization else {
+            // Only UINT8 tensors are allowed without normalization.
+            return Ok(image.pixels);
+        };
+        let normalized = image.pixels.iter().enumerate().map(|(i, &v)| {
+            let c = i % channels;
+            (f32::from(v) - at(&norm.mean, c)) / at(&norm.std, c)
+        });
+
+        let data = match self.type_ {
+            TensorType::FLOAT32 => normalized.flat_map(f32::to_le_bytes).collect(),
+            type_ => {
+                let quant = self
+                    .quantization
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("{type_:?} input tensor is not quantized"))?;
+                let (min, max) = match type_ {
+                    TensorType::UINT8 => (0.0, 255.0),
+                    _ => (-128.0, 127.0),
+                };
+                normalized
+                    .enumerate()
+                    .map(|(i, x)| {
+                        let c = i % channels;
+                        let zero_point = match &quant.zero_point[..] {
+                            [] => 0,
+                            [zero_point] => *zero_point,
+                            zero_points => zero_points[c],
+                        };
+                        let q = (x / at(&quant.scale, c)).round() + zero_point as f32;
+                        // Both types are stored as a single byte.
+                        q.clamp(min, max) as i32 as u8
+                    })
+                    .collect()
+            }
+        };
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flatbuffers::FlatBufferBuilder;
+
+    use super::*;
+    use crate::v3c::tflite as v3c;
+    use crate::{
+        write_metadata, Content, ImageProperties, ImageSize, ProcessUnit, SubGraphMetadata,
+        TensorMetadata,
+    };
+
+    /// The `NormalizationOptions` of the MobileNet models of the Task Library examples, which map
+    /// pixel values to [-1, 1].
+    fn mobilenet_normalization() -> NormalizationOptions {
+        NormalizationOptions {
+            mean: vec![127.5],
+            std: vec![127.5],
+        }
+    }
+
+    fn quantization(scale: f32, zero_point: i64) -> Quantization {
+        Quantization {
+            scale: vec![scale],
+            zero_point: vec![zero_point],
+            min: Vec::new(),
+            max: Vec::new(),
+            quantized_dimension: 0,
+            custom: None,
+        }
+    }
+
+    fn preprocessor(
+        type_: TensorType,
+        normalization: Option<NormalizationOptions>,
+        quantization: Option<Quantization>,
+    ) -> ImagePreprocessor {
+        ImagePreprocessor {
+            width: 2,
+            height: 1,
+            color_space: ColorSpaceType::GRAYSCALE,
+            normalization,
+            type_,
+            quantization,
+        }
+    }
+
+    fn gray(width: u32, height: u32, pixels: &[u8]) -> Image {
+        Image::new(width, height, ColorSpaceType::GRAYSCALE, pixels.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn resize_bilinear() {
+        // Upscaling samples at pixel centers, clamping at the edges.
+        let image = gray(2, 1, &[0, 100]).resize(4, 1).unwrap();
+        assert_eq!(image.pixels, [0, 25, 75, 100]);
+        let image = gray(1, 2, &[0, 100]).resize(1, 4).unwrap();
+        assert_eq!(image.pixels, [0, 25, 75, 100]);
+        // Halving averages pairs of pixels.
+        let image = gray(4, 2, &[0, 10, 20, 40, 100, 110, 120, 140]).resize(2, 1);
+        assert_eq!(image.unwrap().pixels, [55, 80]);
+
+        let rgb = Image::new(2, 1, ColorSpaceType::RGB, vec![0, 0, 0, 200, 100, 50]).unwrap();
+        let resized = rgb.resize(4, 1).unwrap();
+        assert_eq!(resized.pixels[3..9], [50, 25, 13, 150, 75, 38]);
+        assert!(rgb.resize(0, 1).is_err());
+    }
+
+    #[test]
+    fn decode_pnm() {
+        let image = Image::decode(b"P5\n# comment\n2 1\n255\n\x00\x80").unwrap();
+        assert_eq!(image, gray(2, 1, &[0, 128]));
+        let image = Image::decode(b"P6 1 1 65535 \xff\xff\x80\x00\x00\x00").unwrap();
+        assert_eq!(image.pixels, [255, 128, 0]);
+        assert!(Image::decode(b"P5 2 2 255 \x00\x00\x00").is_err());
+
+        // The sample count overflows usize; this must be an error rather than a panic or a
+        // wrapped-around length.
+        let e = Image::decode(b"P6 4294967295 4294967295 255 \x00\x00\x00").unwrap_err();
+        assert!(format!("{e:#}").contains("too large"), "{e:#}");
+        let e = Image::new(u32::MAX, u32::MAX, ColorSpaceType::RGB, vec![0; 3]).unwrap_err();
+        assert!(format!("{e:#}").contains("too large"), "{e:#}");
+    }
+
+    #[test]
+    fn convert() {
+        let rgb = Image::new(1, 1, ColorSpaceType::RGB, vec![255, 0, 0]).unwrap();
+        let gray = rgb.convert(ColorSpaceType::GRAYSCALE).unwrap();
+        assert_eq!(gray.pixels, [76]);
+        let back = gray.convert(ColorSpaceType::RGB).unwrap();
+        assert_eq!(back.pixels, [76, 76, 76]);
+    }
+
+    #[test]
+    fn normalize_float() {
+        let preprocessor = preprocessor(TensorType::FLOAT32, Some(mobilenet_normalization()), None);
+        let data = preprocessor.process(&gray(2, 1, &[0, 255])).unwrap();
+        let values = data
+            .chunks_exact(4)
+            .map(|v| f32::from_le_bytes(v.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(values, [-1.0, 1.0]);
+
+        let mut per_channel = preprocessor.clone();
+        per_channel.color_space = ColorSpaceType::RGB;
+        per_channel.width = 1;
+        per_channel.normalization = Some(NormalizationOptions {
+            mean: vec![0.0, 100.0, 200.0],
+            std: vec![1.0, 2.0, 4.0],
+        });
+        let image = Image::new(1, 1, ColorSpaceType::RGB, vec![10, 110, 220]).unwrap();
+        let data = per_channel.process(&image).unwrap();
+        assert_eq!(data[..4], 10.0f32.to_le_bytes());
+        assert_eq!(data[4..8], 5.0f32.to_le_bytes());
+        assert_eq!(data[8..], 5.0f32.to_le_bytes());
+    }
+
+    #[test]
+    fn quantize() {
+        // Quantized MobileNet V1: the normalized [-1, 1] range is quantized with a scale of
+        // 1/128 and a zero point of 128, and clamped to the range of UINT8.
+        let uint8 = preprocessor(
+            TensorType::UINT8,
+            Some(mobilenet_normalization()),
+            Some(quantization(0.0078125, 128)),
+        );
+        assert_eq!(uint8.process(&gray(2, 1, &[0, 255])).unwrap(), [0, 255]);
+        assert_eq!(uint8.process(&gray(2, 1, &[64, 128])).unwrap(), [64, 129]);
+
+        let int8 = preprocessor(
+            TensorType::INT8,
+            Some(mobilenet_normalization()),
+            Some(quantization(0.0078125, 0)),
+        );
+        let data = int8.process(&gray(2, 1, &[0, 255])).unwrap();
+        assert_eq!(data, [-128i8 as u8, 127]);
+
+        // Without normalization, UINT8 tensors get the pixel values.
+        let raw = preprocessor(TensorType::UINT8, None, None);
+        assert_eq!(raw.process(&gray(2, 1, &[3, 250])).unwrap(), [3, 250]);
+    }
+
+    #[test]
+    fn unquantized_tensor_is_an_error() {
+        let preprocessor = preprocessor(TensorType::UINT8, Some(mobilenet_normalization()), None);
+        assert!(preprocessor.check().is_err());
+        assert!(preprocessor.process(&gray(2, 1, &[0, 255])).is_err());
+    }
+
+    /// Builds a model with a single `FLOAT32` input tensor, with MobileNet normalization and the
+    /// given `ImageProperties.default_size`.
+    fn model(shape: &[i32], shape_signature: &[i32], default_size: Option<ImageSize>) -> Vec<u8> {
+        let mut fbb = FlatBufferBuilder::new();
+        let buffer = v3c::Buffer::create(&mut fbb, &Default::default());
+        let buffers = fbb.create_vector(&[buffer]);
+        let args = v3c::TensorArgs {
+            shape: Some(fbb.create_vector(shape)),
+            shape_signature: Some(fbb.create_vector(shape_signature)),
+            type_: v3c::TensorType::FLOAT32,
+            ..Default::default()
+        };
+        let tensor = v3c::Tensor::create(&mut fbb, &args);
+        let args = v3c::SubGraphArgs {
+            tensors: Some(fbb.create_vector(&[tensor])),
+            inputs: Some(fbb.create_vector(&[0])),
+            outputs: Some(fbb.create_vector(&[0])),
+            ..Default::default()
+        };
+        let subgraph = v3c::SubGraph::create(&mut fbb, &args);
+        let args = v3c::ModelArgs {
+            version: 3,
+            subgraphs: Some(fbb.create_vector(&[subgraph])),
+            buffers: Some(buffers),
+            ..Default::default()
+        };
+        let root = v3c::Model::create(&mut fbb, &args);
+        fbb.finish(root, Some("TFL3"));
+
+        let properties = ImageProperties {
+            color_space: ColorSpaceType::RGB,
+            default_size,
+        };
+        let input = TensorMetadata {
+            content: Some(Content {
+                content_properties: Some(ContentProperties::ImageProperties(properties)),
+                range: None,
+            }),
+            process_units: vec![ProcessUnit {
+                options: Some(ProcessUnitOptions::NormalizationOptions(
+                    mobilenet_normalization(),
+                )),
+            }],
+            ..Default::default()
+        };
+        let meta = ModelInfo {
+            subgraph_metadata: vec![SubGraphMetadata {
+                input_tensor_metadata: vec![input],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        write_metadata(fbb.finished_data(), &meta, &[]).unwrap()
+    }
+
+    #[test]
+    fn size_from_shape() {
+        let tflite = model(&[1, 224, 160, 3], &[1, 224, 160, 3], None);
+        let preprocessor = ImagePreprocessor::from_model(&tflite, 0, 0).unwrap();
+        assert_eq!((preprocessor.width, preprocessor.height), (160, 224));
+        assert_eq!(preprocessor.color_space, ColorSpaceType::RGB);
+    }
+
+    #[test]
+    fn dynamic_size() {
+        let default_size = ImageSize {
+            width: 320,
+            height: 240,
+        };
+        let tflite = model(&[1, 1, 1, 3], &[1, -1, -1, 3], Some(default_size));
+        let preprocessor = ImagePreprocessor::from_model(&tflite, 0, 0).unwrap();
+        assert_eq!((preprocessor.width, preprocessor.height), (320, 240));
+
+        let tflite = model(&[1, 1, 1, 3], &[1, -1, -1, 3], None);
+        assert!(ImagePreprocessor::from_model(&tflite, 0, 0).is_err());
+        let tflite = model(&[1, 0, 224, 3], &[1, 0, 224, 3], None);
+        assert!(ImagePreprocessor::from_model(&tflite, 0, 0).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Label maps of output tensors, from their `TENSOR_AXIS_LABELS` and `TENSOR_VALUE_LABELS` files.
+
+use anyhow::{bail, Context};
+use serde::Serialize;
+
+use crate::{Archive, AssociatedFile, AssociatedFileType, TensorMetadata};
+
+/// The labels of an output tensor, indexed by class (axis labels) or by value (value labels).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LabelMap {
+    #[serde(rename = "type")]
+    pub type_: AssociatedFileType,
+    /// The file the labels were read from.
+    pub file: String,
+    /// The file the display names were read from, if any.
+    pub display_names_file: Option<String>,
+    /// Locale of the display names.
+    pub locale: Option<String>,
+    pub labels: Vec<Label>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Label {
+    pub index: usize,
+    pub name: String,
+    /// Name of the label in the requested locale.
+    pub display_name: Option<String>,
+}
+
+impl LabelMap {
+    /// Loads the labels of an output tensor.
+    ///
+    /// Label names are read from the first file of type `type_` without a locale (or the first
+    /// one, if all have a locale). Display names are read from the first file of that type whose
+    /// locale is exactly `locale`, or else from the first one whose locale is the language part of
+    /// `locale`, before any `-` or `_` (`de-CH` falls back to `de`). If neither exists, the labels
+    /// have no display names.
+    ///
+    /// Returns [`None`] if the tensor has no file of type `type_`. The files are read from the
+    /// model's associated files `archive`.
+    pub fn from_tensor(
+        meta: &TensorMetadata,
+        mut archive: Option<&mut Archive<'_>>,
+        type_: AssociatedFileType,
+        locale: Option<&str>,
+    ) -> anyhow::Result<Option<Self>> {
+        let files = meta
+            .associated_files
+            .iter()
+            .filter(|file| file.type_ == type_ && file.name.is_some())
+            .collect::<Vec<_>>();
+        let Some(&file) = files
+            .iter()
+            .find(|file| file.locale.as_deref().unwrap_or_default().is_empty())
+            .or_else(|| files.first())
+        else {
+            return Ok(None);
+        };
+
+        let display_names = locale.and_then(|locale| {
+            let find = |locale: &str| {
+                files
+                    .iter()
+                    .find(|file| file.locale.as_deref() == Some(locale))
+                    .copied()
+            };
+            find(locale).or_else(|| find(locale.split(['-', '_']).next()?))
+        });
+
+        let names = read_lines(file, archive.as_deref_mut())?;
+        let (display_names_file, display_names) = match display_names {
+            Some(display) => {
+                let display_names = read_lines(display, archive)?;
+                if display_names.len() != names.len() {
+                    bail!(
+                        "`{}` has {} labels, but `{}` has {} display names",
+                        name(file),
+                        names.len(),
+                        name(display),
+                        display_names.len()
+                    );
+                }
+                (Some(display), display_names.into_iter().map(Some).collect())
+            }
+            None => (None, vec![None; names.len()]),
+        };
+
+        Ok(Some(Self {
+            type_,
+            file: name(file).to_string(),
+            display_names_file: display_names_file.map(|file| name(file).to_string()),
+            locale: display_names_file.and_then(|file| file.locale.clone()),
+            labels: names
+                .into_iter()
+                .zip(display_names)
+                .enumerate()
+                .map(|(index, (name, display_name))| Label {
+                    index,
+                    name,
+                    display_name,
+                })
+                .collect(),
+        }))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Label> {
+        self.labels.get(index)
+    }
+}
+
+impl Label {
+    /// Returns the display name of the label, or its name if it has none.
+    pub fn display(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+}
+
+fn name(file: &AssociatedFile) -> &str {
+    file.name.as_deref().unwrap_or_default()
+}
+
+/// Reads a label file, with one label per line.
+fn read_lines(
+    file: &AssociatedFile,
+    archive: Option<&mut Archive<'_>>,
+) -> anyhow::Result<Vec<String>> {
+    let name = name(file);
+    let Some(archive) = archive else {
+        bail!("label file `{name}` can't be loaded: model has no archive");
+    };
+    let data = String::from_utf8(archive.read(name)?)
+        .with_context(|| format!("label file `{name}` is not UTF-8"))?;
+    Ok(data.lines().map(str::to_string).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    fn zip(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn file(name: &str, locale: Option<&str>) -> AssociatedFile {
+        AssociatedFile {
+            name: Some(name.into()),
+            type_: AssociatedFileType::TENSOR_AXIS_LABELS,
+            locale: locale.map(Into::into),
+            ..Default::default()
+        }
+    }
+
+    fn tensor(files: Vec<AssociatedFile>) -> TensorMetadata {
+        TensorMetadata {
+            associated_files: files,
+            ..Default::default()
+        }
+    }
+
+    fn load(meta: &TensorMetadata, data: &[u8], locale: Option<&str>) -> Option<LabelMap> {
+        let mut archive = Archive::from_bytes(data).unwrap().unwrap();
+        LabelMap::from_tensor(
+            meta,
+            Some(&mut archive),
+            AssociatedFileType::TENSOR_AXIS_LABELS,
+            locale,
+        )
+        .unwrap()
+    }
+
+    fn display_names(labels: &LabelMap) -> Vec<&str> {
+        labels.labels.iter().map(Label::display).collect()
+    }
+
+    #[test]
+    fn display_names_by_locale() {
+        let data = zip(&[
+            ("labels.txt", "cat\ndog\n"),
+            ("labels_de.txt", "Katze\nHund\n"),
+            ("labels_fr.txt", "chat\nchien\n"),
+        ]);
+        let meta = tensor(vec![
+            file("labels_de.txt", Some("de")),
+            file("labels.txt", None),
+            file("labels_fr.txt", Some("fr")),
+        ]);
+
+        let labels = load(&meta, &data, None).unwrap();
+        assert_eq!(labels.file, "labels.txt");
+        assert_eq!(labels.display_names_file, None);
+        assert_eq!(display_names(&labels), ["cat", "dog"]);
+
+        let labels = load(&meta, &data, Some("fr")).unwrap();
+        assert_eq!(labels.display_names_file.as_deref(), Some("labels_fr.txt"));
+        assert_eq!(labels.locale.as_deref(), Some("fr"));
+        assert_eq!(labels.labels[1].name, "dog");
+        assert_eq!(display_names(&labels), ["chat", "chien"]);
+    }
+
+    #[test]
+    fn locale_fallback() {
+        let data = zip(&[("labels.txt", "cat\n"), ("labels_de.txt", "Katze\n")]);
+        let meta = tensor(vec![
+            file("labels.txt", None),
+            file("labels_de.txt", Some("de")),
+        ]);
+
+        // The region is dropped if no file matches it.
+        for locale in ["de-CH", "de_AT"] {
+            let labels = load(&meta, &data, Some(locale)).unwrap();
+            assert_eq!(display_names(&labels), ["Katze"], "{locale}");
+        }
+        // No display names for other locales.
+        let labels = load(&meta, &data, Some("ja")).unwrap();
+        assert_eq!(labels.display_names_file, None);
+        assert_eq!(display_names(&labels), ["cat"]);
+    }
+
+    #[test]
+    fn files_with_locales_only() {
+        let data = zip(&[("labels_en.txt", "cat\n"), ("labels_de.txt", "Katze\n")]);
+        let meta = tensor(vec![
+            file("labels_en.txt", Some("en")),
+            file("labels_de.txt", Some("de")),
+        ]);
+        let labels = load(&meta, &data, Some("de")).unwrap();
+        assert_eq!(labels.file, "labels_en.txt");
+        assert_eq!(labels.labels[0].name, "cat");
+        assert_eq!(display_names(&labels), ["Katze"]);
+    }
+
+    #[test]
+    fn label_file_errors() {
+        let meta = tensor(vec![
+            file("labels.txt", None),
+            file("labels_de.txt", Some("de")),
+        ]);
+        let data = zip(&[("labels.txt", "cat\ndog\n"), ("labels_de.txt", "Katze\n")]);
+        let mut archive = Archive::from_bytes(&data).unwrap().unwrap();
+        let axis = AssociatedFileType::TENSOR_AXIS_LABELS;
+        assert!(LabelMap::from_tensor(&meta, Some(&mut archive), axis, Some("de")).is_err());
+        assert!(LabelMap::from_tensor(&meta, None, axis, None).is_err());
+
+        let value = AssociatedFileType::TENSOR_VALUE_LABELS;
+        let labels = LabelMap::from_tensor(&meta, Some(&mut archive), value, None).unwrap();
+        assert!(labels.is_none());
+    }
+}
@@ -0,0 +1,144 @@
+//! Extracts [TFLite metadata] from `.tflite` model files.
+//!
+//! The main entry point is [`ModelInfo::from_bytes`], which locates the `TFLITE_METADATA` entry of
+//! a model and converts it into owned Rust types.
+//!
+//! The raw FlatBuffers bindings of the schemas in `schemas/` are available in the [`metadata`],
+//! [`object_detector`], [`image_segmenter`] and [`v3c`] modules.
+//!
+//! [TFLite metadata]: https://www.tensorflow.org/lite/models/convert/metadata
+
+/// Implements [`serde::Serialize`] and [`serde::Deserialize`] for generated enum types, using
+/// their schema names (numeric values are also accepted when deserializing).
+macro_rules! serde_by_name {
+    ($($ty:ident),+ $(,)?) => {
+        $(
+            impl serde::Serialize for $ty {
+                fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    match self.variant_name() {
+                        Some(name) => serializer.serialize_str(name),
+                        None => serializer.serialize_i64(self.0.into()),
+                    }
+                }
+            }
+
+            impl<'de> serde::Deserialize<'de> for $ty {
+                fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    use serde::de::Error;
+
+                    #[derive(serde::Deserialize)]
+                    #[serde(untagged)]
+                    enum NameOrValue {
+                        Name(String),
+                        Value(i64),
+                    }
+
+                    match NameOrValue::deserialize(deserializer)? {
+                        NameOrValue::Name(name) => $ty::ENUM_VALUES
+                            .iter()
+                            .find(|v| v.variant_name() == Some(&*name))
+                            .copied()
+                            .ok_or_else(|| {
+                                D::Error::custom(format!(
+                                    "unknown {} `{name}`",
+                                    stringify!($ty),
+                                ))
+                            }),
+                        NameOrValue::Value(value) => value
+                            .try_into()
+                            .map($ty)
+                            .map_err(|_| D::Error::custom(format!(
+                                "{value} is out of range for {}",
+                                stringify!($ty),
+                            ))),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+use anyhow::Context;
+
+mod anchors;
+mod archive;
+mod buffer;
+mod calibration;
+mod custom;
+mod detector;
+mod diff;
+mod file;
+mod graph;
+mod image;
+mod labels;
+mod model;
+mod ops;
+mod quant;
+mod rebuild;
+mod segmenter;
+mod signature;
+mod size;
+mod ssd;
+mod tokenizer;
+mod validate;
+mod writer;
+
+pub use anchors::*;
+pub use archive::*;
+pub use buffer::*;
+pub use calibration::*;
+pub use custom::*;
+pub use detector::*;
+pub use diff::*;
+pub use file::*;
+pub use graph::*;
+pub use image::*;
+pub use labels::*;
+pub use model::*;
+pub use ops::*;
+pub use quant::*;
+pub use segmenter::*;
+pub use signature::*;
+pub use size::*;
+pub use ssd::*;
+pub use tokenizer::*;
+pub use validate::*;
+pub use writer::*;
+
+#[allow(warnings)]
+#[path = "../generated/object_detector_metadata_schema_generated.rs"]
+pub mod object_detector;
+
+#[allow(warnings)]
+pub mod image_segmenter;
+
+#[allow(warnings)]
+#[path = "../generated/metadata_schema_generated.rs"]
+pub mod metadata;
+
+#[allow(warnings)]
+#[path = "../generated/schema_v3c_generated.rs"]
+pub mod v3c;
+
+/// Name of the [`v3c::tflite::Metadata`] entry that references the [`ModelInfo`] buffer.
+pub const METADATA_NAME: &str = "TFLITE_METADATA";
+
+/// Returns the contents of the buffer referenced by the model's `TFLITE_METADATA` entry.
+///
+/// `tflite` is the content of a `.tflite` file.
+pub fn metadata_buffer(tflite: &[u8]) -> anyhow::Result<&[u8]> {
+    let model = flatbuffers::root::<v3c::tflite::Model>(tflite)?;
+    let Some(metadata) = model.metadata() else {
+        anyhow::bail!("model contains no metadata entries")
+    };
+
+    let Some(metadata) = metadata
+        .iter()
+        .find(|meta| meta.name() == Some(METADATA_NAME))
+    else {
+        anyhow::bail!("model contains no `{METADATA_NAME}` entry")
+    };
+
+    buffer_data(tflite, &model, metadata.buffer())
+        .with_context(|| format!("failed to read the `{METADATA_NAME}` buffer"))
+}
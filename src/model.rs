@@ -0,0 +1,589 @@
+//! Owned mirror of the types in `metadata_schema.fbs`.
+
+use flatbuffers::{ForwardsUOffset, Vector};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+
+use crate::metadata::tflite as fb;
+use crate::{decode_custom_metadata, DecodedMetadata};
+
+pub use crate::metadata::tflite::{
+    AssociatedFileType, BoundingBoxType, ColorSpaceType, CoordinateType, ScoreTransformationType,
+};
+
+/// Owned version of the `ModelMetadata` table stored in a model's `TFLITE_METADATA` buffer.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelInfo {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub subgraph_metadata: Vec<SubGraphMetadata>,
+    pub author: Option<String>,
+    pub license: Option<String>,
+    pub associated_files: Vec<AssociatedFile>,
+    pub min_parser_version: Option<String>,
+}
+
+impl ModelInfo {
+    /// Extracts the metadata from the contents of a `.tflite` file.
+    pub fn from_bytes(tflite: &[u8]) -> anyhow::Result<Self> {
+        Self::from_metadata_bytes(crate::metadata_buffer(tflite)?)
+    }
+
+    /// Parses a serialized `ModelMetadata` flatbuffer (the contents of the `TFLITE_METADATA`
+    /// buffer).
+    pub fn from_metadata_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let meta = flatbuffers::root::<fb::ModelMetadata>(bytes)?;
+        Ok(meta.into())
+    }
+
+    /// Returns every [`AssociatedFile`] declared anywhere in the metadata.
+    ///
+    /// This includes the files of the model, of every subgraph and tensor, and those referenced
+    /// by tokenizer process units.
+    pub fn all_associated_files(&self) -> Vec<&AssociatedFile> {
+        let mut files = Vec::new();
+        files.extend(&self.associated_files);
+        for sub in &self.subgraph_metadata {
+            files.extend(&sub.associated_files);
+            let tensors = sub
+                .input_tensor_metadata
+                .iter()
+                .chain(&sub.output_tensor_metadata);
+            let mut units = sub
+                .input_process_units
+                .iter()
+                .chain(&sub.output_process_units)
+                .collect::<Vec<_>>();
+            for tensor in tensors {
+                files.extend(&tensor.associated_files);
+                units.extend(&tensor.process_units);
+            }
+            for unit in units {
+                if let Some(options) = &unit.options {
+                    files.extend(options.associated_files());
+                }
+            }
+        }
+        files
+    }
+}
+
+impl From<fb::ModelMetadata<'_>> for ModelInfo {
+    fn from(meta: fb::ModelMetadata<'_>) -> Self {
+        Self {
+            name: meta.name().map(Into::into),
+            description: meta.description().map(Into::into),
+            version: meta.version().map(Into::into),
+            subgraph_metadata: collect(meta.subgraph_metadata()),
+            author: meta.author().map(Into::into),
+            license: meta.license().map(Into::into),
+            associated_files: collect(meta.associated_files()),
+            min_parser_version: meta.min_parser_version().map(Into::into),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubGraphMetadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub input_tensor_metadata: Vec<TensorMetadata>,
+    pub output_tensor_metadata: Vec<TensorMetadata>,
+    pub associated_files: Vec<AssociatedFile>,
+    pub input_process_units: Vec<ProcessUnit>,
+    pub output_process_units: Vec<ProcessUnit>,
+    pub input_tensor_groups: Vec<TensorGroup>,
+    pub output_tensor_groups: Vec<TensorGroup>,
+    pub custom_metadata: Vec<CustomMetadata>,
+}
+
+impl From<fb::SubGraphMetadata<'_>> for SubGraphMetadata {
+    fn from(sub: fb::SubGraphMetadata<'_>) -> Self {
+        Self {
+            name: sub.name().map(Into::into),
+            description: sub.description().map(Into::into),
+            input_tensor_metadata: collect(sub.input_tensor_metadata()),
+            output_tensor_metadata: collect(sub.output_tensor_metadata()),
+            associated_files: collect(sub.associated_files()),
+            input_process_units: collect(sub.input_process_units()),
+            output_process_units: collect(sub.output_process_units()),
+            input_tensor_groups: collect(sub.input_tensor_groups()),
+            output_tensor_groups: collect(sub.output_tensor_groups()),
+            custom_metadata: collect(sub.custom_metadata()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TensorMetadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub dimension_names: Vec<String>,
+    pub content: Option<Content>,
+    pub process_units: Vec<ProcessUnit>,
+    pub stats: Option<Stats>,
+    pub associated_files: Vec<AssociatedFile>,
+}
+
+impl From<fb::TensorMetadata<'_>> for TensorMetadata {
+    fn from(tens: fb::TensorMetadata<'_>) -> Self {
+        Self {
+            name: tens.name().map(Into::into),
+            description: tens.description().map(Into::into),
+            dimension_names: strings(tens.dimension_names()),
+            content: tens.content().map(Into::into),
+            process_units: collect(tens.process_units()),
+            stats: tens.stats().map(Into::into),
+            associated_files: collect(tens.associated_files()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssociatedFile {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: AssociatedFileType,
+    pub locale: Option<String>,
+    pub version: Option<String>,
+}
+
+impl From<fb::AssociatedFile<'_>> for AssociatedFile {
+    fn from(file: fb::AssociatedFile<'_>) -> Self {
+        Self {
+            name: file.name().map(Into::into),
+            description: file.description().map(Into::into),
+            type_: file.type_(),
+            locale: file.locale().map(Into::into),
+            version: file.version().map(Into::into),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(try_from = "RawContent")]
+pub struct Content {
+    #[serde(flatten)]
+    pub content_properties: Option<ContentProperties>,
+    pub range: Option<ValueRange>,
+}
+
+impl From<fb::Content<'_>> for Content {
+    fn from(cont: fb::Content<'_>) -> Self {
+        let content_properties = match cont.content_properties_type() {
+            fb::ContentProperties::NONE => None,
+            fb::ContentProperties::FeatureProperties => {
+                Some(ContentProperties::FeatureProperties(FeatureProperties {}))
+            }
+            fb::ContentProperties::ImageProperties => cont
+                .content_properties_as_image_properties()
+                .map(|p| ContentProperties::ImageProperties(p.into())),
+            fb::ContentProperties::BoundingBoxProperties => cont
+                .content_properties_as_bounding_box_properties()
+                .map(|p| ContentProperties::BoundingBoxProperties(p.into())),
+            fb::ContentProperties::AudioProperties => cont
+                .content_properties_as_audio_properties()
+                .map(|p| ContentProperties::AudioProperties(p.into())),
+            fb::ContentProperties(unknown) => Some(ContentProperties::Unknown(unknown)),
+        };
+        Self {
+            content_properties,
+            range: cont.range().map(|range| ValueRange {
+                min: range.min(),
+                max: range.max(),
+            }),
+        }
+    }
+}
+
+/// The `ContentProperties` union.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "content_properties_type", content = "content_properties")]
+pub enum ContentProperties {
+    FeatureProperties(FeatureProperties),
+    ImageProperties(ImageProperties),
+    BoundingBoxProperties(BoundingBoxProperties),
+    AudioProperties(AudioProperties),
+    /// A union member that is not known to this version of the schema.
+    Unknown(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FeatureProperties {}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageProperties {
+    pub color_space: ColorSpaceType,
+    pub default_size: Option<ImageSize>,
+}
+
+impl From<fb::ImageProperties<'_>> for ImageProperties {
+    fn from(props: fb::ImageProperties<'_>) -> Self {
+        Self {
+            color_space: props.color_space(),
+            default_size: props.default_size().map(|size| ImageSize {
+                width: size.width(),
+                height: size.height(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoundingBoxProperties {
+    pub index: Vec<u32>,
+    #[serde(rename = "type")]
+    pub type_: BoundingBoxType,
+    pub coordinate_type: CoordinateType,
+}
+
+impl From<fb::BoundingBoxProperties<'_>> for BoundingBoxProperties {
+    fn from(props: fb::BoundingBoxProperties<'_>) -> Self {
+        Self {
+            index: props.index().map_or(Vec::new(), |v| v.iter().collect()),
+            type_: props.type_(),
+            coordinate_type: props.coordinate_type(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioProperties {
+    pub sample_rate: u32,
+    pub channels: u32,
+}
+
+impl From<fb::AudioProperties<'_>> for AudioProperties {
+    fn from(props: fb::AudioProperties<'_>) -> Self {
+        Self {
+            sample_rate: props.sample_rate(),
+            channels: props.channels(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValueRange {
+    pub min: i32,
+    pub max: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(try_from = "RawProcessUnit")]
+pub struct ProcessUnit {
+    #[serde(flatten)]
+    pub options: Option<ProcessUnitOptions>,
+}
+
+impl From<fb::ProcessUnit<'_>> for ProcessUnit {
+    fn from(pu: fb::ProcessUnit<'_>) -> Self {
+        let options = match pu.options_type() {
+            fb::ProcessUnitOptions::NONE => None,
+            fb::ProcessUnitOptions::NormalizationOptions => {
+                pu.options_as_normalization_options().map(|opts| {
+                    ProcessUnitOptions::NormalizationOptions(NormalizationOptions {
+                        mean: floats(opts.mean()),
+                        std: floats(opts.std_()),
+                    })
+                })
+            }
+            fb::ProcessUnitOptions::ScoreCalibrationOptions => {
+                pu.options_as_score_calibration_options().map(|opts| {
+                    ProcessUnitOptions::ScoreCalibrationOptions(ScoreCalibrationOptions {
+                        score_transformation: opts.score_transformation(),
+                        default_score: opts.default_score(),
+                    })
+                })
+            }
+            fb::ProcessUnitOptions::ScoreThresholdingOptions => {
+                pu.options_as_score_thresholding_options().map(|opts| {
+                    ProcessUnitOptions::ScoreThresholdingOptions(ScoreThresholdingOptions {
+                        global_score_threshold: opts.global_score_threshold(),
+                    })
+                })
+            }
+            fb::ProcessUnitOptions::BertTokenizerOptions => {
+                pu.options_as_bert_tokenizer_options().map(|opts| {
+                    ProcessUnitOptions::BertTokenizerOptions(BertTokenizerOptions {
+                        vocab_file: collect(opts.vocab_file()),
+                    })
+                })
+            }
+            fb::ProcessUnitOptions::SentencePieceTokenizerOptions => pu
+                .options_as_sentence_piece_tokenizer_options()
+                .map(|opts| {
+                    ProcessUnitOptions::SentencePieceTokenizerOptions(
+                        SentencePieceTokenizerOptions {
+                            sentence_piece_model: collect(opts.sentencePiece_model()),
+                            vocab_file: collect(opts.vocab_file()),
+                        },
+                    )
+                }),
+            fb::ProcessUnitOptions::RegexTokenizerOptions => {
+                pu.options_as_regex_tokenizer_options().map(|opts| {
+                    ProcessUnitOptions::RegexTokenizerOptions(RegexTokenizerOptions {
+                        delim_regex_pattern: opts.delim_regex_pattern().map(Into::into),
+                        vocab_file: collect(opts.vocab_file()),
+                    })
+                })
+            }
+            fb::ProcessUnitOptions(unknown) => Some(ProcessUnitOptions::Unknown(unknown)),
+        };
+        Self { options }
+    }
+}
+
+/// The `ProcessUnitOptions` union.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "options_type", content = "options")]
+pub enum ProcessUnitOptions {
+    NormalizationOptions(NormalizationOptions),
+    ScoreCalibrationOptions(ScoreCalibrationOptions),
+    ScoreThresholdingOptions(ScoreThresholdingOptions),
+    BertTokenizerOptions(BertTokenizerOptions),
+    SentencePieceTokenizerOptions(SentencePieceTokenizerOptions),
+    RegexTokenizerOptions(RegexTokenizerOptions),
+    /// A union member that is not known to this version of the schema.
+    Unknown(u8),
+}
+
+impl ProcessUnitOptions {
+    /// Returns the [`AssociatedFile`]s referenced by these options (tokenizer vocabularies and
+    /// models).
+    pub fn associated_files(&self) -> Vec<&AssociatedFile> {
+        match self {
+            Self::BertTokenizerOptions(opts) => opts.vocab_file.iter().collect(),
+            Self::SentencePieceTokenizerOptions(opts) => opts
+                .sentence_piece_model
+                .iter()
+                .chain(&opts.vocab_file)
+                .collect(),
+            Self::RegexTokenizerOptions(opts) => opts.vocab_file.iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizationOptions {
+    pub mean: Vec<f32>,
+    pub std: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreCalibrationOptions {
+    pub score_transformation: ScoreTransformationType,
+    pub default_score: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreThresholdingOptions {
+    pub global_score_threshold: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BertTokenizerOptions {
+    pub vocab_file: Vec<AssociatedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SentencePieceTokenizerOptions {
+    #[serde(rename = "sentencePiece_model")]
+    pub sentence_piece_model: Vec<AssociatedFile>,
+    pub vocab_file: Vec<AssociatedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegexTokenizerOptions {
+    pub delim_regex_pattern: Option<String>,
+    pub vocab_file: Vec<AssociatedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub max: Vec<f32>,
+    pub min: Vec<f32>,
+}
+
+impl From<fb::Stats<'_>> for Stats {
+    fn from(stats: fb::Stats<'_>) -> Self {
+        Self {
+            max: floats(stats.max()),
+            min: floats(stats.min()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TensorGroup {
+    pub name: Option<String>,
+    pub tensor_names: Vec<String>,
+}
+
+impl From<fb::TensorGroup<'_>> for TensorGroup {
+    fn from(group: fb::TensorGroup<'_>) -> Self {
+        Self {
+            name: group.name().map(Into::into),
+            tensor_names: strings(group.tensor_names()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct CustomMetadata {
+    pub name: Option<String>,
+    pub data: Vec<u8>,
+}
+
+impl CustomMetadata {
+    /// Decodes the entry's data with the [`decode_custom_metadata`] registry.
+    ///
+    /// Entries of an unknown format are described by [`crate::fallback`], so this only fails if the
+    /// decoder handling the entry does.
+    pub fn decode(&self) -> anyhow::Result<DecodedMetadata> {
+        decode_custom_metadata(self.name.as_deref(), &self.data)
+    }
+}
+
+/// Serializes `name` and `data` as in the schema, plus the [`CustomMetadata::decode`]d value as
+/// `decoded` (or `decoding_error`).
+impl Serialize for CustomMetadata {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("CustomMetadata", 3)?;
+        s.serialize_field("name", &self.name)?;
+        s.serialize_field("data", &self.data)?;
+        match self.decode() {
+            Ok(decoded) => s.serialize_field("decoded", &decoded)?,
+            Err(e) => s.serialize_field("decoding_error", &e.to_string())?,
+        }
+        s.end()
+    }
+}
+
+impl From<fb::CustomMetadata<'_>> for CustomMetadata {
+    fn from(custom: fb::CustomMetadata<'_>) -> Self {
+        Self {
+            name: custom.name().map(Into::into),
+            data: custom.data().map_or(Vec::new(), |v| v.bytes().to_vec()),
+        }
+    }
+}
+
+/// Deserialization helper for [`Content`], which reports errors in the union instead of silently
+/// dropping it (like `#[serde(flatten)]` would).
+#[derive(Deserialize)]
+struct RawContent {
+    #[serde(default)]
+    content_properties_type: Option<serde_json::Value>,
+    #[serde(default)]
+    content_properties: Option<serde_json::Value>,
+    #[serde(default)]
+    range: Option<ValueRange>,
+}
+
+impl TryFrom<RawContent> for Content {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: RawContent) -> Result<Self, Self::Error> {
+        Ok(Self {
+            content_properties: union_from_json(
+                "content_properties_type",
+                raw.content_properties_type,
+                "content_properties",
+                raw.content_properties,
+            )?,
+            range: raw.range,
+        })
+    }
+}
+
+/// Deserialization helper for [`ProcessUnit`], see [`RawContent`].
+#[derive(Deserialize)]
+struct RawProcessUnit {
+    #[serde(default)]
+    options_type: Option<serde_json::Value>,
+    #[serde(default)]
+    options: Option<serde_json::Value>,
+}
+
+impl TryFrom<RawProcessUnit> for ProcessUnit {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: RawProcessUnit) -> Result<Self, Self::Error> {
+        Ok(Self {
+            options: union_from_json("options_type", raw.options_type, "options", raw.options)?,
+        })
+    }
+}
+
+/// Deserializes a union from its flatc-style JSON representation: a `<field>_type` member naming
+/// the union member (or `NONE`), and the value itself.
+fn union_from_json<T: serde::de::DeserializeOwned>(
+    tag_field: &str,
+    tag: Option<serde_json::Value>,
+    value_field: &str,
+    value: Option<serde_json::Value>,
+) -> Result<Option<T>, serde_json::Error> {
+    match tag {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(tag)) if tag == "NONE" => Ok(None),
+        Some(tag) => {
+            let mut map = serde_json::Map::new();
+            map.insert(tag_field.into(), tag);
+            map.insert(
+                value_field.into(),
+                value.unwrap_or_else(|| serde_json::Value::Object(Default::default())),
+            );
+            serde_json::from_value(map.into()).map(Some)
+        }
+    }
+}
+
+serde_by_name!(
+    AssociatedFileType,
+    ColorSpaceType,
+    BoundingBoxType,
+    CoordinateType,
+    ScoreTransformationType
+);
+
+fn collect<'a, F, T>(v: Option<Vector<'a, ForwardsUOffset<F>>>) -> Vec<T>
+where
+    F: flatbuffers::Follow<'a> + 'a,
+    T: From<F::Inner>,
+{
+    v.map_or(Vec::new(), |v| v.iter().map(T::from).collect())
+}
+
+fn strings(v: Option<Vector<'_, ForwardsUOffset<&str>>>) -> Vec<String> {
+    v.map_or(Vec::new(), |v| v.iter().map(Into::into).collect())
+}
+
+fn floats(v: Option<Vector<'_, f32>>) -> Vec<f32> {
+    v.map_or(Vec::new(), |v| v.iter().collect())
+}
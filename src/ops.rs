@@ -0,0 +1,234 @@
+//! Operator inventory of a model, and checks against the operators supported by a runtime.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context};
+use serde::Serialize;
+
+use crate::{BuiltinOperator, Diagnostic, ModelGraph};
+
+/// An entry of `Model.operator_codes`, with the number of operators using it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OpUsage {
+    /// Index into [`ModelGraph::operator_codes`].
+    pub index: usize,
+    /// [`OperatorCode::name`][crate::OperatorCode::name] of the operator.
+    pub name: String,
+    /// The builtin operator, resolved from `builtin_code` and `deprecated_builtin_code`.
+    pub builtin: BuiltinOperator,
+    pub deprecated_builtin_code: i8,
+    pub builtin_code: BuiltinOperator,
+    pub is_custom: bool,
+    pub version: i32,
+    /// Number of operators using the code in each subgraph, by subgraph index.
+    pub counts: BTreeMap<usize, usize>,
+}
+
+impl OpUsage {
+    /// Returns the number of operators using the code across all subgraphs.
+    pub fn count(&self) -> usize {
+        self.counts.values().sum()
+    }
+}
+
+/// Lists the operator codes of `graph` and how often each is used.
+///
+/// Operators with an out of range `opcode_index` aren't counted; [`crate::validate`] doesn't check
+/// them either, but the runtime rejects such models.
+pub fn op_inventory(graph: &ModelGraph) -> Vec<OpUsage> {
+    let mut ops = graph
+        .operator_codes
+        .iter()
+        .enumerate()
+        .map(|(index, code)| OpUsage {
+            index,
+            name: code.name(),
+            builtin: code.builtin(),
+            deprecated_builtin_code: code.deprecated_builtin_code,
+            builtin_code: code.builtin_code,
+            is_custom: code.builtin() == BuiltinOperator::CUSTOM,
+            version: code.version,
+            counts: BTreeMap::new(),
+        })
+        .collect::<Vec<_>>();
+    for (s, sub) in graph.subgraphs.iter().enumerate() {
+        for op in &sub.operators {
+            if let Some(usage) = ops.get_mut(op.opcode_index as usize) {
+                *usage.counts.entry(s).or_default() += 1;
+            }
+        }
+    }
+    ops
+}
+
+/// The operators supported by a runtime build, with the highest supported version of each.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpAllowlist {
+    /// Maximum version by operator name; [`None`] allows all versions.
+    pub ops: BTreeMap<String, Option<i32>>,
+}
+
+impl OpAllowlist {
+    /// Parses an allowlist file.
+    ///
+    /// Each line holds an operator name (the builtin operator name such as `CONV_2D`, or the
+    /// custom code of a custom operator), optionally followed by the maximum supported version.
+    /// Empty lines and text after `#` are ignored.
+    pub fn parse(file: &str) -> anyhow::Result<Self> {
+        let mut ops = BTreeMap::new();
+        for (i, line) in file.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let Some(name) = fields.next() else {
+                continue;
+            };
+            let version = fields
+                .next()
+                .map(|v| v.parse::<i32>())
+                .transpose()
+                .with_context(|| format!("invalid version on line {}", i + 1))?;
+            if let Some(extra) = fields.next() {
+                bail!("unexpected `{extra}` on line {}", i + 1);
+            }
+            ops.insert(name.to_string(), version);
+        }
+        Ok(Self { ops })
+    }
+
+    /// Checks the operators of `ops` that are used by the graph against the allowlist.
+    pub fn check(&self, ops: &[OpUsage]) -> Vec<Diagnostic> {
+        let mut diags = Vec::new();
+        for op in ops.iter().filter(|op| op.count() > 0) {
+            let location = format!("operator code {} ({} v{})", op.index, op.name, op.version);
+            let message = match self.ops.get(&op.name) {
+                None => "operator is not supported by the runtime".to_string(),
+                Some(Some(max)) if op.version > *max => {
+                    format!("the runtime only supports versions up to {max}")
+                }
+                Some(_) => continue,
+            };
+            diags.push(Diagnostic { location, message });
+        }
+        diags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Operator, OperatorCode, SubGraph};
+
+    fn graph(codes: &[(BuiltinOperator, Option<&str>, i32)], opcodes: &[u32]) -> ModelGraph {
+        ModelGraph {
+            version: 3,
+            description: None,
+            operator_codes: codes
+                .iter()
+                .map(|&(builtin_code, custom_code, version)| OperatorCode {
+                    deprecated_builtin_code: builtin_code.0.min(127) as i8,
+                    builtin_code,
+                    custom_code: custom_code.map(Into::into),
+                    version,
+                })
+                .collect(),
+            subgraphs: vec![SubGraph {
+                name: None,
+                tensors: Vec::new(),
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+                operators: opcodes
+                    .iter()
+                    .map(|&opcode_index| Operator {
+                        opcode_index,
+                        inputs: Vec::new(),
+                        outputs: Vec::new(),
+                        custom_options_size: 0,
+                    })
+                    .collect(),
+            }],
+            signature_defs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parse_allowlist() {
+        let allowlist = OpAllowlist::parse(
+            "# TFLite Micro build\n\
+             CONV_2D 3\n\
+             \n\
+             ADD  # any version\n\
+             \tTFLite_Detection_PostProcess 1 # custom\n",
+        )
+        .unwrap();
+        assert_eq!(
+            allowlist.ops,
+            BTreeMap::from([
+                ("ADD".into(), None),
+                ("CONV_2D".into(), Some(3)),
+                ("TFLite_Detection_PostProcess".into(), Some(1)),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let e = OpAllowlist::parse("ADD\nCONV_2D 3 4\n").unwrap_err();
+        assert_eq!(e.to_string(), "unexpected `4` on line 2");
+        let e = OpAllowlist::parse("CONV_2D v3\n").unwrap_err();
+        assert_eq!(e.to_string(), "invalid version on line 1");
+    }
+
+    #[test]
+    fn inventory() {
+        let graph = graph(
+            &[
+                (BuiltinOperator::CONV_2D, None, 2),
+                (BuiltinOperator::CUSTOM, Some("Detect"), 1),
+                (BuiltinOperator::ADD, None, 1),
+            ],
+            &[0, 0, 1, 7],
+        );
+        let ops = op_inventory(&graph);
+        let counts = ops
+            .iter()
+            .map(|op| (&*op.name, op.is_custom, op.count()))
+            .collect::<Vec<_>>();
+        // The operator with the out of range opcode index isn't counted.
+        assert_eq!(
+            counts,
+            [
+                ("CONV_2D", false, 2),
+                ("Detect", true, 1),
+                ("ADD", false, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn check() {
+        let graph = graph(
+            &[
+                (BuiltinOperator::CONV_2D, None, 5),
+                (BuiltinOperator::FULLY_CONNECTED, None, 1),
+                (BuiltinOperator::CUSTOM, Some("Detect"), 1),
+                (BuiltinOperator::ADD, None, 9),
+                (BuiltinOperator::MUL, None, 1),
+            ],
+            &[0, 1, 2, 3],
+        );
+        let allowlist = OpAllowlist::parse("CONV_2D 3\nFULLY_CONNECTED 1\nADD\n").unwrap();
+        let diags = allowlist
+            .check(&op_inventory(&graph))
+            .iter()
+            .map(Diagnostic::to_string)
+            .collect::<Vec<_>>();
+        // MUL isn't in the allowlist either, but no operator uses it.
+        assert_eq!(
+            diags,
+            [
+                "operator code 0 (CONV_2D v5): the runtime only supports versions up to 3",
+                "operator code 2 (Detect v1): operator is not supported by the runtime",
+            ]
+        );
+    }
+}
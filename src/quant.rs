@@ -0,0 +1,437 @@
+//! Quantization report: the quantization and sparsity of each tensor, checked against the
+//! `Stats` and `NormalizationOptions` of the tensor metadata.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    ContentProperties, Diagnostic, ModelGraph, ModelInfo, NormalizationOptions, ProcessUnitOptions,
+    Quantization, SubGraph, SubGraphMetadata, Tensor, TensorMetadata, TensorType,
+};
+
+/// Relative tolerance when comparing stats and quantization parameters with the values implied by
+/// the normalization options.
+const TOLERANCE: f32 = 1e-2;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuantReport {
+    pub subgraphs: Vec<SubGraphQuant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubGraphQuant {
+    pub index: usize,
+    pub name: Option<String>,
+    pub tensors: Vec<TensorQuant>,
+    /// Number of non-constant tensors of each type.
+    pub activation_types: BTreeMap<String, usize>,
+    /// Whether the subgraph has both float and quantized integer activations.
+    pub mixed_precision: bool,
+    /// Mismatches between the tensors and their metadata.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Unusual but valid metadata, e.g. float-domain stats of quantized tensors.
+    pub notes: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TensorQuant {
+    pub index: usize,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: TensorType,
+    pub shape: Vec<i32>,
+    pub is_constant: bool,
+    /// [`Quantization::summary`][crate::Quantization::summary] of the tensor's quantization.
+    pub quantization: Option<String>,
+    /// [`Sparsity::describe`][crate::Sparsity::describe] of the tensor's storage format.
+    pub sparsity: Option<String>,
+}
+
+/// Lists the quantization of each tensor of `graph`, and checks the tensors described by `meta`.
+pub fn quant_report(meta: Option<&ModelInfo>, graph: &ModelGraph) -> QuantReport {
+    let subgraphs = graph
+        .subgraphs
+        .iter()
+        .enumerate()
+        .map(|(index, sub)| {
+            let mut activation_types = BTreeMap::new();
+            for tensor in sub.tensors.iter().filter(|t| !t.is_constant) {
+                let name = tensor.type_.variant_name().unwrap_or("UNKNOWN");
+                *activation_types.entry(name.to_string()).or_default() += 1;
+            }
+            let activations = sub.tensors.iter().filter(|t| !t.is_constant);
+            let mixed_precision =
+                activations.clone().any(is_float) && activations.clone().any(is_quantized_int);
+
+            let mut diagnostics = Vec::new();
+            let mut notes = Vec::new();
+            if let Some(sub_meta) = meta.and_then(|meta| meta.subgraph_metadata.get(index)) {
+                check_subgraph(&mut diagnostics, &mut notes, index, sub_meta, sub);
+            }
+
+            SubGraphQuant {
+                index,
+                name: sub.name.clone(),
+                tensors: sub
+                    .tensors
+                    .iter()
+                    .enumerate()
+                    .map(|(index, tensor)| TensorQuant {
+                        index,
+                        name: tensor.name.clone(),
+                        type_: tensor.type_,
+                        shape: tensor.shape.clone(),
+                        is_constant: tensor.is_constant,
+                        quantization: tensor.quantization.as_ref().map(|q| q.summary()),
+                        sparsity: tensor.sparsity.as_ref().map(|s| s.describe(&tensor.shape)),
+                    })
+                    .collect(),
+                activation_types,
+                mixed_precision,
+                diagnostics,
+                notes,
+            }
+        })
+        .collect();
+    QuantReport { subgraphs }
+}
+
+fn is_float(tensor: &Tensor) -> bool {
+    matches!(
+        tensor.type_,
+        TensorType::FLOAT16 | TensorType::FLOAT32 | TensorType::FLOAT64
+    )
+}
+
+fn is_quantized_int(tensor: &Tensor) -> bool {
+    int_range(tensor.type_).is_some()
+        && tensor
+            .quantization
+            .as_ref()
+            .is_some_and(|q| q.is_quantized())
+}
+
+/// Returns the value range of the integer types used for quantization.
+fn int_range(type_: TensorType) -> Option<(f32, f32)> {
+    match type_ {
+        TensorType::UINT8 => Some((0.0, 255.0)),
+        TensorType::INT8 => Some((-128.0, 127.0)),
+        TensorType::INT16 => Some((-32768.0, 32767.0)),
+        _ => None,
+    }
+}
+
+fn check_subgraph(
+    diags: &mut Vec<Diagnostic>,
+    notes: &mut Vec<Diagnostic>,
+    index: usize,
+    meta: &SubGraphMetadata,
+    sub: &SubGraph,
+) {
+    for (kind, tensor_meta, tensors) in [
+        ("input", &meta.input_tensor_metadata, &sub.inputs),
+        ("output", &meta.output_tensor_metadata, &sub.outputs),
+    ] {
+        for (i, (tensor_meta, &tensor)) in tensor_meta.iter().zip(tensors).enumerate() {
+            let Some(tensor) = sub.tensor(tensor) else {
+                continue;
+            };
+            let location = format!(
+                "subgraph {index} {kind} {i} ({:?})",
+                tensor_meta.name.as_deref().unwrap_or("<unnamed>"),
+            );
+            check_tensor(diags, notes, &location, tensor_meta, tensor);
+        }
+    }
+}
+
+fn check_tensor(
+    diags: &mut Vec<Diagnostic>,
+    notes: &mut Vec<Diagnostic>,
+    location: &str,
+    meta: &TensorMetadata,
+    tensor: &Tensor,
+) {
+    let diagnostic = |message: String| Diagnostic {
+        location: location.into(),
+        message,
+    };
+    let mut error = |message: String| diags.push(diagnostic(message));
+    let type_ = tensor.type_;
+    let quant = tensor.quantization.as_ref().filter(|q| q.is_quantized());
+    let norm = meta
+        .process_units
+        .iter()
+        .find_map(|unit| match &unit.options {
+            Some(ProcessUnitOptions::NormalizationOptions(norm)) => Some(norm),
+            _ => None,
+        });
+    let is_image = matches!(
+        meta.content
+            .as_ref()
+            .and_then(|c| c.content_properties.as_ref()),
+        Some(ContentProperties::ImageProperties(_))
+    );
+
+    // Stats of float image inputs are given in the domain of the normalized values. Those of
+    // quantized tensors may be quantized or float values, which is only worth a note.
+    if let Some(stats) = &meta.stats {
+        if let (Some((min, max)), Some(_)) = (int_range(type_), quant) {
+            let outside = stats
+                .min
+                .iter()
+                .chain(&stats.max)
+                .any(|&v| v.fract() != 0.0 || v < min || v > max);
+            if outside {
+                notes.push(diagnostic(format!(
+                    "stats (min {:?}, max {:?}) of the quantized tensor are float values, not \
+                     {type_:?} values ({min}..{max})",
+                    stats.min, stats.max,
+                )));
+            }
+        }
+        let norm = norm.filter(|norm| !norm.mean.is_empty() && !norm.std.is_empty());
+        if let (true, Some(norm), true) = (is_float(tensor), norm, is_image) {
+            let channels = norm.mean.len().max(norm.std.len());
+            let expected = |pixel: f32| {
+                (0..channels)
+                    .map(|c| (pixel - at(&norm.mean, c)) / at(&norm.std, c))
+                    .collect::<Vec<_>>()
+            };
+            let (min, max) = (expected(0.0), expected(255.0));
+            if !matches_per_channel(&stats.min, &min) || !matches_per_channel(&stats.max, &max) {
+                error(format!(
+                    "stats (min {:?}, max {:?}) don't match the normalized pixel range \
+                     (min {min:?}, max {max:?}) of the NormalizationOptions",
+                    stats.min, stats.max,
+                ));
+            }
+        }
+    }
+
+    // Like the TFLite Task Library, quantized image inputs are fed raw pixel values, so the
+    // quantization has to implement the normalization.
+    if let (TensorType::UINT8, Some(quant), Some(norm), true) = (type_, quant, norm, is_image) {
+        if !quantization_matches(quant, norm) {
+            error(format!(
+                "NormalizationOptions (mean {:?}, std {:?}) don't match the quantization ({}), \
+                 which implies mean = zero point and std = 1 / scale",
+                norm.mean,
+                norm.std,
+                quant.summary(),
+            ));
+        }
+    }
+}
+
+/// Returns the value of channel `c` of non-empty per-channel `values`, which may be given once for
+/// all channels.
+fn at<T: Copy>(values: &[T], c: usize) -> T {
+    values[c.min(values.len() - 1)]
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() <= TOLERANCE * a.abs().max(b.abs()).max(1.0)
+}
+
+fn matches_per_channel(actual: &[f32], expected: &[f32]) -> bool {
+    actual.is_empty()
+        || expected.is_empty()
+        || (0..actual.len().max(expected.len())).all(|c| close(at(actual, c), at(expected, c)))
+}
+
+fn quantization_matches(quant: &Quantization, norm: &NormalizationOptions) -> bool {
+    if quant.scale.is_empty() || norm.mean.is_empty() || norm.std.is_empty() {
+        return true;
+    }
+    let channels = norm.mean.len().max(norm.std.len()).max(quant.scale.len());
+    (0..channels).all(|c| {
+        let zero_point = match &quant.zero_point[..] {
+            [] => 0.0,
+            zero_points => at(zero_points, c) as f32,
+        };
+        (zero_point - at(&norm.mean, c)).abs() <= 1.0
+            && close(at(&quant.scale, c) * at(&norm.std, c), 1.0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorSpaceType, Content, ImageProperties, ProcessUnit, Stats};
+
+    fn tensor(name: &str, type_: TensorType, quantization: Option<(f32, i64)>) -> Tensor {
+        Tensor {
+            name: Some(name.into()),
+            shape: vec![1, 4, 4, 3],
+            shape_signature: None,
+            type_,
+            buffer: 0,
+            is_constant: false,
+            is_variable: false,
+            quantization: quantization.map(|(scale, zero_point)| Quantization {
+                scale: vec![scale],
+                zero_point: vec![zero_point],
+                min: Vec::new(),
+                max: Vec::new(),
+                quantized_dimension: 0,
+                custom: None,
+            }),
+            sparsity: None,
+        }
+    }
+
+    /// A graph whose single subgraph has `tensors`, with the first as input and the last as
+    /// output.
+    fn graph(tensors: Vec<Tensor>) -> ModelGraph {
+        ModelGraph {
+            version: 3,
+            description: None,
+            operator_codes: Vec::new(),
+            subgraphs: vec![SubGraph {
+                name: None,
+                inputs: vec![0],
+                outputs: vec![tensors.len() as i32 - 1],
+                tensors,
+                operators: Vec::new(),
+            }],
+            signature_defs: Vec::new(),
+        }
+    }
+
+    /// Metadata of an image input with MobileNet normalization and the given stats.
+    fn image_input(stats: Option<(f32, f32)>) -> ModelInfo {
+        let input = TensorMetadata {
+            name: Some("image".into()),
+            content: Some(Content {
+                content_properties: Some(ContentProperties::ImageProperties(ImageProperties {
+                    color_space: ColorSpaceType::RGB,
+                    default_size: None,
+                })),
+                range: None,
+            }),
+            process_units: vec![ProcessUnit {
+                options: Some(ProcessUnitOptions::NormalizationOptions(
+                    NormalizationOptions {
+                        mean: vec![127.5],
+                        std: vec![127.5],
+                    },
+                )),
+            }],
+            stats: stats.map(|(min, max)| Stats {
+                min: vec![min],
+                max: vec![max],
+            }),
+            ..Default::default()
+        };
+        ModelInfo {
+            subgraph_metadata: vec![SubGraphMetadata {
+                input_tensor_metadata: vec![input],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn report(meta: &ModelInfo, tensors: Vec<Tensor>) -> SubGraphQuant {
+        quant_report(Some(meta), &graph(tensors))
+            .subgraphs
+            .remove(0)
+    }
+
+    fn messages(diags: &[Diagnostic]) -> Vec<String> {
+        diags.iter().map(Diagnostic::to_string).collect()
+    }
+
+    #[test]
+    fn float_stats_match_normalization() {
+        let input = || tensor("image", TensorType::FLOAT32, None);
+        let sub = report(&image_input(Some((-1.0, 1.0))), vec![input()]);
+        assert_eq!(sub.diagnostics, []);
+        assert_eq!(sub.notes, []);
+
+        let sub = report(&image_input(Some((0.0, 1.0))), vec![input()]);
+        assert_eq!(
+            messages(&sub.diagnostics),
+            [
+                r#"subgraph 0 input 0 ("image"): stats (min [0.0], max [1.0]) don't match the normalized pixel range (min [-1.0], max [1.0]) of the NormalizationOptions"#
+            ]
+        );
+    }
+
+    #[test]
+    fn uint8_quantization_matches_normalization() {
+        let meta = image_input(None);
+        let sub = report(
+            &meta,
+            vec![tensor("image", TensorType::UINT8, Some((1.0 / 127.5, 128)))],
+        );
+        assert_eq!(sub.diagnostics, []);
+
+        let sub = report(
+            &meta,
+            vec![tensor("image", TensorType::UINT8, Some((1.0 / 255.0, 0)))],
+        );
+        assert_eq!(
+            messages(&sub.diagnostics),
+            [
+                r#"subgraph 0 input 0 ("image"): NormalizationOptions (mean [127.5], std [127.5]) don't match the quantization (scale 0.003921569, zero point 0), which implies mean = zero point and std = 1 / scale"#
+            ]
+        );
+    }
+
+    #[test]
+    fn float_stats_of_quantized_tensor_are_a_note() {
+        let sub = report(
+            &image_input(Some((-1.0, 1.0))),
+            vec![tensor("image", TensorType::UINT8, Some((1.0 / 127.5, 128)))],
+        );
+        assert_eq!(sub.diagnostics, []);
+        assert_eq!(
+            messages(&sub.notes),
+            [
+                r#"subgraph 0 input 0 ("image"): stats (min [-1.0], max [1.0]) of the quantized tensor are float values, not UINT8 values (0..255)"#
+            ]
+        );
+
+        // Stats in the quantized domain are fine.
+        let sub = report(
+            &image_input(Some((0.0, 255.0))),
+            vec![tensor("image", TensorType::UINT8, Some((1.0 / 127.5, 128)))],
+        );
+        assert_eq!(sub.notes, []);
+    }
+
+    #[test]
+    fn mixed_precision() {
+        let meta = ModelInfo::default();
+        let mut weights = tensor("weights", TensorType::FLOAT32, None);
+        weights.is_constant = true;
+        let int8 = || tensor("x", TensorType::INT8, Some((0.5, 0)));
+
+        let sub = report(&meta, vec![int8(), weights.clone(), int8()]);
+        assert!(!sub.mixed_precision);
+        assert_eq!(sub.activation_types, BTreeMap::from([("INT8".into(), 2)]));
+
+        let sub = report(
+            &meta,
+            vec![int8(), weights, tensor("y", TensorType::FLOAT32, None)],
+        );
+        assert!(sub.mixed_precision);
+        assert_eq!(
+            sub.activation_types,
+            BTreeMap::from([("FLOAT32".into(), 1), ("INT8".into(), 1)])
+        );
+
+        // Unquantized integer tensors (e.g. indices) don't count as quantized activations.
+        let sub = report(
+            &meta,
+            vec![
+                tensor("indices", TensorType::INT8, None),
+                tensor("y", TensorType::FLOAT32, None),
+            ],
+        );
+        assert!(!sub.mixed_precision);
+    }
+}
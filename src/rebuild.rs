@@ -0,0 +1,560 @@
+//! Deep copy of model flatbuffers, driven by the tables declared in `schema_v3c.fbs`.
+//!
+//! The generated code has no object API, and the model schema has too many tables (most of them
+//! operator options) to copy each one by hand. Instead, the schema is parsed at runtime, and tables
+//! are copied field by field according to their declared types. Fields are copied as they are
+//! stored, including deprecated fields and fields set to their default value.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+use anyhow::{bail, ensure, Context};
+
+use flatbuffers::{FlatBufferBuilder, Push, UnionWIPOffset, WIPOffset};
+
+use crate::buffer::is_external;
+
+/// Offset of a copied table, string or vector in the builder.
+pub(crate) type Offset = WIPOffset<UnionWIPOffset>;
+
+/// Fields holding offsets of data stored after the flatbuffer, which move with the data.
+const EXTERNAL_OFFSETS: [(&str, &str); 2] = [
+    ("Buffer", "offset"),
+    ("Operator", "large_custom_options_offset"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    /// A scalar or enum value of the given size in bytes.
+    Scalar(usize),
+    String,
+    /// A vector, with the alignment given by its `force_align` attribute (or 1).
+    Vector(Box<Type>, usize),
+    Table(String),
+    /// The value of a union, whose type is stored in the preceding field.
+    Union(String),
+}
+
+#[derive(Debug)]
+struct Field {
+    name: String,
+    type_: Type,
+}
+
+/// The tables and unions of a flatbuffers schema.
+#[derive(Debug, Default)]
+pub(crate) struct Schema {
+    /// Fields of each table, in vtable order.
+    tables: HashMap<String, Vec<Field>>,
+    /// Member tables of each union, by type value.
+    unions: HashMap<String, HashMap<u8, String>>,
+}
+
+impl Schema {
+    /// Returns the parsed `schema_v3c.fbs`.
+    pub(crate) fn model() -> &'static Schema {
+        static SCHEMA: OnceLock<Schema> = OnceLock::new();
+        SCHEMA.get_or_init(|| {
+            Schema::parse(include_str!("../schemas/schema_v3c.fbs"))
+                .expect("the vendored model schema is supported")
+        })
+    }
+
+    /// Parses the subset of the schema language used by the model schema: tables, enums and
+    /// unions, without structs, vectors of unions or explicit field ids.
+    fn parse(source: &str) -> anyhow::Result<Self> {
+        let mut tokens = Tokens::new(source);
+        let mut enums = HashMap::new();
+        let mut tables = Vec::new();
+        let mut unions = HashMap::new();
+        while let Some(token) = tokens.next() {
+            match token {
+                "table" => {
+                    let name = tokens.ident()?;
+                    tokens.attributes()?;
+                    tokens.expect("{")?;
+                    let mut fields = Vec::new();
+                    while tokens.peek() != Some("}") {
+                        let field = tokens.ident()?;
+                        tokens.expect(":")?;
+                        let type_ = tokens.type_()?;
+                        if tokens.peek() == Some("=") {
+                            tokens.next();
+                            tokens.ident()?;
+                        }
+                        let attributes = tokens.attributes()?;
+                        ensure!(
+                            !attributes.contains_key("id"),
+                            "field `{name}.{field}` has an explicit id"
+                        );
+                        let align = match attributes.get("force_align") {
+                            Some(align) => align.parse()?,
+                            None => 1,
+                        };
+                        tokens.expect(";")?;
+                        fields.push((field.to_string(), type_, align));
+                    }
+                    tokens.expect("}")?;
+                    tables.push((name.to_string(), fields));
+                }
+                "enum" => {
+                    let name = tokens.ident()?;
+                    tokens.expect(":")?;
+                    let size = scalar_size(tokens.ident()?)
+                        .with_context(|| format!("enum `{name}` has no integer type"))?;
+                    tokens.attributes()?;
+                    tokens.skip_block()?;
+                    enums.insert(name.to_string(), size);
+                }
+                "union" => {
+                    let name = tokens.ident()?;
+                    tokens.attributes()?;
+                    tokens.expect("{")?;
+                    let mut members = HashMap::new();
+                    let mut value = 0;
+                    while tokens.peek() != Some("}") {
+                        let mut member = tokens.ident()?;
+                        if tokens.peek() == Some(":") {
+                            tokens.next();
+                            member = tokens.ident()?;
+                        }
+                        value += 1;
+                        if tokens.peek() == Some("=") {
+                            tokens.next();
+                            value = tokens.ident()?.parse()?;
+                        }
+                        members.insert(value, member.to_string());
+                        if tokens.peek() == Some(",") {
+                            tokens.next();
+                        }
+                    }
+                    tokens.expect("}")?;
+                    unions.insert(name.to_string(), members);
+                }
+                "struct" => bail!("structs are not supported"),
+                "namespace" | "include" | "attribute" | "file_identifier" | "file_extension"
+                | "root_type" => while tokens.next().context("unterminated declaration")? != ";" {},
+                _ => bail!("unexpected `{token}`"),
+            }
+        }
+
+        let table_names = tables
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let resolve = |name: &str| -> anyhow::Result<Type> {
+            if let Some(size) = scalar_size(name).or_else(|| enums.get(name).copied()) {
+                Ok(Type::Scalar(size))
+            } else if name == "string" {
+                Ok(Type::String)
+            } else if table_names.iter().any(|table| table == name) {
+                Ok(Type::Table(name.into()))
+            } else if unions.contains_key(name) {
+                Ok(Type::Union(name.into()))
+            } else {
+                bail!("unknown type `{name}`")
+            }
+        };
+        let mut schema = Schema::default();
+        for (name, fields) in tables {
+            let mut resolved = Vec::new();
+            for (field, type_, align) in fields {
+                let type_ = match type_.strip_prefix('[') {
+                    Some(elem) => match resolve(elem.trim_end_matches(']'))? {
+                        Type::Union(_) => bail!("vectors of unions are not supported"),
+                        elem => Type::Vector(Box::new(elem), align),
+                    },
+                    None => resolve(&type_)?,
+                };
+                if let Type::Union(_) = type_ {
+                    resolved.push(Field {
+                        name: format!("{field}_type"),
+                        type_: Type::Scalar(1),
+                    });
+                }
+                resolved.push(Field { name: field, type_ });
+            }
+            schema.tables.insert(name, resolved);
+        }
+        schema.unions = unions;
+        Ok(schema)
+    }
+}
+
+fn scalar_size(name: &str) -> Option<usize> {
+    match name {
+        "bool" | "byte" | "ubyte" | "int8" | "uint8" => Some(1),
+        "short" | "ushort" | "int16" | "uint16" => Some(2),
+        "int" | "uint" | "float" | "int32" | "uint32" | "float32" => Some(4),
+        "long" | "ulong" | "double" | "int64" | "uint64" | "float64" => Some(8),
+        _ => None,
+    }
+}
+
+struct Tokens<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(source: &'a str) -> Self {
+        let mut tokens = Vec::new();
+        for line in source.lines() {
+            let line = line.split("//").next().unwrap_or_default();
+            let mut rest = line.trim_start();
+            while let Some(c) = rest.chars().next() {
+                let len = if c == '"' {
+                    rest[1..].find('"').map_or(rest.len(), |end| end + 2)
+                } else if c.is_alphanumeric() || "_.-+".contains(c) {
+                    rest.find(|c: char| !(c.is_alphanumeric() || "_.-+".contains(c)))
+                        .unwrap_or(rest.len())
+                } else {
+                    c.len_utf8()
+                };
+                tokens.push(&rest[..len]);
+                rest = rest[len..].trim_start();
+            }
+        }
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek()?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> anyhow::Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            token => bail!("expected `{expected}`, found {token:?}"),
+        }
+    }
+
+    fn ident(&mut self) -> anyhow::Result<&'a str> {
+        match self.next() {
+            Some(token)
+                if token
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "_.-+".contains(c)) =>
+            {
+                Ok(token)
+            }
+            token => bail!("expected an identifier, found {token:?}"),
+        }
+    }
+
+    /// Parses a type name, with vector types returned as `[element]`.
+    fn type_(&mut self) -> anyhow::Result<String> {
+        if self.peek() == Some("[") {
+            self.next();
+            let elem = self.ident()?;
+            self.expect("]")?;
+            Ok(format!("[{elem}]"))
+        } else {
+            Ok(self.ident()?.into())
+        }
+    }
+
+    /// Parses an optional `(key: value, ...)` attribute list.
+    fn attributes(&mut self) -> anyhow::Result<HashMap<&'a str, &'a str>> {
+        let mut attributes = HashMap::new();
+        if self.peek() != Some("(") {
+            return Ok(attributes);
+        }
+        self.next();
+        while self.peek() != Some(")") {
+            let key = self.ident()?;
+            let mut value = "";
+            if self.peek() == Some(":") {
+                self.next();
+                value = self.next().context("unterminated attributes")?;
+            }
+            attributes.insert(key, value);
+            if self.peek() == Some(",") {
+                self.next();
+            }
+        }
+        self.expect(")")?;
+        Ok(attributes)
+    }
+
+    fn skip_block(&mut self) -> anyhow::Result<()> {
+        self.expect("{")?;
+        while self.next().context("unterminated block")? != "}" {}
+        Ok(())
+    }
+}
+
+/// Copies tables of a model flatbuffer into a new builder.
+pub(crate) struct Copier<'a> {
+    schema: &'static Schema,
+    buf: &'a [u8],
+    /// New offsets of data stored after the flatbuffer, by old offset; offsets that aren't in the
+    /// map are kept.
+    pub(crate) relocate: HashMap<u64, u64>,
+}
+
+impl<'a> Copier<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self {
+            schema: Schema::model(),
+            buf,
+            relocate: HashMap::new(),
+        }
+    }
+
+    /// Copies the table `name` at `loc`, and everything it references.
+    pub(crate) fn table(
+        &self,
+        fbb: &mut FlatBufferBuilder<'_>,
+        loc: usize,
+        name: &str,
+    ) -> anyhow::Result<Offset> {
+        self.table_with(fbb, loc, name, &[])
+    }
+
+    /// Copies the table `name` at `loc`, replacing the fields named in `replace` with the given
+    /// offsets.
+    pub(crate) fn table_with(
+        &self,
+        fbb: &mut FlatBufferBuilder<'_>,
+        loc: usize,
+        name: &str,
+        replace: &[(&str, Offset)],
+    ) -> anyhow::Result<Offset> {
+        enum Value<'b> {
+            Scalar(&'b [u8]),
+            Offset(Offset),
+        }
+
+        let fields = self
+            .schema
+            .tables
+            .get(name)
+            .with_context(|| format!("unknown table `{name}`"))?;
+        let vtable = loc
+            .checked_add_signed(-(self.i32_at(loc)? as isize))
+            .context("invalid vtable offset")?;
+        let slots = (self.u16_at(vtable)? as usize).saturating_sub(4) / 2;
+        for slot in fields.len()..slots {
+            if self.field(loc, slot)?.is_some() {
+                bail!("`{name}` table has fields that are not part of the supported schema");
+            }
+        }
+
+        let mut values = Vec::new();
+        for (slot, field) in fields.iter().enumerate() {
+            let voffset = 4 + 2 * slot as u16;
+            if let Some(&(_, offset)) = replace.iter().find(|(f, _)| *f == field.name) {
+                values.push((voffset, Value::Offset(offset)));
+                continue;
+            }
+            let Some(pos) = self.field(loc, slot)? else {
+                continue;
+            };
+            let value = match &field.type_ {
+                Type::Scalar(size) => Value::Scalar(self.bytes(pos, *size)?),
+                Type::String => Value::Offset(self.string(fbb, self.deref(pos)?)?),
+                Type::Vector(elem, align) => {
+                    Value::Offset(self.vector(fbb, self.deref(pos)?, elem, *align)?)
+                }
+                Type::Table(table) => Value::Offset(self.table(fbb, self.deref(pos)?, table)?),
+                Type::Union(union) => {
+                    let Some(tag) = self.field(loc, slot - 1)? else {
+                        continue;
+                    };
+                    let tag = self.bytes(tag, 1)?[0];
+                    if tag == 0 {
+                        continue;
+                    }
+                    let member = self.schema.unions[union]
+                        .get(&tag)
+                        .with_context(|| format!("unknown `{union}` type {tag}"))?;
+                    Value::Offset(self.table(fbb, self.deref(pos)?, member)?)
+                }
+            };
+            values.push((voffset, value));
+        }
+
+        // Like the builders generated by flatc, store the largest fields first to avoid padding,
+        // and fields of the same size in reverse order.
+        values.sort_by_key(|(voffset, value)| {
+            let size = match value {
+                Value::Scalar(bytes) => bytes.len(),
+                Value::Offset(_) => 4,
+            };
+            std::cmp::Reverse((size, *voffset))
+        });
+        let start = fbb.start_table();
+        for (voffset, value) in values {
+            match value {
+                Value::Offset(offset) => fbb.push_slot_always(voffset, offset),
+                Value::Scalar(bytes) => match *bytes {
+                    [b] => fbb.push_slot_always(voffset, b),
+                    [b0, b1] => fbb.push_slot_always(voffset, u16::from_le_bytes([b0, b1])),
+                    [b0, b1, b2, b3] => {
+                        fbb.push_slot_always(voffset, u32::from_le_bytes([b0, b1, b2, b3]))
+                    }
+                    _ => {
+                        let mut value = u64::from_le_bytes(bytes.try_into()?);
+                        let field = &fields[(voffset as usize - 4) / 2].name;
+                        if EXTERNAL_OFFSETS.contains(&(name, field)) && is_external(value) {
+                            value = self.relocate.get(&value).copied().unwrap_or(value);
+                        }
+                        fbb.push_slot_always(voffset, value)
+                    }
+                },
+            }
+        }
+        Ok(WIPOffset::new(fbb.end_table(start).value()))
+    }
+
+    /// Returns the positions of the tables in the vector field `field` of the table `name` at
+    /// `loc`.
+    pub(crate) fn table_locs(
+        &self,
+        loc: usize,
+        name: &str,
+        field: &str,
+    ) -> anyhow::Result<Vec<usize>> {
+        let slot = self.schema.tables[name]
+            .iter()
+            .position(|f| f.name == field)
+            .with_context(|| format!("`{name}` has no field `{field}`"))?;
+        let Some(pos) = self.field(loc, slot)? else {
+            return Ok(Vec::new());
+        };
+        let vector = self.deref(pos)?;
+        (0..self.u32_at(vector)? as usize)
+            .map(|i| self.deref(vector + 4 + 4 * i))
+            .collect()
+    }
+
+    fn vector(
+        &self,
+        fbb: &mut FlatBufferBuilder<'_>,
+        pos: usize,
+        elem: &Type,
+        align: usize,
+    ) -> anyhow::Result<Offset> {
+        let len = self.u32_at(pos)? as usize;
+        let start = pos + 4;
+        let offsets = match elem {
+            Type::Scalar(size) => {
+                let data = self.bytes(start, len * size)?;
+                return raw_vector(fbb, data, *size, align.max(*size));
+            }
+            Type::String => (0..len)
+                .map(|i| self.string(fbb, self.deref(start + 4 * i)?))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            Type::Table(table) => (0..len)
+                .map(|i| self.table(fbb, self.deref(start + 4 * i)?, table))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            Type::Vector(..) | Type::Union(_) => bail!("unsupported vector element {elem:?}"),
+        };
+        Ok(WIPOffset::new(fbb.create_vector(&offsets).value()))
+    }
+
+    fn string(&self, fbb: &mut FlatBufferBuilder<'_>, pos: usize) -> anyhow::Result<Offset> {
+        let len = self.u32_at(pos)? as usize;
+        let bytes = self.bytes(pos + 4, len)?;
+        Ok(WIPOffset::new(fbb.create_byte_string(bytes).value()))
+    }
+
+    /// Returns the position of field `slot` of the table at `loc`, if it is present.
+    fn field(&self, loc: usize, slot: usize) -> anyhow::Result<Option<usize>> {
+        let vtable = loc
+            .checked_add_signed(-(self.i32_at(loc)? as isize))
+            .context("invalid vtable offset")?;
+        let voffset = 4 + 2 * slot;
+        if voffset + 2 > self.u16_at(vtable)? as usize {
+            return Ok(None);
+        }
+        let offset = self.u16_at(vtable + voffset)? as usize;
+        Ok((offset != 0).then_some(loc + offset))
+    }
+
+    /// Follows the offset stored at `pos`.
+    fn deref(&self, pos: usize) -> anyhow::Result<usize> {
+        Ok(pos + self.u32_at(pos)? as usize)
+    }
+
+    fn bytes(&self, pos: usize, len: usize) -> anyhow::Result<&'a [u8]> {
+        pos.checked_add(len)
+            .and_then(|end| self.buf.get(pos..end))
+            .with_context(|| format!("{len} bytes at {pos} exceed the flatbuffer"))
+    }
+
+    fn u16_at(&self, pos: usize) -> anyhow::Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(pos, 2)?.try_into()?))
+    }
+
+    fn u32_at(&self, pos: usize) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(pos, 4)?.try_into()?))
+    }
+
+    fn i32_at(&self, pos: usize) -> anyhow::Result<i32> {
+        Ok(i32::from_le_bytes(self.bytes(pos, 4)?.try_into()?))
+    }
+}
+
+#[repr(align(4))]
+struct Align4;
+#[repr(align(8))]
+struct Align8;
+#[repr(align(16))]
+struct Align16;
+
+/// A vector element of `N` bytes, aligned like `A`.
+struct Elem<const N: usize, A>([u8; N], PhantomData<A>);
+
+impl<const N: usize, A> Push for Elem<N, A> {
+    // Only used to derive the alignment.
+    type Output = A;
+
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        dst.copy_from_slice(&self.0);
+    }
+
+    fn size() -> usize {
+        N
+    }
+}
+
+/// Creates a vector of `size`-byte scalars from their little-endian `data`, aligned to `align`.
+fn raw_vector(
+    fbb: &mut FlatBufferBuilder<'_>,
+    data: &[u8],
+    size: usize,
+    align: usize,
+) -> anyhow::Result<Offset> {
+    fn create<const N: usize, A: 'static>(fbb: &mut FlatBufferBuilder<'_>, data: &[u8]) -> Offset {
+        let items = data
+            .chunks_exact(N)
+            .map(|c| Elem::<N, A>(c.try_into().unwrap(), PhantomData))
+            .collect::<Vec<_>>();
+        WIPOffset::new(fbb.create_vector(&items).value())
+    }
+
+    // The builder aligns vectors to at least 4 bytes anyway.
+    Ok(match (size, align.max(4)) {
+        (1, 4) => create::<1, Align4>(fbb, data),
+        (1, 8) => create::<1, Align8>(fbb, data),
+        (1, 16) => create::<1, Align16>(fbb, data),
+        (2, 4) => create::<2, Align4>(fbb, data),
+        (2, 8) => create::<2, Align8>(fbb, data),
+        (2, 16) => create::<2, Align16>(fbb, data),
+        (4, 4) => create::<4, Align4>(fbb, data),
+        (4, 8) => create::<4, Align8>(fbb, data),
+        (4, 16) => create::<4, Align16>(fbb, data),
+        (8, 8) => create::<8, Align8>(fbb, data),
+        (8, 16) => create::<8, Align16>(fbb, data),
+        _ => bail!("unsupported alignment {align} for {size}-byte elements"),
+    })
+}
@@ -0,0 +1,87 @@
+//! Owned mirror of the types in `image_segmenter_metadata_schema.fbs`.
+
+use serde::Serialize;
+
+use crate::image_segmenter::mediapipe::tasks as fb;
+
+pub use fb::Activation;
+
+/// Name of the [`CustomMetadata`][crate::CustomMetadata] entry that holds an
+/// [`ImageSegmenterOptions`] flatbuffer.
+pub const SEGMENTER_METADATA_NAME: &str = "SEGMENTER_METADATA";
+
+/// Options of a MediaPipe image segmenter, stored as `SEGMENTER_METADATA` custom metadata.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageSegmenterOptions {
+    /// Activation function of the output layer.
+    pub activation: Activation,
+    pub min_parser_version: Option<String>,
+}
+
+impl ImageSegmenterOptions {
+    /// Parses a serialized `ImageSegmenterOptions` flatbuffer.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let options = flatbuffers::root::<fb::ImageSegmenterOptions>(bytes)?;
+        Ok(options.into())
+    }
+}
+
+impl From<fb::ImageSegmenterOptions<'_>> for ImageSegmenterOptions {
+    fn from(options: fb::ImageSegmenterOptions<'_>) -> Self {
+        Self {
+            activation: options.activation(),
+            min_parser_version: options.min_parser_version().map(Into::into),
+        }
+    }
+}
+
+serde_by_name!(Activation);
+
+#[cfg(test)]
+mod tests {
+    use flatbuffers::FlatBufferBuilder;
+
+    use super::*;
+    use crate::decode_custom_metadata;
+
+    fn segmenter_metadata(activation: Activation, min_parser_version: Option<&str>) -> Vec<u8> {
+        let mut fbb = FlatBufferBuilder::new();
+        let min_parser_version = min_parser_version.map(|v| fbb.create_string(v));
+        let args = fb::ImageSegmenterOptionsArgs {
+            activation,
+            min_parser_version,
+        };
+        let root = fb::ImageSegmenterOptions::create(&mut fbb, &args);
+        fb::finish_image_segmenter_options_buffer(&mut fbb, root);
+        fbb.finished_data().to_vec()
+    }
+
+    #[test]
+    fn decode() {
+        let data = segmenter_metadata(Activation::SOFTMAX, Some("1.0.0"));
+        assert_eq!(
+            ImageSegmenterOptions::from_bytes(&data).unwrap(),
+            ImageSegmenterOptions {
+                activation: Activation::SOFTMAX,
+                min_parser_version: Some("1.0.0".into()),
+            }
+        );
+
+        let decoded = decode_custom_metadata(Some(SEGMENTER_METADATA_NAME), &data).unwrap();
+        assert_eq!(decoded.format, "ImageSegmenterOptions");
+        assert_eq!(
+            decoded.value,
+            serde_json::json!({ "activation": "SOFTMAX", "min_parser_version": "1.0.0" })
+        );
+        assert_eq!(
+            decoded.text,
+            "activation: SOFTMAX\nmin_parser_version: 1.0.0"
+        );
+
+        // The default activation is omitted from the flatbuffer.
+        let data = segmenter_metadata(Activation::NONE, None);
+        let options = ImageSegmenterOptions::from_bytes(&data).unwrap();
+        assert_eq!(options.activation, Activation::NONE);
+        assert!(decode_custom_metadata(Some(SEGMENTER_METADATA_NAME), &data[..6]).is_err());
+    }
+}
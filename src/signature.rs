@@ -0,0 +1,253 @@
+//! Signatures of a model, resolved to the tensors of their subgraph and the metadata describing
+//! those tensors.
+
+use serde::Serialize;
+
+use crate::{ModelGraph, ModelInfo, SubGraph, TensorMap, TensorMetadata, TensorType};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Signature {
+    pub signature_key: Option<String>,
+    pub subgraph_index: u32,
+    /// Name of the subgraph, if it exists.
+    pub subgraph_name: Option<String>,
+    pub inputs: Vec<SignatureTensor>,
+    pub outputs: Vec<SignatureTensor>,
+}
+
+/// A signature input or output.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SignatureTensor {
+    /// The name of the input or output in the signature.
+    pub alias: Option<String>,
+    pub tensor_index: u32,
+    /// Name of the tensor in the subgraph. The tensor fields are [`None`] if the index is out of
+    /// range.
+    pub tensor_name: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: Option<TensorType>,
+    pub shape: Option<Vec<i32>>,
+    /// Index of the [`TensorMetadata`] in the `input_tensor_metadata` or
+    /// `output_tensor_metadata` of the subgraph's metadata, if the tensor has any.
+    pub metadata_index: Option<usize>,
+    /// Name of that [`TensorMetadata`].
+    pub metadata_name: Option<String>,
+}
+
+/// Resolves the signatures of `graph`, and the tensor metadata of their inputs and outputs.
+///
+/// Tensor metadata entries describe the subgraph inputs and outputs at the same index, so
+/// signature tensors that aren't inputs or outputs of their subgraph have no metadata.
+pub fn signatures(meta: Option<&ModelInfo>, graph: &ModelGraph) -> Vec<Signature> {
+    graph
+        .signature_defs
+        .iter()
+        .map(|def| {
+            let sub = graph.subgraphs.get(def.subgraph_index as usize);
+            let sub_meta =
+                meta.and_then(|meta| meta.subgraph_metadata.get(def.subgraph_index as usize));
+            let inputs = (
+                sub.map(|sub| &sub.inputs[..]),
+                sub_meta.map(|meta| &meta.input_tensor_metadata[..]),
+            );
+            let outputs = (
+                sub.map(|sub| &sub.outputs[..]),
+                sub_meta.map(|meta| &meta.output_tensor_metadata[..]),
+            );
+            let resolve_all = |maps: &[TensorMap], (tensors, tensor_meta)| {
+                maps.iter()
+                    .map(|map| resolve(map, sub, tensors, tensor_meta))
+                    .collect()
+            };
+            Signature {
+                signature_key: def.signature_key.clone(),
+                subgraph_index: def.subgraph_index,
+                subgraph_name: sub.and_then(|sub| sub.name.clone()),
+                inputs: resolve_all(&def.inputs, inputs),
+                outputs: resolve_all(&def.outputs, outputs),
+            }
+        })
+        .collect()
+}
+
+fn resolve(
+    map: &TensorMap,
+    sub: Option<&SubGraph>,
+    tensors: Option<&[i32]>,
+    tensor_meta: Option<&[TensorMetadata]>,
+) -> SignatureTensor {
+    let tensor = sub.and_then(|sub| sub.tensor(map.tensor_index as i32));
+    let metadata_index = tensors
+        .and_then(|tensors| tensors.iter().position(|&t| t == map.tensor_index as i32))
+        .filter(|&i| tensor_meta.is_some_and(|meta| i < meta.len()));
+    SignatureTensor {
+        alias: map.name.clone(),
+        tensor_index: map.tensor_index,
+        tensor_name: tensor.and_then(|t| t.name.clone()),
+        type_: tensor.map(|t| t.type_),
+        shape: tensor.map(|t| t.shape.clone()),
+        metadata_index,
+        metadata_name: metadata_index
+            .and_then(|i| tensor_meta.and_then(|meta| meta[i].name.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SignatureDef, SubGraphMetadata, Tensor};
+
+    fn tensor(name: &str) -> Tensor {
+        Tensor {
+            name: Some(name.into()),
+            shape: vec![1, 2],
+            shape_signature: None,
+            type_: TensorType::FLOAT32,
+            buffer: 0,
+            is_constant: false,
+            is_variable: false,
+            quantization: None,
+            sparsity: None,
+        }
+    }
+
+    fn maps(maps: &[(&str, u32)]) -> Vec<TensorMap> {
+        maps.iter()
+            .map(|&(name, tensor_index)| TensorMap {
+                name: Some(name.into()),
+                tensor_index,
+            })
+            .collect()
+    }
+
+    fn tensor_meta(names: &[&str]) -> Vec<TensorMetadata> {
+        names
+            .iter()
+            .map(|&name| TensorMetadata {
+                name: Some(name.into()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// A subgraph with inputs `b` and `a` (in that order), an intermediate tensor `c` and an
+    /// output `d`, and a signature with the given subgraph index.
+    fn graph(subgraph_index: u32) -> ModelGraph {
+        ModelGraph {
+            version: 3,
+            description: None,
+            operator_codes: Vec::new(),
+            subgraphs: vec![SubGraph {
+                name: Some("main".into()),
+                tensors: ["a", "b", "c", "d"].map(tensor).into(),
+                inputs: vec![1, 0],
+                outputs: vec![3],
+                operators: Vec::new(),
+            }],
+            signature_defs: vec![SignatureDef {
+                signature_key: Some("serving_default".into()),
+                subgraph_index,
+                inputs: maps(&[("x", 0), ("y", 1)]),
+                outputs: maps(&[("mid", 2), ("z", 3), ("missing", 9)]),
+            }],
+        }
+    }
+
+    fn meta(inputs: &[&str], outputs: &[&str]) -> ModelInfo {
+        ModelInfo {
+            subgraph_metadata: vec![SubGraphMetadata {
+                input_tensor_metadata: tensor_meta(inputs),
+                output_tensor_metadata: tensor_meta(outputs),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Returns the tensor name and metadata index and name of each tensor.
+    fn summary(tensors: &[SignatureTensor]) -> Vec<(Option<&str>, Option<usize>, Option<&str>)> {
+        tensors
+            .iter()
+            .map(|t| {
+                (
+                    t.tensor_name.as_deref(),
+                    t.metadata_index,
+                    t.metadata_name.as_deref(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn metadata_by_position() {
+        let meta = meta(&["image_b", "image_a"], &["scores"]);
+        let signatures = signatures(Some(&meta), &graph(0));
+        let [signature] = &signatures[..] else {
+            panic!("expected one signature, got {signatures:?}");
+        };
+        assert_eq!(signature.subgraph_name.as_deref(), Some("main"));
+        let aliases = |tensors: &[SignatureTensor]| {
+            tensors
+                .iter()
+                .map(|t| t.alias.clone().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(aliases(&signature.inputs), ["x", "y"]);
+        assert_eq!(aliases(&signature.outputs), ["mid", "z", "missing"]);
+        assert_eq!(
+            summary(&signature.inputs),
+            [
+                (Some("a"), Some(1), Some("image_a")),
+                (Some("b"), Some(0), Some("image_b")),
+            ]
+        );
+        assert_eq!(
+            summary(&signature.outputs),
+            [
+                // Not a subgraph output, so not described by the metadata.
+                (Some("c"), None, None),
+                (Some("d"), Some(0), Some("scores")),
+                (None, None, None),
+            ]
+        );
+        assert_eq!(signature.outputs[1].type_, Some(TensorType::FLOAT32));
+        assert_eq!(signature.outputs[1].shape.as_deref(), Some(&[1, 2][..]));
+        assert_eq!(signature.outputs[2].type_, None);
+    }
+
+    #[test]
+    fn missing_metadata() {
+        let without_meta = signatures(None, &graph(0));
+        assert!(without_meta[0]
+            .inputs
+            .iter()
+            .all(|t| t.metadata_index.is_none()));
+
+        // Fewer metadata entries than subgraph inputs.
+        let meta = meta(&["image_b"], &[]);
+        let signatures = signatures(Some(&meta), &graph(0));
+        assert_eq!(
+            summary(&signatures[0].inputs),
+            [
+                (Some("a"), None, None),
+                (Some("b"), Some(0), Some("image_b")),
+            ]
+        );
+        assert_eq!(summary(&signatures[0].outputs)[1], (Some("d"), None, None));
+    }
+
+    #[test]
+    fn subgraph_index_out_of_range() {
+        let meta = meta(&["image_b", "image_a"], &["scores"]);
+        let signatures = signatures(Some(&meta), &graph(1));
+        let signature = &signatures[0];
+        assert_eq!(signature.subgraph_index, 1);
+        assert_eq!(signature.subgraph_name, None);
+        for tensor in signature.inputs.iter().chain(&signature.outputs) {
+            assert_eq!(
+                (&tensor.tensor_name, tensor.type_, tensor.metadata_index),
+                (&None, None, None)
+            );
+        }
+    }
+}
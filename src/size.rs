@@ -0,0 +1,519 @@
+//! Size report: where the bytes of a `.tflite` file go.
+
+use std::cmp::Reverse;
+use std::collections::{hash_map::Entry, BTreeMap, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::v3c::tflite as fb;
+use crate::{buffer_data, is_external, Archive, ModelGraph, TensorType};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SizeReport {
+    pub file_size: u64,
+    /// Size of the flatbuffer, including the data of the buffers stored in it. The flatbuffer
+    /// doesn't record its size, so this includes any padding before the external data.
+    pub flatbuffer_size: u64,
+    /// Bytes of the flatbuffer that aren't buffer data: tables, vectors, strings, custom options
+    /// and alignment padding.
+    pub flatbuffer_overhead: u64,
+    /// Bytes of buffer data and custom options stored after the flatbuffer (see
+    /// [`buffer_data`]).
+    pub external_size: u64,
+    /// Bytes of the file not accounted for by the flatbuffer, the external data and the archive,
+    /// such as the alignment padding between external buffers.
+    pub unaccounted_size: u64,
+    /// The non-empty buffers of the model.
+    pub buffers: Vec<BufferSize>,
+    /// The constant tensors of all subgraphs, largest first.
+    pub tensors: Vec<TensorSize>,
+    /// Bytes of constant tensor data by the name of the first operator using them (`<none>` for
+    /// constants that aren't operator inputs). Buffers shared by several tensors are only counted
+    /// once.
+    pub operators: BTreeMap<String, u64>,
+    /// Bytes of constant tensor data by tensor type, counting each buffer once.
+    pub types: BTreeMap<String, u64>,
+    /// The `Model.metadata` entries, such as `TFLITE_METADATA`.
+    pub metadata: Vec<MetadataSize>,
+    /// The associated files archive appended to the model, if any.
+    pub archive: Option<ArchiveSize>,
+    /// Non-empty buffers that no tensor or metadata entry references.
+    pub unreferenced: Vec<u32>,
+    /// Groups of buffers with identical, non-empty contents.
+    pub duplicates: Vec<Vec<u32>>,
+}
+
+impl SizeReport {
+    /// Returns the total size of the constant tensor data, counting shared buffers once.
+    pub fn constant_size(&self) -> u64 {
+        self.types.values().sum()
+    }
+
+    /// Returns the number of bytes that would be saved by deduplicating [`Self::duplicates`].
+    pub fn duplicate_size(&self) -> u64 {
+        self.duplicates
+            .iter()
+            .map(|group| self.buffer_size(group[0]) * (group.len() as u64 - 1))
+            .sum()
+    }
+
+    /// Returns the size of buffer `index`, or 0 if it is empty.
+    pub fn buffer_size(&self, index: u32) -> u64 {
+        self.buffers
+            .iter()
+            .find(|b| b.index == index)
+            .map_or(0, |b| b.size)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BufferSize {
+    pub index: u32,
+    pub size: u64,
+    /// Whether the data is stored after the flatbuffer.
+    pub is_external: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TensorSize {
+    pub subgraph: usize,
+    pub index: usize,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: TensorType,
+    pub buffer: u32,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetadataSize {
+    pub name: Option<String>,
+    pub buffer: u32,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArchiveSize {
+    pub offset: u64,
+    pub size: u64,
+    pub files: Vec<ArchiveFileSize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArchiveFileSize {
+    pub name: String,
+    pub size: u64,
+    pub compressed_size: u64,
+}
+
+/// Attributes the bytes of the `.tflite` file `tflite` to its buffers, tensors, metadata and
+/// associated files.
+pub fn size_report(tflite: &[u8]) -> anyhow::Result<SizeReport> {
+    let graph = ModelGraph::from_bytes(tflite)?;
+    let model = flatbuffers::root::<fb::Model>(tflite)?;
+    let file_size = tflite.len() as u64;
+
+    let archive = Archive::from_bytes(tflite)?.map(|archive| ArchiveSize {
+        offset: archive.offset(),
+        size: file_size - archive.offset(),
+        files: archive
+            .entries()
+            .iter()
+            .map(|e| ArchiveFileSize {
+                name: e.name.clone(),
+                size: e.size,
+                compressed_size: e.compressed_size,
+            })
+            .collect(),
+    });
+
+    // The flatbuffer doesn't record its size; it ends where the first data stored after it
+    // starts.
+    let mut flatbuffer_end = archive.as_ref().map_or(file_size, |a| a.offset);
+    let mut external_size = 0;
+    let mut buffers = Vec::new();
+    let mut data = Vec::new();
+    for (index, buffer) in model.buffers().into_iter().flatten().enumerate() {
+        let index = index as u32;
+        let bytes = buffer_data(tflite, &model, index)?;
+        let is_external = is_external(buffer.offset());
+        if is_external {
+            flatbuffer_end = flatbuffer_end.min(buffer.offset());
+            external_size += bytes.len() as u64;
+        }
+        if !bytes.is_empty() {
+            buffers.push(BufferSize {
+                index,
+                size: bytes.len() as u64,
+                is_external,
+            });
+            data.push((index, bytes));
+        }
+    }
+    for op in model
+        .subgraphs()
+        .into_iter()
+        .flatten()
+        .flat_map(|sub| sub.operators().into_iter().flatten())
+    {
+        let offset = op.large_custom_options_offset();
+        if is_external(offset) {
+            flatbuffer_end = flatbuffer_end.min(offset);
+            external_size += op.large_custom_options_size();
+        }
+    }
+    let inline_size = buffers
+        .iter()
+        .filter(|b| !b.is_external)
+        .map(|b| b.size)
+        .sum::<u64>();
+    let unaccounted_size = (file_size - flatbuffer_end)
+        .saturating_sub(external_size + archive.as_ref().map_or(0, |a| a.size));
+
+    let sizes = buffers
+        .iter()
+        .map(|b| (b.index, b.size))
+        .collect::<HashMap<_, _>>();
+    let size_of = |buffer: u32| sizes.get(&buffer).copied().unwrap_or(0);
+
+    let mut tensors = Vec::new();
+    let mut types = BTreeMap::new();
+    let mut operators = BTreeMap::new();
+    let mut counted = HashMap::new();
+    for (s, sub) in graph.subgraphs.iter().enumerate() {
+        for (index, tensor) in sub.tensors.iter().enumerate() {
+            if !tensor.is_constant {
+                continue;
+            }
+            let size = size_of(tensor.buffer);
+            tensors.push(TensorSize {
+                subgraph: s,
+                index,
+                name: tensor.name.clone(),
+                type_: tensor.type_,
+                buffer: tensor.buffer,
+                size,
+            });
+            if let Entry::Vacant(entry) = counted.entry(tensor.buffer) {
+                entry.insert(false);
+                let type_ = tensor.type_.variant_name().unwrap_or("UNKNOWN");
+                *types.entry(type_.to_string()).or_default() += size;
+            }
+        }
+        for op in &sub.operators {
+            let name = graph
+                .operator_code(op)
+                .map_or_else(|| "<invalid opcode>".into(), |code| code.name());
+            for tensor in op.inputs.iter().filter_map(|&t| sub.tensor(t)) {
+                if let Some(false) = counted.get(&tensor.buffer) {
+                    counted.insert(tensor.buffer, true);
+                    *operators.entry(name.clone()).or_default() += size_of(tensor.buffer);
+                }
+            }
+        }
+    }
+    for (buffer, _) in counted.iter().filter(|(_, &used)| !used) {
+        *operators.entry("<none>".into()).or_default() += size_of(*buffer);
+    }
+    tensors.sort_by_key(|t| Reverse(t.size));
+
+    let metadata = model.metadata().map_or(Vec::new(), |metadata| {
+        metadata
+            .iter()
+            .map(|meta| MetadataSize {
+                name: meta.name().map(Into::into),
+                buffer: meta.buffer(),
+                size: size_of(meta.buffer()),
+            })
+            .collect()
+    });
+
+    let unreferenced = buffers
+        .iter()
+        .map(|b| b.index)
+        .filter(|index| !metadata.iter().any(|m| m.buffer == *index))
+        .filter(|&index| {
+            !graph
+                .subgraphs
+                .iter()
+                .flat_map(|sub| &sub.tensors)
+                .any(|t| t.buffer == index)
+        })
+        .collect();
+
+    Ok(SizeReport {
+        file_size,
+        flatbuffer_size: flatbuffer_end,
+        flatbuffer_overhead: flatbuffer_end
+            .checked_sub(inline_size)
+            .context("buffer data exceeds the size of the flatbuffer")?,
+        external_size,
+        unaccounted_size,
+        buffers,
+        tensors,
+        operators,
+        types,
+        metadata,
+        archive,
+        unreferenced,
+        duplicates: duplicates(&data),
+    })
+}
+
+/// Groups the buffers with identical contents, by hashing them first.
+fn duplicates(data: &[(u32, &[u8])]) -> Vec<Vec<u32>> {
+    let mut by_hash = HashMap::<_, Vec<_>>::new();
+    for (index, bytes) in data {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        by_hash
+            .entry(hasher.finish())
+            .or_default()
+            .push((*index, *bytes));
+    }
+
+    let mut groups = Vec::new();
+    for mut candidates in by_hash.into_values() {
+        // Hash collisions are possible, so split the candidates by their actual contents.
+        while let Some(&(_, bytes)) = candidates.first() {
+            let (same, rest) = candidates
+                .into_iter()
+                .partition::<Vec<_>, _>(|c| c.1 == bytes);
+            if same.len() > 1 {
+                groups.push(same.into_iter().map(|c| c.0).collect::<Vec<_>>());
+            }
+            candidates = rest;
+        }
+    }
+    groups.sort();
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use flatbuffers::{FlatBufferBuilder, WIPOffset};
+
+    use super::*;
+
+    const WEIGHTS: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+    const UNREFERENCED: [u8; 4] = [42; 4];
+    const METADATA: [u8; 16] = [0; 16];
+    const UNUSED: [u8; 6] = [7; 6];
+
+    /// Builds a model with the buffers
+    ///
+    /// 0. the empty sentinel buffer,
+    /// 1. `WEIGHTS`, shared by the `w` and `w_shared` tensors,
+    /// 2. a copy of `WEIGHTS` for the `w_copy` tensor, stored at `copy_offset` if it is set,
+    /// 3. `UNREFERENCED`, which nothing references,
+    /// 4. `METADATA`, referenced by a `Model.metadata` entry, and
+    /// 5. `UNUSED`, the data of a constant tensor that no operator uses.
+    ///
+    /// A `CONV_2D` uses `w`, and a following `ADD` uses `w_shared` and `w_copy`.
+    fn model(copy_offset: Option<u64>) -> Vec<u8> {
+        fn buffer<'a>(
+            fbb: &mut FlatBufferBuilder<'a>,
+            data: Option<&[u8]>,
+            offset: u64,
+        ) -> WIPOffset<fb::Buffer<'a>> {
+            let args = fb::BufferArgs {
+                data: data.map(|data| fbb.create_vector(data)),
+                offset,
+                size_: if offset > 1 { WEIGHTS.len() as u64 } else { 0 },
+            };
+            fb::Buffer::create(fbb, &args)
+        }
+
+        let mut fbb = FlatBufferBuilder::new();
+        let buffers = [
+            buffer(&mut fbb, None, 0),
+            buffer(&mut fbb, Some(&WEIGHTS), 0),
+            match copy_offset {
+                Some(offset) => buffer(&mut fbb, None, offset),
+                None => buffer(&mut fbb, Some(&WEIGHTS), 0),
+            },
+            buffer(&mut fbb, Some(&UNREFERENCED), 0),
+            buffer(&mut fbb, Some(&METADATA), 0),
+            buffer(&mut fbb, Some(&UNUSED), 0),
+        ];
+        let buffers = fbb.create_vector(&buffers);
+
+        let tensors = [
+            ("input", TensorType::FLOAT32, 0),
+            ("w", TensorType::FLOAT32, 1),
+            ("w_shared", TensorType::FLOAT32, 1),
+            ("w_copy", TensorType::INT8, 2),
+            ("unused", TensorType::INT8, 5),
+            ("output", TensorType::FLOAT32, 0),
+        ]
+        .map(|(name, type_, buffer)| {
+            let args = fb::TensorArgs {
+                name: Some(fbb.create_string(name)),
+                type_,
+                buffer,
+                ..Default::default()
+            };
+            fb::Tensor::create(&mut fbb, &args)
+        });
+        let tensors = fbb.create_vector(&tensors);
+
+        let codes = [fb::BuiltinOperator::CONV_2D, fb::BuiltinOperator::ADD].map(|code| {
+            let args = fb::OperatorCodeArgs {
+                builtin_code: code,
+                deprecated_builtin_code: code.0 as i8,
+                ..Default::default()
+            };
+            fb::OperatorCode::create(&mut fbb, &args)
+        });
+        let codes = fbb.create_vector(&codes);
+
+        let operators = [(0, &[0, 1][..]), (1, &[5, 2, 3][..])].map(|(opcode_index, inputs)| {
+            let args = fb::OperatorArgs {
+                opcode_index,
+                inputs: Some(fbb.create_vector(inputs)),
+                outputs: Some(fbb.create_vector(&[5])),
+                ..Default::default()
+            };
+            fb::Operator::create(&mut fbb, &args)
+        });
+        let operators = fbb.create_vector(&operators);
+        let (inputs, outputs) = (fbb.create_vector(&[0]), fbb.create_vector(&[5]));
+        let subgraph = fb::SubGraph::create(
+            &mut fbb,
+            &fb::SubGraphArgs {
+                tensors: Some(tensors),
+                inputs: Some(inputs),
+                outputs: Some(outputs),
+                operators: Some(operators),
+                ..Default::default()
+            },
+        );
+        let subgraphs = fbb.create_vector(&[subgraph]);
+
+        let name = fbb.create_string("min_runtime_version");
+        let metadata = fb::Metadata::create(
+            &mut fbb,
+            &fb::MetadataArgs {
+                name: Some(name),
+                buffer: 4,
+            },
+        );
+        let metadata = fbb.create_vector(&[metadata]);
+
+        let root = fb::Model::create(
+            &mut fbb,
+            &fb::ModelArgs {
+                version: 3,
+                operator_codes: Some(codes),
+                subgraphs: Some(subgraphs),
+                buffers: Some(buffers),
+                metadata: Some(metadata),
+                ..Default::default()
+            },
+        );
+        fbb.finish(root, Some("TFL3"));
+        fbb.finished_data().to_vec()
+    }
+
+    /// Builds the model with the copy of the weights stored after the flatbuffer, separated by 16
+    /// to 31 bytes of padding.
+    fn external_model() -> Vec<u8> {
+        // The placeholder offset is replaced by the real one, which doesn't change the size.
+        let len = model(Some(2)).len();
+        let offset = len.next_multiple_of(16) + 16;
+        let mut tflite = model(Some(offset as u64));
+        assert_eq!(tflite.len(), len);
+        tflite.resize(offset, 0);
+        tflite.extend_from_slice(&WEIGHTS);
+        tflite
+    }
+
+    #[test]
+    fn inline_buffers() {
+        let tflite = model(None);
+        let report = size_report(&tflite).unwrap();
+        let len = tflite.len() as u64;
+        let inline =
+            (2 * WEIGHTS.len() + UNREFERENCED.len() + METADATA.len() + UNUSED.len()) as u64;
+        assert_eq!(report.file_size, len);
+        assert_eq!(report.flatbuffer_size, len);
+        assert_eq!(report.flatbuffer_overhead, len - inline);
+        assert_eq!((report.external_size, report.unaccounted_size), (0, 0));
+        assert_eq!(
+            report.buffers.iter().map(|b| b.index).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5]
+        );
+
+        let tensors = report
+            .tensors
+            .iter()
+            .map(|t| (t.name.as_deref().unwrap(), t.buffer, t.size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tensors,
+            [
+                ("w", 1, 8),
+                ("w_shared", 1, 8),
+                ("w_copy", 2, 8),
+                ("unused", 5, 6)
+            ]
+        );
+        // Buffer 1 is only credited to the first operator using it.
+        assert_eq!(
+            report.operators,
+            BTreeMap::from([
+                ("<none>".into(), 6),
+                ("ADD".into(), 8),
+                ("CONV_2D".into(), 8),
+            ])
+        );
+        assert_eq!(
+            report.types,
+            BTreeMap::from([("FLOAT32".into(), 8), ("INT8".into(), 14)])
+        );
+        assert_eq!(report.constant_size(), 22);
+        assert_eq!(
+            report.metadata,
+            [MetadataSize {
+                name: Some("min_runtime_version".into()),
+                buffer: 4,
+                size: 16,
+            }]
+        );
+        assert_eq!(report.archive, None);
+
+        assert_eq!(report.unreferenced, [3]);
+        assert_eq!(report.duplicates, [vec![1, 2]]);
+        assert_eq!(report.duplicate_size(), 8);
+    }
+
+    #[test]
+    fn external_buffers() {
+        let tflite = external_model();
+        let report = size_report(&tflite).unwrap();
+        let flatbuffer = model(Some(2)).len() as u64;
+        let inline = (WEIGHTS.len() + UNREFERENCED.len() + METADATA.len() + UNUSED.len()) as u64;
+        assert_eq!(report.file_size, tflite.len() as u64);
+        // The flatbuffer ends where the external data starts, so the padding counts as overhead.
+        let offset = flatbuffer.next_multiple_of(16) + 16;
+        assert_eq!(report.flatbuffer_size, offset);
+        assert_eq!(report.flatbuffer_overhead, offset - inline);
+        assert_eq!(report.external_size, 8);
+        assert_eq!(report.unaccounted_size, 0);
+        assert!(report.buffers.iter().any(|b| b.index == 2 && b.is_external));
+        // External buffers are compared by contents too.
+        assert_eq!(report.duplicates, [vec![1, 2]]);
+    }
+
+    #[test]
+    fn external_data_inside_the_flatbuffer() {
+        // Buffer 2 points into the flatbuffer, before the end of the inline data.
+        let e = size_report(&model(Some(4))).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "buffer data exceeds the size of the flatbuffer"
+        );
+    }
+}
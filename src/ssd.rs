@@ -0,0 +1,571 @@
+//! Decoding of SSD detector outputs as described by `DETECTOR_METADATA`, implemented like
+//! MediaPipe's `TensorsToDetectionsCalculator` and `NonMaxSuppressionCalculator`.
+
+use anyhow::{bail, ensure};
+use serde::Serialize;
+
+use crate::{FixedAnchor, ObjectDetectorOptions, TensorsDecodingOptions};
+
+/// Order of the box coordinates in the raw box tensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoxFormat {
+    /// `y_center, x_center, height, width`, as used by TFLite SSD models.
+    Yxhw,
+    /// `x_center, y_center, width, height`, which is what MediaPipe Tasks assumes for models with
+    /// `DETECTOR_METADATA`. Keypoints are `x, y` in this format, and `y, x` otherwise.
+    #[default]
+    Xywh,
+}
+
+/// Decodes raw box and score tensors into [`Detection`]s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SsdDecoder {
+    pub anchors: Vec<FixedAnchor>,
+    pub options: TensorsDecodingOptions,
+    pub box_format: BoxFormat,
+}
+
+/// A decoded box.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Detection {
+    /// Index of the class with the highest score.
+    pub class: usize,
+    pub score: f32,
+    pub xmin: f32,
+    pub ymin: f32,
+    pub xmax: f32,
+    pub ymax: f32,
+    pub keypoints: Vec<Keypoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Keypoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Suppression strategy of [`non_max_suppression`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NmsAlgorithm {
+    /// Drops detections that overlap a higher-scoring one.
+    #[default]
+    Default,
+    /// Replaces each group of overlapping detections by their score-weighted average.
+    Weighted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NmsOptions {
+    /// Detections with a lower score are dropped.
+    pub min_score: f32,
+    /// Detections whose intersection over union exceeds this threshold overlap.
+    pub iou_threshold: f32,
+    /// Maximum number of detections to return, or [`None`] for no limit.
+    pub max_detections: Option<usize>,
+    pub algorithm: NmsAlgorithm,
+}
+
+impl Default for NmsOptions {
+    fn default() -> Self {
+        Self {
+            min_score: 0.5,
+            iou_threshold: 0.3,
+            max_detections: None,
+            algorithm: NmsAlgorithm::Default,
+        }
+    }
+}
+
+impl SsdDecoder {
+    /// Creates a decoder from the anchors and decoding options of `DETECTOR_METADATA`.
+    pub fn new(options: &ObjectDetectorOptions) -> anyhow::Result<Self> {
+        let Some(anchors) = options
+            .ssd_anchors_options
+            .as_ref()
+            .and_then(|ssd| ssd.fixed_anchors_schema.as_ref())
+        else {
+            bail!("detector metadata contains no fixed anchors");
+        };
+        let Some(decoding) = options.tensors_decoding_options else {
+            bail!("detector metadata contains no tensors decoding options");
+        };
+        Self::with_anchors(anchors.anchors.clone(), decoding)
+    }
+
+    /// Creates a decoder from anchors and decoding options.
+    pub fn with_anchors(
+        anchors: Vec<FixedAnchor>,
+        options: TensorsDecodingOptions,
+    ) -> anyhow::Result<Self> {
+        ensure!(
+            anchors.len() == options.num_boxes as usize,
+            "{} anchors given, but the model predicts {} boxes",
+            anchors.len(),
+            options.num_boxes,
+        );
+        ensure!(
+            options.num_coords >= 4,
+            "boxes need at least 4 coordinates, but num_coords is {}",
+            options.num_coords,
+        );
+        ensure!(
+            options.num_classes > 0,
+            "num_classes must be positive, but is {}",
+            options.num_classes,
+        );
+        ensure!(
+            options.num_keypoints >= 0
+                && options.keypoint_coord_offset >= 0
+                && options.num_values_per_keypoint >= 0,
+            "invalid keypoint layout ({} keypoints at offset {}, {} values per keypoint)",
+            options.num_keypoints,
+            options.keypoint_coord_offset,
+            options.num_values_per_keypoint,
+        );
+        if options.num_keypoints > 0 {
+            ensure!(
+                options.num_values_per_keypoint >= 2,
+                "keypoints need at least 2 values, but num_values_per_keypoint is {}",
+                options.num_values_per_keypoint,
+            );
+            let end = i64::from(options.keypoint_coord_offset)
+                + i64::from(options.num_keypoints - 1) * i64::from(options.num_values_per_keypoint)
+                + 2;
+            ensure!(
+                end <= i64::from(options.num_coords),
+                "{} keypoints at offset {} don't fit into {} coordinates",
+                options.num_keypoints,
+                options.keypoint_coord_offset,
+                options.num_coords,
+            );
+        }
+        Ok(Self {
+            anchors,
+            options,
+            box_format: BoxFormat::default(),
+        })
+    }
+
+    /// Decodes the raw box tensor (`[num_boxes, num_coords]`) and score tensor
+    /// (`[num_boxes, num_classes]`) of a single inference.
+    ///
+    /// Returns one detection per box, with the score of the best class, in the order of the
+    /// anchors. Boxes with a negative or NaN size are skipped.
+    pub fn decode(&self, raw_boxes: &[f32], raw_scores: &[f32]) -> anyhow::Result<Vec<Detection>> {
+        let opts = &self.options;
+        let (num_coords, num_classes) = (opts.num_coords as usize, opts.num_classes as usize);
+        ensure!(
+            raw_boxes.len() == self.anchors.len() * num_coords,
+            "expected {} box values, got {}",
+            self.anchors.len() * num_coords,
+            raw_boxes.len(),
+        );
+        ensure!(
+            raw_scores.len() == self.anchors.len() * num_classes,
+            "expected {} scores, got {}",
+            self.anchors.len() * num_classes,
+            raw_scores.len(),
+        );
+
+        let mut detections = Vec::new();
+        for (i, anchor) in self.anchors.iter().enumerate() {
+            let raw = &raw_boxes[i * num_coords..][..num_coords];
+            let (x, y, w, h) = match self.box_format {
+                BoxFormat::Yxhw => (raw[1], raw[0], raw[3], raw[2]),
+                BoxFormat::Xywh => (raw[0], raw[1], raw[2], raw[3]),
+            };
+            let x_center = x / opts.x_scale * anchor.width + anchor.x_center;
+            let y_center = y / opts.y_scale * anchor.height + anchor.y_center;
+            let (w, h) = if opts.apply_exponential_on_box_size {
+                (
+                    (w / opts.w_scale).exp() * anchor.width,
+                    (h / opts.h_scale).exp() * anchor.height,
+                )
+            } else {
+                (
+                    w / opts.w_scale * anchor.width,
+                    h / opts.h_scale * anchor.height,
+                )
+            };
+            // Models can predict invalid boxes, which downstream code may not expect.
+            if !(w >= 0.0 && h >= 0.0) {
+                continue;
+            }
+
+            let keypoints = (0..opts.num_keypoints as usize)
+                .map(|k| {
+                    let offset = opts.keypoint_coord_offset as usize
+                        + k * opts.num_values_per_keypoint as usize;
+                    let (x, y) = match self.box_format {
+                        BoxFormat::Yxhw => (raw[offset + 1], raw[offset]),
+                        BoxFormat::Xywh => (raw[offset], raw[offset + 1]),
+                    };
+                    Keypoint {
+                        x: x / opts.x_scale * anchor.width + anchor.x_center,
+                        y: y / opts.y_scale * anchor.height + anchor.y_center,
+                    }
+                })
+                .collect();
+
+            let (mut class, mut score) = (0, f32::MIN);
+            for (c, &raw) in raw_scores[i * num_classes..][..num_classes]
+                .iter()
+                .enumerate()
+            {
+                let s = if opts.sigmoid_score {
+                    1.0 / (1.0 + (-raw).exp())
+                } else {
+                    raw
+                };
+                if score < s {
+                    (class, score) = (c, s);
+                }
+            }
+
+            detections.push(Detection {
+                class,
+                score,
+                xmin: x_center - w / 2.0,
+                ymin: y_center - h / 2.0,
+                xmax: x_center + w / 2.0,
+                ymax: y_center + h / 2.0,
+                keypoints,
+            });
+        }
+        Ok(detections)
+    }
+
+    /// [`decode`][Self::decode]s the raw tensors and runs [`non_max_suppression`] on the result.
+    pub fn detect(
+        &self,
+        raw_boxes: &[f32],
+        raw_scores: &[f32],
+        nms: &NmsOptions,
+    ) -> anyhow::Result<Vec<Detection>> {
+        Ok(non_max_suppression(
+            self.decode(raw_boxes, raw_scores)?,
+            nms,
+        ))
+    }
+}
+
+impl Detection {
+    fn area(&self) -> f32 {
+        (self.xmax - self.xmin) * (self.ymax - self.ymin)
+    }
+
+    /// Returns the intersection over union of the boxes of two detections.
+    pub fn iou(&self, other: &Detection) -> f32 {
+        let width = self.xmax.min(other.xmax) - self.xmin.max(other.xmin);
+        let height = self.ymax.min(other.ymax) - self.ymin.max(other.ymin);
+        if width < 0.0 || height < 0.0 {
+            return 0.0;
+        }
+        let intersection = width * height;
+        let union = self.area() + other.area() - intersection;
+        if union > 0.0 {
+            intersection / union
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Removes overlapping detections, class-agnostically.
+///
+/// Returns the remaining detections by descending score.
+pub fn non_max_suppression(detections: Vec<Detection>, options: &NmsOptions) -> Vec<Detection> {
+    let mut remaining = detections
+        .into_iter()
+        .filter(|d| d.score >= options.min_score)
+        .collect::<Vec<_>>();
+    // Stable, so that equal scores keep the anchor order.
+    remaining.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let max = options.max_detections.unwrap_or(usize::MAX);
+
+    let mut kept = Vec::<Detection>::new();
+    match options.algorithm {
+        NmsAlgorithm::Default => {
+            for detection in remaining {
+                if kept.len() >= max {
+                    break;
+                }
+                if kept
+                    .iter()
+                    .all(|k| k.iou(&detection) <= options.iou_threshold)
+                {
+                    kept.push(detection);
+                }
+            }
+        }
+        NmsAlgorithm::Weighted => {
+            while !remaining.is_empty() && kept.len() < max {
+                let (overlapping, rest) = remaining
+                    .iter()
+                    .partition::<Vec<_>, _>(|d| d.iou(&remaining[0]) > options.iou_threshold);
+                let mut weighted = remaining[0].clone();
+                // The best detection always overlaps itself, unless its box is empty.
+                if !overlapping.is_empty() {
+                    let total = overlapping.iter().map(|d| d.score).sum::<f32>();
+                    let average = |value: fn(&Detection) -> f32| {
+                        overlapping.iter().map(|d| value(d) * d.score).sum::<f32>() / total
+                    };
+                    weighted.xmin = average(|d| d.xmin);
+                    weighted.ymin = average(|d| d.ymin);
+                    weighted.xmax = average(|d| d.xmax);
+                    weighted.ymax = average(|d| d.ymax);
+                    // Detections with fewer keypoints don't contribute to the missing ones.
+                    for (k, keypoint) in weighted.keypoints.iter_mut().enumerate() {
+                        let (mut x, mut y, mut total) = (0.0, 0.0, 0.0);
+                        for d in &overlapping {
+                            if let Some(other) = d.keypoints.get(k) {
+                                x += other.x * d.score;
+                                y += other.y * d.score;
+                                total += d.score;
+                            }
+                        }
+                        if total > 0.0 {
+                            *keypoint = Keypoint {
+                                x: x / total,
+                                y: y / total,
+                            };
+                        }
+                    }
+                }
+                kept.push(weighted);
+                remaining = if overlapping.is_empty() {
+                    rest.into_iter().skip(1).cloned().collect()
+                } else {
+                    rest.into_iter().cloned().collect()
+                };
+            }
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchor(x_center: f32, y_center: f32, width: f32, height: f32) -> FixedAnchor {
+        FixedAnchor {
+            x_center,
+            y_center,
+            width,
+            height,
+        }
+    }
+
+    fn options(num_boxes: i32) -> TensorsDecodingOptions {
+        TensorsDecodingOptions {
+            num_classes: 2,
+            num_boxes,
+            num_coords: 4,
+            keypoint_coord_offset: 0,
+            num_keypoints: 0,
+            num_values_per_keypoint: 0,
+            x_scale: 10.0,
+            y_scale: 10.0,
+            w_scale: 5.0,
+            h_scale: 5.0,
+            apply_exponential_on_box_size: false,
+            sigmoid_score: false,
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    fn assert_box(d: &Detection, [xmin, ymin, xmax, ymax]: [f32; 4]) {
+        assert_close(d.xmin, xmin);
+        assert_close(d.ymin, ymin);
+        assert_close(d.xmax, xmax);
+        assert_close(d.ymax, ymax);
+    }
+
+    fn detection(score: f32, [xmin, ymin, xmax, ymax]: [f32; 4]) -> Detection {
+        Detection {
+            class: 0,
+            score,
+            xmin,
+            ymin,
+            xmax,
+            ymax,
+            keypoints: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn decode_boxes() {
+        let anchors = vec![anchor(0.5, 0.5, 1.0, 1.0), anchor(0.25, 0.75, 0.5, 0.5)];
+        let decoder = SsdDecoder::with_anchors(anchors, options(2)).unwrap();
+        #[rustfmt::skip]
+        let raw_boxes = [
+            // Centered on the anchor, with half its size.
+            0.0, 0.0, 2.5, 2.5,
+            // Shifted by (0.1, -0.2) anchor sizes, with the anchor's size.
+            1.0, -2.0, 5.0, 5.0,
+        ];
+        let raw_scores = [0.1, 0.9, 0.7, 0.2];
+        let detections = decoder.decode(&raw_boxes, &raw_scores).unwrap();
+
+        assert_eq!(detections.len(), 2);
+        assert_eq!((detections[0].class, detections[0].score), (1, 0.9));
+        assert_box(&detections[0], [0.25, 0.25, 0.75, 0.75]);
+        assert_eq!((detections[1].class, detections[1].score), (0, 0.7));
+        assert_box(&detections[1], [0.05, 0.4, 0.55, 0.9]);
+    }
+
+    #[test]
+    fn decode_yxhw() {
+        let mut decoder =
+            SsdDecoder::with_anchors(vec![anchor(0.5, 0.5, 1.0, 1.0)], options(1)).unwrap();
+        decoder.box_format = BoxFormat::Yxhw;
+        let detections = decoder.decode(&[1.0, 0.0, 5.0, 2.5], &[0.5, 0.5]).unwrap();
+        assert_box(&detections[0], [0.25, 0.1, 0.75, 1.1]);
+    }
+
+    #[test]
+    fn decode_exponential_size_and_sigmoid() {
+        let mut opts = options(1);
+        opts.apply_exponential_on_box_size = true;
+        opts.sigmoid_score = true;
+        let decoder = SsdDecoder::with_anchors(vec![anchor(0.5, 0.5, 0.2, 0.4)], opts).unwrap();
+        let w = 5.0 * 2f32.ln();
+        let detections = decoder.decode(&[0.0, 0.0, w, 0.0], &[0.0, -2.0]).unwrap();
+
+        // exp(ln 2) * 0.2 wide, exp(0) * 0.4 high.
+        assert_box(&detections[0], [0.3, 0.3, 0.7, 0.7]);
+        assert_eq!(detections[0].class, 0);
+        assert_close(detections[0].score, 0.5);
+    }
+
+    #[test]
+    fn decode_keypoints() {
+        let mut opts = options(1);
+        opts.num_coords = 4 + 2 * 3;
+        opts.keypoint_coord_offset = 4;
+        opts.num_keypoints = 2;
+        opts.num_values_per_keypoint = 3;
+        let decoder = SsdDecoder::with_anchors(vec![anchor(0.5, 0.5, 0.5, 0.5)], opts).unwrap();
+        #[rustfmt::skip]
+        let raw_boxes = [
+            0.0, 0.0, 5.0, 5.0,
+            // x, y and an unused third value per keypoint.
+            2.0, -2.0, 9.0,
+            -4.0, 0.0, 9.0,
+        ];
+        let detections = decoder.decode(&raw_boxes, &[1.0, 0.0]).unwrap();
+        let keypoints = &detections[0].keypoints;
+        assert_eq!(keypoints.len(), 2);
+        assert_close(keypoints[0].x, 0.6);
+        assert_close(keypoints[0].y, 0.4);
+        assert_close(keypoints[1].x, 0.3);
+        assert_close(keypoints[1].y, 0.5);
+    }
+
+    #[test]
+    fn decode_skips_negative_sizes() {
+        let decoder =
+            SsdDecoder::with_anchors(vec![anchor(0.5, 0.5, 1.0, 1.0)], options(1)).unwrap();
+        let detections = decoder.decode(&[0.0, 0.0, -1.0, 1.0], &[1.0, 0.0]).unwrap();
+        assert!(detections.is_empty());
+    }
+
+    #[test]
+    fn decode_checks_sizes() {
+        assert!(SsdDecoder::with_anchors(vec![], options(1)).is_err());
+        let decoder =
+            SsdDecoder::with_anchors(vec![anchor(0.5, 0.5, 1.0, 1.0)], options(1)).unwrap();
+        assert!(decoder.decode(&[0.0; 3], &[0.0; 2]).is_err());
+        assert!(decoder.decode(&[0.0; 4], &[0.0; 3]).is_err());
+    }
+
+    #[test]
+    fn invalid_keypoint_layout() {
+        let anchors = || vec![anchor(0.5, 0.5, 1.0, 1.0)];
+        for (num_keypoints, offset, values) in [(-1, 0, 2), (1, -4, 2), (0, 0, -2), (1, 4, 1)] {
+            let mut opts = options(1);
+            opts.num_coords = 8;
+            opts.num_keypoints = num_keypoints;
+            opts.keypoint_coord_offset = offset;
+            opts.num_values_per_keypoint = values;
+            assert!(
+                SsdDecoder::with_anchors(anchors(), opts).is_err(),
+                "{num_keypoints} keypoints at offset {offset}, {values} values per keypoint",
+            );
+        }
+
+        // Keypoints past the end of the box coordinates, with an overflowing end offset.
+        let mut opts = options(1);
+        opts.num_coords = 8;
+        opts.keypoint_coord_offset = 4;
+        opts.num_keypoints = i32::MAX;
+        opts.num_values_per_keypoint = i32::MAX;
+        assert!(SsdDecoder::with_anchors(anchors(), opts).is_err());
+    }
+
+    #[test]
+    fn nms_default() {
+        let detections = vec![
+            detection(0.6, [0.0, 0.0, 0.5, 0.5]),
+            detection(0.9, [0.05, 0.0, 0.55, 0.5]),
+            detection(0.8, [0.5, 0.5, 1.0, 1.0]),
+            detection(0.3, [0.0, 0.5, 0.5, 1.0]),
+        ];
+        let kept = non_max_suppression(detections.clone(), &NmsOptions::default());
+        assert_eq!(kept, vec![detections[1].clone(), detections[2].clone()]);
+
+        let options = NmsOptions {
+            max_detections: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(non_max_suppression(detections, &options).len(), 1);
+    }
+
+    #[test]
+    fn nms_weighted() {
+        let mut a = detection(0.75, [0.0, 0.0, 0.4, 0.4]);
+        a.keypoints = vec![Keypoint { x: 0.2, y: 0.2 }];
+        let mut b = detection(0.25, [0.0, 0.0, 0.4, 0.8]);
+        b.keypoints = vec![Keypoint { x: 0.6, y: 0.6 }];
+        let mut c = detection(0.5, [0.6, 0.6, 1.0, 1.0]);
+        c.keypoints = vec![Keypoint { x: 0.8, y: 0.8 }];
+        let options = NmsOptions {
+            min_score: 0.0,
+            algorithm: NmsAlgorithm::Weighted,
+            ..Default::default()
+        };
+        let kept = non_max_suppression(vec![a, b, c.clone()], &options);
+
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].score, 0.75);
+        assert_box(&kept[0], [0.0, 0.0, 0.4, 0.5]);
+        assert_close(kept[0].keypoints[0].x, 0.3);
+        assert_close(kept[0].keypoints[0].y, 0.3);
+        assert_eq!(kept[1], c);
+    }
+
+    #[test]
+    fn nms_weighted_with_missing_keypoints() {
+        let mut a = detection(0.75, [0.0, 0.0, 0.4, 0.4]);
+        a.keypoints = vec![Keypoint { x: 0.2, y: 0.2 }, Keypoint { x: 0.1, y: 0.1 }];
+        let mut b = detection(0.25, [0.0, 0.0, 0.4, 0.8]);
+        b.keypoints = vec![Keypoint { x: 0.6, y: 0.6 }];
+        let options = NmsOptions {
+            min_score: 0.0,
+            algorithm: NmsAlgorithm::Weighted,
+            ..Default::default()
+        };
+        let kept = non_max_suppression(vec![a, b], &options);
+
+        assert_eq!(kept.len(), 1);
+        assert_close(kept[0].keypoints[0].x, 0.3);
+        assert_close(kept[0].keypoints[1].x, 0.1);
+    }
+}
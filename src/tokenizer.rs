@@ -0,0 +1,574 @@
+//! Text tokenizers described by the tokenizer process units of a subgraph, with their vocabulary
+//! loaded from the model's associated files.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Context};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{Archive, AssociatedFile, ModelGraph, ModelInfo, ProcessUnitOptions};
+
+/// Output of a tokenizer's `encode` method, in the format of the model's inputs.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Encoding {
+    /// The tokens as strings, without padding.
+    pub tokens: Vec<String>,
+    /// Token IDs.
+    pub ids: Vec<i32>,
+    /// 1 for tokens, 0 for padding. Empty for tokenizers of models that only take token IDs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mask: Vec<i32>,
+    /// Sequence index of each token; always 0, since only single sequences are encoded. Empty for
+    /// tokenizers of models that only take token IDs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub segment_ids: Vec<i32>,
+}
+
+/// A tokenizer vocabulary, mapping tokens to IDs and back.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Vocab {
+    tokens: HashMap<i32, String>,
+    ids: HashMap<String, i32>,
+}
+
+impl Vocab {
+    /// Parses a vocabulary file with one token per line, whose ID is its line index.
+    pub fn parse(file: &str) -> Self {
+        let mut vocab = Self::default();
+        for (id, line) in file.lines().enumerate() {
+            vocab.insert(line.trim_end().to_string(), id as i32);
+        }
+        vocab
+    }
+
+    /// Parses a vocabulary file with a token and its ID, separated by a space, on each line.
+    pub fn parse_indexed(file: &str) -> anyhow::Result<Self> {
+        let mut vocab = Self::default();
+        for (i, line) in file.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            let (token, id) = line
+                .rsplit_once(' ')
+                .ok_or_else(|| anyhow!("line {}: expected `<token> <id>`", i + 1))?;
+            let id = id
+                .parse()
+                .with_context(|| format!("line {}: invalid token ID `{id}`", i + 1))?;
+            vocab.insert(token.to_string(), id);
+        }
+        Ok(vocab)
+    }
+
+    fn insert(&mut self, token: String, id: i32) {
+        // Like other implementations, use the first of duplicate tokens.
+        self.tokens.entry(id).or_insert_with(|| token.clone());
+        self.ids.entry(token).or_insert(id);
+    }
+
+    /// Reads the vocabulary file `file` from the model's associated files archive.
+    fn read(archive: Option<&mut Archive<'_>>, file: &AssociatedFile) -> anyhow::Result<String> {
+        let Some(name) = &file.name else {
+            bail!("vocabulary file has no name");
+        };
+        let Some(archive) = archive else {
+            bail!("vocabulary file `{name}` can't be loaded: model has no archive");
+        };
+        String::from_utf8(archive.read(name)?)
+            .with_context(|| format!("vocabulary file `{name}` is not UTF-8"))
+    }
+
+    pub fn id(&self, token: &str) -> Option<i32> {
+        self.ids.get(token).copied()
+    }
+
+    pub fn token(&self, id: i32) -> Option<&str> {
+        self.tokens.get(&id).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// Finds the tokenizer process unit of a subgraph's inputs.
+///
+/// BERT and SentencePiece tokenizers are described in the subgraph's input process units, while
+/// regex tokenizers are described in the process units of its input tensor.
+fn tokenizer_options(meta: &ModelInfo, subgraph: usize) -> anyhow::Result<&ProcessUnitOptions> {
+    let sub = meta
+        .subgraph_metadata
+        .get(subgraph)
+        .ok_or_else(|| anyhow!("model has no metadata for subgraph {subgraph}"))?;
+    let tensor_units = sub
+        .input_tensor_metadata
+        .first()
+        .map_or(&[][..], |tensor| &tensor.process_units);
+    sub.input_process_units
+        .iter()
+        .chain(tensor_units)
+        .filter_map(|unit| unit.options.as_ref())
+        .find(|options| !options.associated_files().is_empty())
+        .ok_or_else(|| anyhow!("subgraph {subgraph} has no tokenizer process unit"))
+}
+
+/// Returns the sequence length of a subgraph's inputs, if it is static.
+fn max_seq_len(tflite: &[u8], subgraph: usize) -> anyhow::Result<Option<usize>> {
+    let graph = ModelGraph::from_bytes(tflite)?;
+    let Some(tensor) = graph
+        .subgraphs
+        .get(subgraph)
+        .and_then(|sub| sub.tensor(*sub.inputs.first()?))
+    else {
+        return Ok(None);
+    };
+    let dynamic = tensor
+        .shape_signature
+        .as_ref()
+        .and_then(|sig| sig.last())
+        .is_some_and(|&dim| dim < 0);
+    Ok(tensor
+        .shape
+        .last()
+        .filter(|&&len| !dynamic && len > 0)
+        .map(|&len| len as usize))
+}
+
+/// The tokenizer described by the metadata of a subgraph's inputs.
+#[derive(Debug, Clone)]
+pub enum Tokenizer {
+    Bert(BertTokenizer),
+    Regex(RegexTokenizer),
+}
+
+impl Tokenizer {
+    /// Creates the tokenizer described by the tokenizer process unit of a subgraph's inputs.
+    ///
+    /// `tflite` is the content of a `.tflite` file. The sequence length is taken from the shape of
+    /// the subgraph's first input tensor.
+    pub fn from_model(tflite: &[u8], subgraph: usize) -> anyhow::Result<Self> {
+        let meta = ModelInfo::from_bytes(tflite)?;
+        let options = tokenizer_options(&meta, subgraph)?;
+        let [file, ..] = &options.associated_files()[..] else {
+            unreachable!("tokenizer options without files are skipped");
+        };
+        let vocab = Vocab::read(Archive::from_bytes(tflite)?.as_mut(), file)?;
+        let max_seq_len = max_seq_len(tflite, subgraph)?;
+
+        match options {
+            ProcessUnitOptions::BertTokenizerOptions(_) => {
+                let mut tokenizer = BertTokenizer::new(Vocab::parse(&vocab))?;
+                tokenizer.max_seq_len = max_seq_len;
+                Ok(Self::Bert(tokenizer))
+            }
+            ProcessUnitOptions::RegexTokenizerOptions(options) => {
+                let pattern = options.delim_regex_pattern.as_deref().unwrap_or_default();
+                let vocab = Vocab::parse_indexed(&vocab).with_context(|| {
+                    format!(
+                        "invalid vocabulary file `{}`",
+                        file.name.as_deref().unwrap_or_default()
+                    )
+                })?;
+                let mut tokenizer = RegexTokenizer::new(pattern, vocab)?;
+                tokenizer.max_seq_len = max_seq_len;
+                Ok(Self::Regex(tokenizer))
+            }
+            ProcessUnitOptions::SentencePieceTokenizerOptions(_) => {
+                bail!("SentencePiece tokenizers are not supported")
+            }
+            _ => unreachable!("only tokenizer options have associated files"),
+        }
+    }
+
+    pub fn encode(&self, text: &str) -> Encoding {
+        match self {
+            Self::Bert(tokenizer) => tokenizer.encode(text),
+            Self::Regex(tokenizer) => tokenizer.encode(text),
+        }
+    }
+}
+
+/// BERT tokenizer: basic tokenization on whitespace and punctuation, then WordPiece.
+///
+/// This follows the TFLite Task Library, which lowercases ASCII characters but doesn't strip
+/// accents, and maps words without a WordPiece segmentation to a single `[UNK]` token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BertTokenizer {
+    pub vocab: Vocab,
+    /// Pad or truncate encodings to this length, including the `[CLS]` and `[SEP]` tokens.
+    pub max_seq_len: Option<usize>,
+    /// Whether to lowercase the input, which uncased models expect.
+    pub lower_case: bool,
+    cls: i32,
+    sep: i32,
+    unk: i32,
+    pad: i32,
+}
+
+impl BertTokenizer {
+    /// Prefix of WordPiece continuation tokens.
+    const SUFFIX_INDICATOR: &'static str = "##";
+    /// Longer words are mapped to `[UNK]`.
+    const MAX_BYTES_PER_TOKEN: usize = 100;
+
+    pub fn new(vocab: Vocab) -> anyhow::Result<Self> {
+        let special = |token| {
+            vocab
+                .id(token)
+                .ok_or_else(|| anyhow!("vocabulary contains no `{token}` token"))
+        };
+        Ok(Self {
+            cls: special("[CLS]")?,
+            sep: special("[SEP]")?,
+            unk: special("[UNK]")?,
+            pad: vocab.id("[PAD]").unwrap_or(0),
+            vocab,
+            max_seq_len: None,
+            lower_case: true,
+        })
+    }
+
+    /// Creates the tokenizer described by the `BertTokenizerOptions` of a subgraph's inputs.
+    ///
+    /// See [`Tokenizer::from_model`].
+    pub fn from_model(tflite: &[u8], subgraph: usize) -> anyhow::Result<Self> {
+        match Tokenizer::from_model(tflite, subgraph)? {
+            Tokenizer::Bert(tokenizer) => Ok(tokenizer),
+            _ => bail!("subgraph {subgraph} has no BERT tokenizer"),
+        }
+    }
+
+    /// Splits `text` into WordPiece tokens.
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        let text = if self.lower_case {
+            text.to_ascii_lowercase()
+        } else {
+            text.to_string()
+        };
+        let mut tokens = Vec::new();
+        for word in basic_tokenize(&text) {
+            self.word_piece(word, &mut tokens);
+        }
+        tokens
+    }
+
+    fn word_piece(&self, word: &str, tokens: &mut Vec<String>) {
+        if word.len() > Self::MAX_BYTES_PER_TOKEN {
+            tokens.push(self.vocab.token(self.unk).unwrap().to_string());
+            return;
+        }
+
+        let start_len = tokens.len();
+        let mut start = 0;
+        while start < word.len() {
+            // Greedily take the longest prefix of the rest of the word that is in the vocabulary.
+            let piece = word[start..]
+                .char_indices()
+                .map(|(i, c)| start + i + c.len_utf8())
+                .rev()
+                .find_map(|end| {
+                    let piece = match start {
+                        0 => word[..end].to_string(),
+                        _ => format!("{}{}", Self::SUFFIX_INDICATOR, &word[start..end]),
+                    };
+                    self.vocab.id(&piece).map(|_| (piece, end))
+                });
+            match piece {
+                Some((piece, end)) => {
+                    tokens.push(piece);
+                    start = end;
+                }
+                None => {
+                    tokens.truncate(start_len);
+                    tokens.push(self.vocab.token(self.unk).unwrap().to_string());
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Encodes `text` as `[CLS] tokens... [SEP]`, truncated and padded to
+    /// [`max_seq_len`][Self::max_seq_len] if set.
+    pub fn encode(&self, text: &str) -> Encoding {
+        let mut tokens = self.tokenize(text);
+        if let Some(len) = self.max_seq_len {
+            tokens.truncate(len.saturating_sub(2));
+        }
+        let cls = self.vocab.token(self.cls).unwrap().to_string();
+        let sep = self.vocab.token(self.sep).unwrap().to_string();
+        tokens.insert(0, cls);
+        tokens.push(sep);
+
+        let mut ids = tokens
+            .iter()
+            .map(|token| self.vocab.id(token).unwrap_or(self.unk))
+            .collect::<Vec<_>>();
+        let mut mask = vec![1; ids.len()];
+        if let Some(len) = self.max_seq_len {
+            ids.resize(len, self.pad);
+            mask.resize(len, 0);
+            tokens.truncate(len);
+        }
+        Encoding {
+            tokens,
+            segment_ids: vec![0; ids.len()],
+            ids,
+            mask,
+        }
+    }
+}
+
+/// Tokenizer that splits text on the matches of a delimiter regex, as described by
+/// `RegexTokenizerOptions`.
+///
+/// Like the TFLite Task Library's `NLClassifier`, encodings start with the `<START>` token if the
+/// vocabulary has one, map words not in the vocabulary to `<UNKNOWN>`, and are padded with `<PAD>`.
+/// Special tokens missing from the vocabulary default to ID 0.
+#[derive(Debug, Clone)]
+pub struct RegexTokenizer {
+    pub delim_regex: Regex,
+    pub vocab: Vocab,
+    /// Pad or truncate encodings to this length, including the `<START>` token.
+    pub max_seq_len: Option<usize>,
+}
+
+impl RegexTokenizer {
+    const START: &'static str = "<START>";
+    const UNKNOWN: &'static str = "<UNKNOWN>";
+    const PAD: &'static str = "<PAD>";
+
+    pub fn new(delim_regex_pattern: &str, vocab: Vocab) -> anyhow::Result<Self> {
+        let delim_regex = Regex::new(delim_regex_pattern)
+            .with_context(|| format!("invalid delimiter regex `{delim_regex_pattern}`"))?;
+        Ok(Self {
+            delim_regex,
+            vocab,
+            max_seq_len: None,
+        })
+    }
+
+    /// Creates the tokenizer described by the `RegexTokenizerOptions` of a subgraph's inputs.
+    ///
+    /// See [`Tokenizer::from_model`].
+    pub fn from_model(tflite: &[u8], subgraph: usize) -> anyhow::Result<Self> {
+        match Tokenizer::from_model(tflite, subgraph)? {
+            Tokenizer::Regex(tokenizer) => Ok(tokenizer),
+            _ => bail!("subgraph {subgraph} has no regex tokenizer"),
+        }
+    }
+
+    /// Splits `text` into the non-empty strings between delimiters.
+    pub fn tokenize<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        self.delim_regex
+            .split(text)
+            .filter(|token| !token.is_empty())
+            .collect()
+    }
+
+    /// Encodes `text` as `[<START>] tokens...`, truncated and padded to
+    /// [`max_seq_len`][Self::max_seq_len] if set.
+    pub fn encode(&self, text: &str) -> Encoding {
+        let unknown = self.vocab.id(Self::UNKNOWN).unwrap_or(0);
+        let mut tokens = Vec::new();
+        let mut ids = Vec::new();
+        if let Some(start) = self.vocab.id(Self::START) {
+            tokens.push(Self::START.to_string());
+            ids.push(start);
+        }
+        for token in self.tokenize(text) {
+            match self.vocab.id(token) {
+                Some(id) => {
+                    tokens.push(token.to_string());
+                    ids.push(id);
+                }
+                None => {
+                    tokens.push(Self::UNKNOWN.to_string());
+                    ids.push(unknown);
+                }
+            }
+        }
+        if let Some(len) = self.max_seq_len {
+            tokens.truncate(len);
+            ids.resize(len, self.vocab.id(Self::PAD).unwrap_or(0));
+        }
+        Encoding {
+            tokens,
+            ids,
+            ..Default::default()
+        }
+    }
+}
+
+/// Splits text on whitespace, and splits off punctuation and CJK characters as separate words.
+fn basic_tokenize(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() || c.is_control() {
+            if let Some(s) = start.take() {
+                words.push(&text[s..i]);
+            }
+        } else if is_punctuation(c) || is_cjk(c) {
+            if let Some(s) = start.take() {
+                words.push(&text[s..i]);
+            }
+            words.push(&text[i..i + c.len_utf8()]);
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(s) = start {
+        words.push(&text[s..]);
+    }
+    words
+}
+
+/// Returns whether `c` is ASCII punctuation (including symbols like `$` and `` ` ``), or in the
+/// Unicode punctuation categories, like BERT's `_is_punctuation`.
+fn is_punctuation(c: char) -> bool {
+    static PUNCTUATION: OnceLock<Regex> = OnceLock::new();
+    c.is_ascii_punctuation()
+        || PUNCTUATION
+            .get_or_init(|| Regex::new(r"^\p{P}$").unwrap())
+            .is_match(c.encode_utf8(&mut [0; 4]))
+}
+
+/// Returns whether `c` is a CJK ideograph, which BERT treats as a word of its own.
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{4e00}'..='\u{9fff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{20000}'..='\u{2a6df}'
+            | '\u{2a700}'..='\u{2b73f}'
+            | '\u{2b740}'..='\u{2b81f}'
+            | '\u{2b820}'..='\u{2ceaf}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{2f800}'..='\u{2fa1f}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bert() -> BertTokenizer {
+        let vocab = Vocab::parse(
+            "[PAD]\n[UNK]\n[CLS]\n[SEP]\nun\n##aff\n##able\nhello\nworld\n!\n,\ncafé\n中\n国\n¿\n##un\n",
+        );
+        BertTokenizer::new(vocab).unwrap()
+    }
+
+    #[test]
+    fn word_piece() {
+        let tokenizer = bert();
+        assert_eq!(tokenizer.tokenize("unaffable"), ["un", "##aff", "##able"]);
+        // Words without a segmentation become a single `[UNK]`.
+        assert_eq!(tokenizer.tokenize("unaffablex hello"), ["[UNK]", "hello"]);
+    }
+
+    #[test]
+    fn lower_case() {
+        let mut tokenizer = bert();
+        assert_eq!(tokenizer.tokenize("Hello WORLD"), ["hello", "world"]);
+        tokenizer.lower_case = false;
+        assert_eq!(tokenizer.tokenize("Hello world"), ["[UNK]", "world"]);
+    }
+
+    #[test]
+    fn accents_are_kept() {
+        // Like the Task Library, only ASCII characters are lowercased and accents aren't
+        // stripped.
+        let tokenizer = bert();
+        assert_eq!(tokenizer.tokenize("café cafe"), ["café", "[UNK]"]);
+        assert_eq!(tokenizer.tokenize("CAFÉ"), ["[UNK]"]);
+    }
+
+    #[test]
+    fn punctuation_and_cjk() {
+        let tokenizer = bert();
+        assert_eq!(
+            tokenizer.tokenize("hello,world!"),
+            ["hello", ",", "world", "!"]
+        );
+        assert_eq!(tokenizer.tokenize("¿hello"), ["¿", "hello"]);
+        assert_eq!(tokenizer.tokenize("中国hello"), ["中", "国", "hello"]);
+        assert_eq!(
+            basic_tokenize("a\u{2014}b\u{300c}c\u{ff01} d$e"),
+            ["a", "\u{2014}", "b", "\u{300c}", "c", "\u{ff01}", "d", "$", "e"]
+        );
+    }
+
+    #[test]
+    fn long_words_are_unknown() {
+        let tokenizer = bert();
+        let long = "un".repeat(BertTokenizer::MAX_BYTES_PER_TOKEN / 2);
+        assert_eq!(tokenizer.tokenize(&long).len(), 50);
+        assert_eq!(tokenizer.tokenize(&format!("{long}un")), ["[UNK]"]);
+    }
+
+    #[test]
+    fn bert_encode() {
+        let mut tokenizer = bert();
+        tokenizer.max_seq_len = Some(6);
+        let encoding = tokenizer.encode("hello unaffable world");
+        assert_eq!(
+            encoding.tokens,
+            ["[CLS]", "hello", "un", "##aff", "##able", "[SEP]"]
+        );
+        assert_eq!(encoding.ids, [2, 7, 4, 5, 6, 3]);
+        assert_eq!(encoding.mask, [1; 6]);
+
+        let encoding = tokenizer.encode("hello");
+        assert_eq!(encoding.ids, [2, 7, 3, 0, 0, 0]);
+        assert_eq!(encoding.mask, [1, 1, 1, 0, 0, 0]);
+        assert_eq!(encoding.segment_ids, [0; 6]);
+    }
+
+    /// The delimiter pattern of the Task Library's `NLClassifier` example models.
+    const DELIM: &str = r"[^\w\']+";
+
+    #[test]
+    fn regex_split() {
+        let tokenizer = RegexTokenizer::new(DELIM, Vocab::default()).unwrap();
+        assert_eq!(
+            tokenizer.tokenize("  It's a  great movie, isn't it?!"),
+            ["It's", "a", "great", "movie", "isn't", "it"]
+        );
+        assert!(tokenizer.tokenize(" ,.").is_empty());
+        assert!(RegexTokenizer::new("[", Vocab::default()).is_err());
+    }
+
+    #[test]
+    fn regex_special_tokens() {
+        let vocab =
+            Vocab::parse_indexed("<PAD> 0\n<START> 1\n<UNKNOWN> 2\ngreat 3\nmovie 4\n").unwrap();
+        let mut tokenizer = RegexTokenizer::new(DELIM, vocab).unwrap();
+        tokenizer.max_seq_len = Some(5);
+        let encoding = tokenizer.encode("great film");
+        assert_eq!(encoding.tokens, ["<START>", "great", "<UNKNOWN>"]);
+        assert_eq!(encoding.ids, [1, 3, 2, 0, 0]);
+        assert!(encoding.mask.is_empty() && encoding.segment_ids.is_empty());
+
+        let encoding = tokenizer.encode("great movie great movie great movie");
+        assert_eq!(encoding.ids, [1, 3, 4, 3, 4]);
+        assert_eq!(encoding.tokens.len(), 5);
+    }
+
+    #[test]
+    fn regex_without_special_tokens() {
+        // Without `<START>`, nothing is prepended; `<UNKNOWN>` and `<PAD>` default to ID 0.
+        let vocab = Vocab::parse_indexed("great 5\nmovie 6\n").unwrap();
+        let mut tokenizer = RegexTokenizer::new(DELIM, vocab).unwrap();
+        tokenizer.max_seq_len = Some(4);
+        let encoding = tokenizer.encode("great film");
+        assert_eq!(encoding.tokens, ["great", "<UNKNOWN>"]);
+        assert_eq!(encoding.ids, [5, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,501 @@
+//! Consistency checks between the metadata and the model graph.
+
+use core::fmt;
+
+use serde::Serialize;
+
+use crate::{
+    ColorSpaceType, ContentProperties, ModelGraph, ModelInfo, ProcessUnitOptions, SubGraph,
+    SubGraphMetadata, Tensor, TensorGroup, TensorMetadata, TensorType,
+};
+
+/// A single violation found by [`validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// Where in the metadata the problem was found, e.g. `subgraph 0 input 1 ("image")`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Checks each [`SubGraphMetadata`] against the [`SubGraph`] at the same index.
+///
+/// Returns one [`Diagnostic`] per violation; an empty list means the metadata is consistent with
+/// the graph.
+pub fn validate(meta: &ModelInfo, graph: &ModelGraph) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    if meta.subgraph_metadata.len() > graph.subgraphs.len() {
+        diags.push(Diagnostic {
+            location: "model".into(),
+            message: format!(
+                "metadata describes {} subgraph(s), but the model only has {}",
+                meta.subgraph_metadata.len(),
+                graph.subgraphs.len(),
+            ),
+        });
+    }
+
+    for (index, (sub_meta, sub)) in meta
+        .subgraph_metadata
+        .iter()
+        .zip(&graph.subgraphs)
+        .enumerate()
+    {
+        validate_subgraph(&mut diags, index, sub_meta, sub);
+    }
+    diags
+}
+
+fn validate_subgraph(
+    diags: &mut Vec<Diagnostic>,
+    index: usize,
+    meta: &SubGraphMetadata,
+    sub: &SubGraph,
+) {
+    let location = format!("subgraph {index}");
+    for (kind, tensor_meta, tensors) in [
+        ("input", &meta.input_tensor_metadata, &sub.inputs),
+        ("output", &meta.output_tensor_metadata, &sub.outputs),
+    ] {
+        if tensor_meta.len() != tensors.len() {
+            diags.push(Diagnostic {
+                location: location.clone(),
+                message: format!(
+                    "{} {kind} tensor metadata entries, but the subgraph has {} {kind}s",
+                    tensor_meta.len(),
+                    tensors.len(),
+                ),
+            });
+        }
+
+        for (i, (tensor_meta, &tensor)) in tensor_meta.iter().zip(tensors).enumerate() {
+            let location = format!(
+                "{location} {kind} {i} ({:?})",
+                tensor_meta.name.as_deref().unwrap_or("<unnamed>"),
+            );
+            match sub.tensor(tensor) {
+                Some(tensor) => validate_tensor(diags, &location, tensor_meta, tensor),
+                None => diags.push(Diagnostic {
+                    location,
+                    message: format!("subgraph {kind} refers to invalid tensor index {tensor}"),
+                }),
+            }
+        }
+    }
+
+    for (kind, groups, tensor_meta) in [
+        (
+            "input",
+            &meta.input_tensor_groups,
+            &meta.input_tensor_metadata,
+        ),
+        (
+            "output",
+            &meta.output_tensor_groups,
+            &meta.output_tensor_metadata,
+        ),
+    ] {
+        validate_groups(diags, &location, kind, groups, tensor_meta);
+    }
+}
+
+fn validate_tensor(
+    diags: &mut Vec<Diagnostic>,
+    location: &str,
+    meta: &TensorMetadata,
+    tensor: &Tensor,
+) {
+    let mut error = |message: String| {
+        diags.push(Diagnostic {
+            location: location.into(),
+            message,
+        })
+    };
+
+    let shape = &tensor.shape;
+    let channels = shape.last().copied().filter(|&c| c > 0);
+    match meta
+        .content
+        .as_ref()
+        .and_then(|c| c.content_properties.as_ref())
+    {
+        Some(ContentProperties::ImageProperties(props)) => {
+            if shape.len() != 4 {
+                error(format!(
+                    "image tensor must have rank 4 (NHWC), but has shape {shape:?}"
+                ));
+            }
+            if !matches!(
+                tensor.type_,
+                TensorType::UINT8 | TensorType::INT8 | TensorType::FLOAT32
+            ) {
+                error(format!(
+                    "image tensor must be UINT8, INT8 or FLOAT32, but is {:?}",
+                    tensor.type_
+                ));
+            }
+            let expected_channels = match props.color_space {
+                ColorSpaceType::RGB => Some(3),
+                ColorSpaceType::GRAYSCALE => Some(1),
+                _ => None,
+            };
+            if let (Some(expected), Some(actual)) = (expected_channels, channels) {
+                if shape.len() == 4 && expected != actual {
+                    error(format!(
+                        "color space {:?} needs {expected} channel(s), but the tensor has {actual}",
+                        props.color_space,
+                    ));
+                }
+            }
+            if let (Some(size), [_, height, width, _]) = (props.default_size, &shape[..]) {
+                let matches = |dim: i32, expected: u32| dim <= 0 || dim as u32 == expected;
+                if !matches(*width, size.width) || !matches(*height, size.height) {
+                    error(format!(
+                        "default image size is {}x{}, but the tensor has shape {shape:?}",
+                        size.width, size.height,
+                    ));
+                }
+            }
+        }
+        Some(ContentProperties::FeatureProperties(_)) => {
+            if matches!(tensor.type_, TensorType::STRING) {
+                error("feature tensor has type STRING".into());
+            }
+        }
+        Some(ContentProperties::BoundingBoxProperties(props)) => {
+            if props.index.len() != 4 {
+                error(format!(
+                    "bounding box index must have exactly 4 entries, but has {:?}",
+                    props.index,
+                ));
+            }
+            if let Some(coords) = channels {
+                if let Some(&bad) = props.index.iter().find(|&&i| i >= coords as u32) {
+                    error(format!(
+                        "bounding box index {bad} is out of range for a last dimension of {coords}"
+                    ));
+                }
+            }
+        }
+        Some(ContentProperties::AudioProperties(props)) => {
+            if tensor.type_ != TensorType::FLOAT32 {
+                error(format!(
+                    "audio tensor must be FLOAT32, but is {:?}",
+                    tensor.type_
+                ));
+            }
+            if props.channels == 0 || props.sample_rate == 0 {
+                error(format!(
+                    "audio properties need a nonzero sample rate and channel count, got {props:?}"
+                ));
+            }
+        }
+        Some(ContentProperties::Unknown(_)) | None => {}
+    }
+
+    for unit in &meta.process_units {
+        let Some(ProcessUnitOptions::NormalizationOptions(norm)) = &unit.options else {
+            continue;
+        };
+        if norm.mean.len() != norm.std.len() {
+            error(format!(
+                "normalization mean has {} value(s), but std has {}",
+                norm.mean.len(),
+                norm.std.len(),
+            ));
+        }
+        for (name, values) in [("mean", &norm.mean), ("std", &norm.std)] {
+            let ok = match channels {
+                Some(channels) => values.len() == 1 || values.len() == channels as usize,
+                None => !values.is_empty(),
+            };
+            if !ok {
+                error(format!(
+                    "normalization {name} has {} value(s), but the channel dimension of {shape:?} \
+                     needs 1 or {}",
+                    values.len(),
+                    channels.unwrap_or(0),
+                ));
+            }
+        }
+        if norm.std.contains(&0.0) {
+            error("normalization std contains 0".into());
+        }
+    }
+}
+
+fn validate_groups(
+    diags: &mut Vec<Diagnostic>,
+    location: &str,
+    kind: &str,
+    groups: &[TensorGroup],
+    tensor_meta: &[TensorMetadata],
+) {
+    for (i, group) in groups.iter().enumerate() {
+        let location = format!(
+            "{location} {kind} tensor group {i} ({:?})",
+            group.name.as_deref().unwrap_or("<unnamed>"),
+        );
+        for name in &group.tensor_names {
+            if !tensor_meta.iter().any(|t| t.name.as_ref() == Some(name)) {
+                diags.push(Diagnostic {
+                    location: location.clone(),
+                    message: format!("group refers to unknown {kind} tensor {name:?}"),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BoundingBoxProperties, Content, ImageProperties, ImageSize, NormalizationOptions,
+        ProcessUnit,
+    };
+
+    fn tensor(shape: &[i32], type_: TensorType) -> Tensor {
+        Tensor {
+            name: None,
+            shape: shape.to_vec(),
+            shape_signature: None,
+            type_,
+            buffer: 0,
+            is_constant: false,
+            is_variable: false,
+            quantization: None,
+            sparsity: None,
+        }
+    }
+
+    /// A graph with one subgraph whose inputs and outputs are the given tensors.
+    fn graph(inputs: Vec<Tensor>, outputs: Vec<Tensor>) -> ModelGraph {
+        let ins = (0..inputs.len() as i32).collect();
+        let outs = (inputs.len() as i32..(inputs.len() + outputs.len()) as i32).collect();
+        ModelGraph {
+            version: 3,
+            description: None,
+            operator_codes: Vec::new(),
+            subgraphs: vec![SubGraph {
+                name: None,
+                tensors: inputs.into_iter().chain(outputs).collect(),
+                inputs: ins,
+                outputs: outs,
+                operators: Vec::new(),
+            }],
+            signature_defs: Vec::new(),
+        }
+    }
+
+    fn model(inputs: Vec<TensorMetadata>, outputs: Vec<TensorMetadata>) -> ModelInfo {
+        ModelInfo {
+            subgraph_metadata: vec![SubGraphMetadata {
+                input_tensor_metadata: inputs,
+                output_tensor_metadata: outputs,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn tensor_meta(
+        name: &str,
+        props: Option<ContentProperties>,
+        norm: Option<(&[f32], &[f32])>,
+    ) -> TensorMetadata {
+        TensorMetadata {
+            name: Some(name.into()),
+            content: props.map(|props| Content {
+                content_properties: Some(props),
+                range: None,
+            }),
+            process_units: norm
+                .map(|(mean, std)| ProcessUnit {
+                    options: Some(ProcessUnitOptions::NormalizationOptions(
+                        NormalizationOptions {
+                            mean: mean.to_vec(),
+                            std: std.to_vec(),
+                        },
+                    )),
+                })
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn image(color_space: ColorSpaceType, default_size: Option<(u32, u32)>) -> ContentProperties {
+        ContentProperties::ImageProperties(ImageProperties {
+            color_space,
+            default_size: default_size.map(|(width, height)| ImageSize { width, height }),
+        })
+    }
+
+    fn boxes(index: &[u32]) -> ContentProperties {
+        ContentProperties::BoundingBoxProperties(BoundingBoxProperties {
+            index: index.to_vec(),
+            ..Default::default()
+        })
+    }
+
+    /// Returns the diagnostics as `location: message` strings.
+    fn messages(meta: &ModelInfo, graph: &ModelGraph) -> Vec<String> {
+        validate(meta, graph)
+            .into_iter()
+            .map(|diag| diag.to_string())
+            .collect()
+    }
+
+    fn valid() -> (ModelInfo, ModelGraph) {
+        let meta = model(
+            vec![tensor_meta(
+                "image",
+                Some(image(ColorSpaceType::RGB, Some((224, 224)))),
+                Some((&[127.5], &[127.5])),
+            )],
+            vec![tensor_meta("boxes", Some(boxes(&[1, 0, 3, 2])), None)],
+        );
+        let graph = graph(
+            vec![tensor(&[1, 224, 224, 3], TensorType::FLOAT32)],
+            vec![tensor(&[1, 10, 4], TensorType::FLOAT32)],
+        );
+        (meta, graph)
+    }
+
+    #[test]
+    fn valid_model() {
+        let (meta, graph) = valid();
+        assert_eq!(messages(&meta, &graph), Vec::<String>::new());
+    }
+
+    #[test]
+    fn count_mismatch() {
+        let (mut meta, graph) = valid();
+        meta.subgraph_metadata[0].output_tensor_metadata.clear();
+        meta.subgraph_metadata.push(SubGraphMetadata::default());
+        assert_eq!(
+            messages(&meta, &graph),
+            [
+                "model: metadata describes 2 subgraph(s), but the model only has 1",
+                "subgraph 0: 0 output tensor metadata entries, but the subgraph has 1 outputs",
+            ]
+        );
+    }
+
+    #[test]
+    fn image_rank() {
+        let (meta, mut graph) = valid();
+        graph.subgraphs[0].tensors[0].shape = vec![224, 224, 3];
+        assert_eq!(
+            messages(&meta, &graph),
+            [
+                r#"subgraph 0 input 0 ("image"): image tensor must have rank 4 (NHWC), but has shape [224, 224, 3]"#
+            ]
+        );
+    }
+
+    #[test]
+    fn image_type() {
+        let (meta, mut graph) = valid();
+        graph.subgraphs[0].tensors[0].type_ = TensorType::INT32;
+        assert_eq!(
+            messages(&meta, &graph),
+            [
+                r#"subgraph 0 input 0 ("image"): image tensor must be UINT8, INT8 or FLOAT32, but is INT32"#
+            ]
+        );
+    }
+
+    #[test]
+    fn image_channels() {
+        let (mut meta, graph) = valid();
+        meta.subgraph_metadata[0].input_tensor_metadata[0] =
+            tensor_meta("image", Some(image(ColorSpaceType::GRAYSCALE, None)), None);
+        assert_eq!(
+            messages(&meta, &graph),
+            [
+                r#"subgraph 0 input 0 ("image"): color space GRAYSCALE needs 1 channel(s), but the tensor has 3"#
+            ]
+        );
+    }
+
+    #[test]
+    fn default_size() {
+        let (mut meta, mut graph) = valid();
+        meta.subgraph_metadata[0].input_tensor_metadata[0].content = Some(Content {
+            content_properties: Some(image(ColorSpaceType::RGB, Some((320, 240)))),
+            range: None,
+        });
+        assert_eq!(
+            messages(&meta, &graph),
+            [
+                r#"subgraph 0 input 0 ("image"): default image size is 320x240, but the tensor has shape [1, 224, 224, 3]"#
+            ]
+        );
+
+        // Dynamic dimensions match any size.
+        graph.subgraphs[0].tensors[0].shape = vec![1, -1, -1, 3];
+        assert_eq!(messages(&meta, &graph), Vec::<String>::new());
+    }
+
+    #[test]
+    fn bounding_box_index() {
+        let (mut meta, graph) = valid();
+        meta.subgraph_metadata[0].output_tensor_metadata[0] =
+            tensor_meta("boxes", Some(boxes(&[0, 1, 2])), None);
+        assert_eq!(
+            messages(&meta, &graph),
+            [
+                r#"subgraph 0 output 0 ("boxes"): bounding box index must have exactly 4 entries, but has [0, 1, 2]"#
+            ]
+        );
+
+        meta.subgraph_metadata[0].output_tensor_metadata[0] =
+            tensor_meta("boxes", Some(boxes(&[0, 1, 2, 4])), None);
+        assert_eq!(
+            messages(&meta, &graph),
+            [
+                r#"subgraph 0 output 0 ("boxes"): bounding box index 4 is out of range for a last dimension of 4"#
+            ]
+        );
+    }
+
+    #[test]
+    fn normalization_lengths() {
+        let (mut meta, graph) = valid();
+        meta.subgraph_metadata[0].input_tensor_metadata[0] = tensor_meta(
+            "image",
+            Some(image(ColorSpaceType::RGB, None)),
+            Some((&[0.0, 0.0], &[1.0, 0.0, 1.0])),
+        );
+        assert_eq!(
+            messages(&meta, &graph),
+            [
+                r#"subgraph 0 input 0 ("image"): normalization mean has 2 value(s), but std has 3"#,
+                r#"subgraph 0 input 0 ("image"): normalization mean has 2 value(s), but the channel dimension of [1, 224, 224, 3] needs 1 or 3"#,
+                r#"subgraph 0 input 0 ("image"): normalization std contains 0"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn tensor_groups() {
+        let (mut meta, graph) = valid();
+        meta.subgraph_metadata[0].output_tensor_groups = vec![TensorGroup {
+            name: Some("detections".into()),
+            tensor_names: vec!["boxes".into(), "scores".into()],
+        }];
+        assert_eq!(
+            messages(&meta, &graph),
+            [
+                r#"subgraph 0 output tensor group 0 ("detections"): group refers to unknown output tensor "scores""#
+            ]
+        );
+    }
+}
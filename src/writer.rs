@@ -0,0 +1,759 @@
+//! Serialization of [`ModelInfo`] and populating it into a model, like the `tflite-support`
+//! `MetadataPopulator` does.
+
+use std::io::{Cursor, Write};
+
+use anyhow::{bail, ensure, Context};
+use flatbuffers::{FlatBufferBuilder, ForwardsUOffset, Push, Vector, WIPOffset};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::buffer::is_external;
+use crate::metadata::tflite as fb;
+use crate::rebuild::{Copier, Offset};
+use crate::v3c::tflite as v3c;
+use crate::{
+    Archive, AssociatedFile, Content, ContentProperties, CustomMetadata, ModelInfo, ProcessUnit,
+    ProcessUnitOptions, Stats, SubGraphMetadata, TensorGroup, TensorMetadata, METADATA_NAME,
+};
+
+/// File identifier of `ModelMetadata` flatbuffers.
+const METADATA_FILE_IDENTIFIER: &str = "M001";
+
+type Builder<'fbb> = FlatBufferBuilder<'fbb>;
+type Offsets<'fbb, T> = WIPOffset<Vector<'fbb, ForwardsUOffset<T>>>;
+
+impl ModelInfo {
+    /// Serializes the metadata as a `ModelMetadata` flatbuffer, suitable for the
+    /// `TFLITE_METADATA` buffer of a model.
+    pub fn to_flatbuffer(&self) -> Vec<u8> {
+        let mut fbb = FlatBufferBuilder::new();
+        let args = fb::ModelMetadataArgs {
+            name: string(&mut fbb, &self.name),
+            description: string(&mut fbb, &self.description),
+            version: string(&mut fbb, &self.version),
+            subgraph_metadata: tables(&mut fbb, &self.subgraph_metadata, subgraph),
+            author: string(&mut fbb, &self.author),
+            license: string(&mut fbb, &self.license),
+            associated_files: tables(&mut fbb, &self.associated_files, associated_file),
+            min_parser_version: string(&mut fbb, &self.min_parser_version),
+        };
+        let root = fb::ModelMetadata::create(&mut fbb, &args);
+        fbb.finish(root, Some(METADATA_FILE_IDENTIFIER));
+        fbb.finished_data().to_vec()
+    }
+}
+
+/// Populates `meta` and the given associated files into a model.
+///
+/// `tflite` is the content of a `.tflite` file. The metadata is stored in the buffer referenced
+/// by the model's `TFLITE_METADATA` entry, which is added if it doesn't exist yet. `files` are
+/// `(name, contents)` pairs that are packed into the zip archive appended to the model, replacing
+/// existing files of the same name.
+///
+/// Like the upstream populator, this fails if an [`AssociatedFile`] declared in `meta` is neither
+/// passed in `files` nor already packed into the model.
+pub fn write_metadata(
+    tflite: &[u8],
+    meta: &ModelInfo,
+    files: &[(String, Vec<u8>)],
+) -> anyhow::Result<Vec<u8>> {
+    let mut archive = Archive::from_bytes(tflite)?;
+    let packed = archive
+        .as_ref()
+        .map_or(Vec::new(), |archive| archive.entries().to_vec());
+
+    for file in meta.all_associated_files() {
+        let Some(name) = &file.name else { continue };
+        if !files.iter().any(|(f, _)| f == name) && !packed.iter().any(|e| &e.name == name) {
+            bail!("associated file `{name}` is declared in the metadata, but was not provided");
+        }
+    }
+
+    let model = match &archive {
+        Some(archive) => &tflite[..archive.offset() as usize],
+        None => tflite,
+    };
+    let mut out = set_metadata_buffer(model, METADATA_NAME, &meta.to_flatbuffer())?;
+
+    if !packed.is_empty() || !files.is_empty() {
+        let mut cursor = Cursor::new(out);
+        cursor.set_position(cursor.get_ref().len() as u64);
+        let mut zip = ZipWriter::new(cursor);
+        // The TFLite metadata extractor only supports uncompressed files.
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        if let Some(archive) = &mut archive {
+            for entry in &packed {
+                if files.iter().any(|(name, _)| *name == entry.name) {
+                    continue;
+                }
+                let data = archive.read(&entry.name)?;
+                zip.start_file(&*entry.name, options)?;
+                zip.write_all(&data)?;
+            }
+        }
+        for (name, data) in files {
+            zip.start_file(&**name, options)?;
+            zip.write_all(data)?;
+        }
+        out = zip.finish()?.into_inner();
+    }
+
+    ModelInfo::from_bytes(&out).context("failed to read back the written metadata")?;
+    Ok(out)
+}
+
+fn subgraph<'fbb>(
+    fbb: &mut Builder<'fbb>,
+    sub: &SubGraphMetadata,
+) -> WIPOffset<fb::SubGraphMetadata<'fbb>> {
+    let args = fb::SubGraphMetadataArgs {
+        name: string(fbb, &sub.name),
+        description: string(fbb, &sub.description),
+        input_tensor_metadata: tables(fbb, &sub.input_tensor_metadata, tensor),
+        output_tensor_metadata: tables(fbb, &sub.output_tensor_metadata, tensor),
+        associated_files: tables(fbb, &sub.associated_files, associated_file),
+        input_process_units: tables(fbb, &sub.input_process_units, process_unit),
+        output_process_units: tables(fbb, &sub.output_process_units, process_unit),
+        input_tensor_groups: tables(fbb, &sub.input_tensor_groups, tensor_group),
+        output_tensor_groups: tables(fbb, &sub.output_tensor_groups, tensor_group),
+        custom_metadata: tables(fbb, &sub.custom_metadata, custom_metadata),
+    };
+    fb::SubGraphMetadata::create(fbb, &args)
+}
+
+fn tensor<'fbb>(
+    fbb: &mut Builder<'fbb>,
+    tens: &TensorMetadata,
+) -> WIPOffset<fb::TensorMetadata<'fbb>> {
+    let dimension_names = (!tens.dimension_names.is_empty()).then(|| {
+        let names = tens
+            .dimension_names
+            .iter()
+            .map(|name| fbb.create_string(name))
+            .collect::<Vec<_>>();
+        fbb.create_vector(&names)
+    });
+    let args = fb::TensorMetadataArgs {
+        name: string(fbb, &tens.name),
+        description: string(fbb, &tens.description),
+        dimension_names,
+        content: tens.content.as_ref().map(|c| content(fbb, c)),
+        process_units: tables(fbb, &tens.process_units, process_unit),
+        stats: tens.stats.as_ref().map(|s| stats(fbb, s)),
+        associated_files: tables(fbb, &tens.associated_files, associated_file),
+    };
+    fb::TensorMetadata::create(fbb, &args)
+}
+
+fn associated_file<'fbb>(
+    fbb: &mut Builder<'fbb>,
+    file: &AssociatedFile,
+) -> WIPOffset<fb::AssociatedFile<'fbb>> {
+    let args = fb::AssociatedFileArgs {
+        name: string(fbb, &file.name),
+        description: string(fbb, &file.description),
+        type_: file.type_,
+        locale: string(fbb, &file.locale),
+        version: string(fbb, &file.version),
+    };
+    fb::AssociatedFile::create(fbb, &args)
+}
+
+fn content<'fbb>(fbb: &mut Builder<'fbb>, content: &Content) -> WIPOffset<fb::Content<'fbb>> {
+    let (content_properties_type, content_properties) = match &content.content_properties {
+        None => (fb::ContentProperties::NONE, None),
+        Some(ContentProperties::FeatureProperties(_)) => {
+            let props = fb::FeatureProperties::create(fbb, &fb::FeaturePropertiesArgs {});
+            (
+                fb::ContentProperties::FeatureProperties,
+                Some(props.as_union_value()),
+            )
+        }
+        Some(ContentProperties::ImageProperties(props)) => {
+            let default_size = props.default_size.map(|size| {
+                let args = fb::ImageSizeArgs {
+                    width: size.width,
+                    height: size.height,
+                };
+                fb::ImageSize::create(fbb, &args)
+            });
+            let args = fb::ImagePropertiesArgs {
+                color_space: props.color_space,
+                default_size,
+            };
+            let props = fb::ImageProperties::create(fbb, &args);
+            (
+                fb::ContentProperties::ImageProperties,
+                Some(props.as_union_value()),
+            )
+        }
+        Some(ContentProperties::BoundingBoxProperties(props)) => {
+            let args = fb::BoundingBoxPropertiesArgs {
+                index: Some(fbb.create_vector(&props.index)),
+                type_: props.type_,
+                coordinate_type: props.coordinate_type,
+            };
+            let props = fb::BoundingBoxProperties::create(fbb, &args);
+            (
+                fb::ContentProperties::BoundingBoxProperties,
+                Some(props.as_union_value()),
+            )
+        }
+        Some(ContentProperties::AudioProperties(props)) => {
+            let args = fb::AudioPropertiesArgs {
+                sample_rate: props.sample_rate,
+                channels: props.channels,
+            };
+            let props = fb::AudioProperties::create(fbb, &args);
+            (
+                fb::ContentProperties::AudioProperties,
+                Some(props.as_union_value()),
+            )
+        }
+        // There is no way to reconstruct the contents of an unknown union member.
+        Some(ContentProperties::Unknown(_)) => (fb::ContentProperties::NONE, None),
+    };
+    let range = content.range.map(|range| {
+        let args = fb::ValueRangeArgs {
+            min: range.min,
+            max: range.max,
+        };
+        fb::ValueRange::create(fbb, &args)
+    });
+    let args = fb::ContentArgs {
+        content_properties_type,
+        content_properties,
+        range,
+    };
+    fb::Content::create(fbb, &args)
+}
+
+fn process_unit<'fbb>(
+    fbb: &mut Builder<'fbb>,
+    pu: &ProcessUnit,
+) -> WIPOffset<fb::ProcessUnit<'fbb>> {
+    let (options_type, options) = match &pu.options {
+        None | Some(ProcessUnitOptions::Unknown(_)) => (fb::ProcessUnitOptions::NONE, None),
+        Some(ProcessUnitOptions::NormalizationOptions(opts)) => {
+            let args = fb::NormalizationOptionsArgs {
+                mean: Some(fbb.create_vector(&opts.mean)),
+                std_: Some(fbb.create_vector(&opts.std)),
+            };
+            let opts = fb::NormalizationOptions::create(fbb, &args);
+            (
+                fb::ProcessUnitOptions::NormalizationOptions,
+                Some(opts.as_union_value()),
+            )
+        }
+        Some(ProcessUnitOptions::ScoreCalibrationOptions(opts)) => {
+            let args = fb::ScoreCalibrationOptionsArgs {
+                score_transformation: opts.score_transformation,
+                default_score: opts.default_score,
+            };
+            let opts = fb::ScoreCalibrationOptions::create(fbb, &args);
+            (
+                fb::ProcessUnitOptions::ScoreCalibrationOptions,
+                Some(opts.as_union_value()),
+            )
+        }
+        Some(ProcessUnitOptions::ScoreThresholdingOptions(opts)) => {
+            let args = fb::ScoreThresholdingOptionsArgs {
+                global_score_threshold: opts.global_score_threshold,
+            };
+            let opts = fb::ScoreThresholdingOptions::create(fbb, &args);
+            (
+                fb::ProcessUnitOptions::ScoreThresholdingOptions,
+                Some(opts.as_union_value()),
+            )
+        }
+        Some(ProcessUnitOptions::BertTokenizerOptions(opts)) => {
+            let args = fb::BertTokenizerOptionsArgs {
+                vocab_file: tables(fbb, &opts.vocab_file, associated_file),
+            };
+            let opts = fb::BertTokenizerOptions::create(fbb, &args);
+            (
+                fb::ProcessUnitOptions::BertTokenizerOptions,
+                Some(opts.as_union_value()),
+            )
+        }
+        Some(ProcessUnitOptions::SentencePieceTokenizerOptions(opts)) => {
+            let args = fb::SentencePieceTokenizerOptionsArgs {
+                sentencePiece_model: tables(fbb, &opts.sentence_piece_model, associated_file),
+                vocab_file: tables(fbb, &opts.vocab_file, associated_file),
+            };
+            let opts = fb::SentencePieceTokenizerOptions::create(fbb, &args);
+            (
+                fb::ProcessUnitOptions::SentencePieceTokenizerOptions,
+                Some(opts.as_union_value()),
+            )
+        }
+        Some(ProcessUnitOptions::RegexTokenizerOptions(opts)) => {
+            let args = fb::RegexTokenizerOptionsArgs {
+                delim_regex_pattern: string(fbb, &opts.delim_regex_pattern),
+                vocab_file: tables(fbb, &opts.vocab_file, associated_file),
+            };
+            let opts = fb::RegexTokenizerOptions::create(fbb, &args);
+            (
+                fb::ProcessUnitOptions::RegexTokenizerOptions,
+                Some(opts.as_union_value()),
+            )
+        }
+    };
+    let args = fb::ProcessUnitArgs {
+        options_type,
+        options,
+    };
+    fb::ProcessUnit::create(fbb, &args)
+}
+
+fn stats<'fbb>(fbb: &mut Builder<'fbb>, stats: &Stats) -> WIPOffset<fb::Stats<'fbb>> {
+    let args = fb::StatsArgs {
+        max: Some(fbb.create_vector(&stats.max)),
+        min: Some(fbb.create_vector(&stats.min)),
+    };
+    fb::Stats::create(fbb, &args)
+}
+
+fn tensor_group<'fbb>(
+    fbb: &mut Builder<'fbb>,
+    group: &TensorGroup,
+) -> WIPOffset<fb::TensorGroup<'fbb>> {
+    let names = group
+        .tensor_names
+        .iter()
+        .map(|name| fbb.create_string(name))
+        .collect::<Vec<_>>();
+    let args = fb::TensorGroupArgs {
+        name: string(fbb, &group.name),
+        tensor_names: Some(fbb.create_vector(&names)),
+    };
+    fb::TensorGroup::create(fbb, &args)
+}
+
+fn custom_metadata<'fbb>(
+    fbb: &mut Builder<'fbb>,
+    custom: &CustomMetadata,
+) -> WIPOffset<fb::CustomMetadata<'fbb>> {
+    let args = fb::CustomMetadataArgs {
+        name: string(fbb, &custom.name),
+        data: Some(aligned_bytes(fbb, &custom.data)),
+    };
+    fb::CustomMetadata::create(fbb, &args)
+}
+
+/// Creates a byte vector with `force_align: 16`, which the generated code doesn't implement.
+///
+/// The custom metadata `data` usually holds another flatbuffer, so it needs to be aligned for the
+/// flatbuffers verifier to accept it.
+pub(crate) fn aligned_bytes<'fbb>(
+    fbb: &mut Builder<'fbb>,
+    data: &[u8],
+) -> WIPOffset<Vector<'fbb, u8>> {
+    #[repr(align(16))]
+    struct Align16;
+
+    struct AlignedByte(u8);
+
+    impl Push for AlignedByte {
+        // Only used to derive the alignment.
+        type Output = Align16;
+
+        unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+            dst[0] = self.0;
+        }
+
+        fn size() -> usize {
+            1
+        }
+    }
+
+    let bytes = data.iter().map(|&b| AlignedByte(b)).collect::<Vec<_>>();
+    WIPOffset::new(fbb.create_vector(&bytes).value())
+}
+
+fn string<'fbb>(fbb: &mut Builder<'fbb>, s: &Option<String>) -> Option<WIPOffset<&'fbb str>> {
+    s.as_ref().map(|s| fbb.create_string(s))
+}
+
+/// Creates a vector of tables, or nothing if `items` is empty.
+fn tables<'fbb, T, U>(
+    fbb: &mut Builder<'fbb>,
+    items: &[T],
+    mut create: impl FnMut(&mut Builder<'fbb>, &T) -> WIPOffset<U>,
+) -> Option<Offsets<'fbb, U>> {
+    if items.is_empty() {
+        return None;
+    }
+    let offsets = items
+        .iter()
+        .map(|item| create(fbb, item))
+        .collect::<Vec<_>>();
+    Some(fbb.create_vector(&offsets))
+}
+
+/// Returns a copy of the model `tflite` whose metadata entry `name` references a buffer holding
+/// `data`.
+///
+/// The model flatbuffer is rebuilt table by table, so the data of a replaced buffer doesn't remain
+/// in the file. Data stored after the flatbuffer that is still referenced is appended to it, with
+/// each contiguous range aligned to 64 bytes, and the offsets referencing it are adjusted.
+fn set_metadata_buffer(tflite: &[u8], name: &str, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let model = flatbuffers::root::<v3c::Model>(tflite)?;
+    let buffers = model.buffers().map_or(Vec::new(), |b| b.iter().collect());
+    let existing = model
+        .metadata()
+        .into_iter()
+        .flatten()
+        .find(|meta| meta.name() == Some(name))
+        .map(|meta| meta.buffer() as usize);
+    if existing.is_some_and(|index| index >= buffers.len()) {
+        bail!("`{name}` references a buffer that doesn't exist");
+    }
+
+    let custom_options = model
+        .subgraphs()
+        .into_iter()
+        .flatten()
+        .flat_map(|sub| sub.operators().into_iter().flatten())
+        .map(|op| {
+            (
+                op.large_custom_options_offset(),
+                op.large_custom_options_size(),
+            )
+        });
+    let mut spans = buffers
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| Some(index) != existing)
+        .map(|(_, buffer)| (buffer.offset(), buffer.size_()))
+        .chain(custom_options)
+        .filter(|&(offset, _)| is_external(offset))
+        .collect::<Vec<_>>();
+    spans.sort_unstable();
+
+    let mut copier = Copier::new(tflite);
+    let flatbuffer = build_model(&copier, &model, name, data, existing)?;
+    if spans.is_empty() {
+        return Ok(flatbuffer);
+    }
+
+    // Merge overlapping spans into ranges `(start, end, new_start)`, and lay them out after the
+    // new flatbuffer.
+    let mut ranges: Vec<(u64, u64, u64)> = Vec::new();
+    for &(offset, size) in &spans {
+        let end = offset
+            .checked_add(size)
+            .filter(|&end| end <= tflite.len() as u64)
+            .with_context(|| format!("external data at offset {offset} exceeds the file"))?;
+        match ranges.last_mut() {
+            Some((_, range_end, _)) if offset <= *range_end => *range_end = (*range_end).max(end),
+            _ => ranges.push((offset, end, 0)),
+        }
+    }
+    let mut len = flatbuffer.len() as u64;
+    for (start, end, new_start) in &mut ranges {
+        *new_start = len.next_multiple_of(64);
+        len = *new_start + (*end - *start);
+    }
+    for &(offset, _) in &spans {
+        let (start, _, new_start) = ranges[ranges.partition_point(|r| r.1 < offset)..]
+            .first()
+            .copied()
+            .filter(|r| r.0 <= offset)
+            .expect("spans are within their ranges");
+        copier.relocate.insert(offset, new_start + (offset - start));
+    }
+
+    let mut out = build_model(&copier, &model, name, data, existing)?;
+    ensure!(
+        out.len() == flatbuffer.len(),
+        "moving the external data changed the size of the flatbuffer",
+    );
+    for (start, end, new_start) in ranges {
+        out.resize(new_start as usize, 0);
+        out.extend_from_slice(&tflite[start as usize..end as usize]);
+    }
+    Ok(out)
+}
+
+/// Builds the model flatbuffer for [`set_metadata_buffer`].
+fn build_model(
+    copier: &Copier<'_>,
+    model: &v3c::Model<'_>,
+    name: &str,
+    data: &[u8],
+    existing: Option<usize>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut fbb = FlatBufferBuilder::new();
+    let root = model._tab.loc();
+
+    let mut buffers = Vec::new();
+    for (index, loc) in copier
+        .table_locs(root, "Model", "buffers")?
+        .into_iter()
+        .enumerate()
+    {
+        if Some(index) != existing {
+            buffers.push(copier.table(&mut fbb, loc, "Buffer")?);
+            continue;
+        }
+        buffers.push(metadata_buffer(&mut fbb, data));
+    }
+    if buffers.is_empty() {
+        // Buffer 0 must be the empty sentinel buffer.
+        let buffer = v3c::Buffer::create(&mut fbb, &Default::default());
+        buffers.push(WIPOffset::new(buffer.value()));
+    }
+    let index = match existing {
+        Some(index) => index,
+        None => {
+            buffers.push(metadata_buffer(&mut fbb, data));
+            buffers.len() - 1
+        }
+    };
+    let buffers = fbb.create_vector(&buffers);
+
+    let mut metadata = copier
+        .table_locs(root, "Model", "metadata")?
+        .into_iter()
+        .map(|loc| copier.table(&mut fbb, loc, "Metadata"))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if existing.is_none() {
+        let name = fbb.create_string(name);
+        let args = v3c::MetadataArgs {
+            name: Some(name),
+            buffer: index as u32,
+        };
+        let meta = v3c::Metadata::create(&mut fbb, &args);
+        metadata.push(WIPOffset::new(meta.value()));
+    }
+    let metadata = fbb.create_vector(&metadata);
+
+    let replace = [
+        ("buffers", WIPOffset::new(buffers.value())),
+        ("metadata", WIPOffset::new(metadata.value())),
+    ];
+    let root = copier.table_with(&mut fbb, root, "Model", &replace)?;
+    fbb.finish(root, Some("TFL3"));
+    Ok(fbb.finished_data().to_vec())
+}
+
+fn metadata_buffer(fbb: &mut Builder<'_>, data: &[u8]) -> Offset {
+    let data = aligned_bytes(fbb, data);
+    let args = v3c::BufferArgs {
+        data: Some(data),
+        ..Default::default()
+    };
+    WIPOffset::new(v3c::Buffer::create(fbb, &args).value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer_data, custom_options};
+
+    const WEIGHTS: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+    const CUSTOM_OPTIONS: [u8; 3] = [9, 10, 11];
+
+    /// Builds a model with a `RESHAPE` and a custom operator, whose weights and custom options are
+    /// stored after the flatbuffer if `external` is set.
+    fn model(external: bool) -> Vec<u8> {
+        let build = |weights_offset: u64, options_offset: u64| {
+            let mut fbb = FlatBufferBuilder::new();
+            let data = (!external).then(|| fbb.create_vector(&WEIGHTS));
+            let buffers = [
+                v3c::Buffer::create(&mut fbb, &Default::default()),
+                v3c::Buffer::create(
+                    &mut fbb,
+                    &v3c::BufferArgs {
+                        data,
+                        offset: weights_offset,
+                        size_: if external { WEIGHTS.len() as u64 } else { 0 },
+                    },
+                ),
+            ];
+            let buffers = fbb.create_vector(&buffers);
+
+            let shape = fbb.create_vector(&[2, 4]);
+            let tensors = [0, 1, 0].map(|buffer| {
+                let args = v3c::TensorArgs {
+                    shape: Some(shape),
+                    buffer,
+                    ..Default::default()
+                };
+                v3c::Tensor::create(&mut fbb, &args)
+            });
+            let tensors = fbb.create_vector(&tensors);
+
+            let custom_code = fbb.create_string("Custom");
+            let codes = [
+                v3c::OperatorCodeArgs {
+                    builtin_code: v3c::BuiltinOperator::RESHAPE,
+                    deprecated_builtin_code: v3c::BuiltinOperator::RESHAPE.0 as i8,
+                    ..Default::default()
+                },
+                v3c::OperatorCodeArgs {
+                    builtin_code: v3c::BuiltinOperator::CUSTOM,
+                    deprecated_builtin_code: v3c::BuiltinOperator::CUSTOM.0 as i8,
+                    custom_code: Some(custom_code),
+                    ..Default::default()
+                },
+            ]
+            .map(|args| v3c::OperatorCode::create(&mut fbb, &args));
+            let codes = fbb.create_vector(&codes);
+
+            let new_shape = fbb.create_vector(&[4, 2]);
+            let reshape_options = v3c::ReshapeOptions::create(
+                &mut fbb,
+                &v3c::ReshapeOptionsArgs {
+                    new_shape: Some(new_shape),
+                },
+            );
+            let (inputs, outputs) = (fbb.create_vector(&[0, 1]), fbb.create_vector(&[2]));
+            let reshape = v3c::Operator::create(
+                &mut fbb,
+                &v3c::OperatorArgs {
+                    inputs: Some(inputs),
+                    outputs: Some(outputs),
+                    builtin_options_type: v3c::BuiltinOptions::ReshapeOptions,
+                    builtin_options: Some(reshape_options.as_union_value()),
+                    ..Default::default()
+                },
+            );
+            let custom_options = (!external).then(|| fbb.create_vector(&CUSTOM_OPTIONS));
+            let custom = v3c::Operator::create(
+                &mut fbb,
+                &v3c::OperatorArgs {
+                    opcode_index: 1,
+                    inputs: Some(outputs),
+                    outputs: Some(outputs),
+                    custom_options,
+                    large_custom_options_offset: options_offset,
+                    large_custom_options_size: if external {
+                        CUSTOM_OPTIONS.len() as u64
+                    } else {
+                        0
+                    },
+                    ..Default::default()
+                },
+            );
+            let operators = fbb.create_vector(&[reshape, custom]);
+            let subgraph = v3c::SubGraph::create(
+                &mut fbb,
+                &v3c::SubGraphArgs {
+                    tensors: Some(tensors),
+                    inputs: Some(inputs),
+                    outputs: Some(outputs),
+                    operators: Some(operators),
+                    ..Default::default()
+                },
+            );
+            let subgraphs = fbb.create_vector(&[subgraph]);
+
+            let root = v3c::Model::create(
+                &mut fbb,
+                &v3c::ModelArgs {
+                    version: 3,
+                    operator_codes: Some(codes),
+                    subgraphs: Some(subgraphs),
+                    buffers: Some(buffers),
+                    ..Default::default()
+                },
+            );
+            fbb.finish(root, Some("TFL3"));
+            fbb.finished_data().to_vec()
+        };
+
+        if !external {
+            return build(0, 0);
+        }
+        // The placeholder offsets are replaced by the real ones, which don't change the size.
+        let len = build(1, 1).len();
+        let weights_offset = len.next_multiple_of(16);
+        let options_offset = weights_offset + WEIGHTS.len();
+        let mut tflite = build(weights_offset as u64, options_offset as u64);
+        tflite.resize(weights_offset, 0);
+        tflite.extend_from_slice(&WEIGHTS);
+        tflite.extend_from_slice(&CUSTOM_OPTIONS);
+        tflite
+    }
+
+    fn meta(name: &str) -> ModelInfo {
+        ModelInfo {
+            name: Some(name.into()),
+            description: Some("a model with metadata".into()),
+            version: Some("1".into()),
+            min_parser_version: Some("1.0.0".into()),
+            ..Default::default()
+        }
+    }
+
+    /// Checks that the graph, the weights and the custom options of `tflite` are intact.
+    fn check_model(tflite: &[u8]) {
+        let model = flatbuffers::root::<v3c::Model>(tflite).unwrap();
+        assert_eq!(buffer_data(tflite, &model, 1).unwrap(), WEIGHTS);
+        let subgraph = model.subgraphs().unwrap().get(0);
+        assert_eq!(subgraph.tensors().unwrap().len(), 3);
+        let operators = subgraph.operators().unwrap();
+        let new_shape = operators
+            .get(0)
+            .builtin_options_as_reshape_options()
+            .and_then(|options| options.new_shape())
+            .unwrap();
+        assert_eq!(new_shape.iter().collect::<Vec<_>>(), [4, 2]);
+        assert_eq!(
+            custom_options(tflite, &operators.get(1)).unwrap(),
+            CUSTOM_OPTIONS
+        );
+        let code = model.operator_codes().unwrap().get(1);
+        assert_eq!(code.custom_code(), Some("Custom"));
+    }
+
+    fn round_trip(tflite: &[u8]) {
+        let first = write_metadata(tflite, &meta("first"), &[]).unwrap();
+        assert_eq!(ModelInfo::from_bytes(&first).unwrap(), meta("first"));
+        check_model(&first);
+
+        let second = write_metadata(&first, &meta("second"), &[]).unwrap();
+        assert_eq!(ModelInfo::from_bytes(&second).unwrap(), meta("second"));
+        check_model(&second);
+        assert_eq!(second.len(), first.len());
+
+        let third = write_metadata(&second, &meta("first"), &[]).unwrap();
+        assert_eq!(third, first);
+    }
+
+    #[test]
+    fn write_inline_model() {
+        round_trip(&model(false));
+    }
+
+    #[test]
+    fn write_model_with_external_data() {
+        let tflite = model(true);
+        check_model(&tflite);
+        round_trip(&tflite);
+
+        let out = write_metadata(&tflite, &meta("first"), &[]).unwrap();
+        let model = flatbuffers::root::<v3c::Model>(&out).unwrap();
+        let weights = model.buffers().unwrap().get(1);
+        assert_eq!(weights.offset() % 64, 0);
+        assert!(weights.offset() as usize >= out.len() - WEIGHTS.len() - CUSTOM_OPTIONS.len());
+    }
+
+    #[test]
+    fn write_with_associated_files() {
+        let mut meta = meta("first");
+        meta.associated_files.push(AssociatedFile {
+            name: Some("labels.txt".into()),
+            ..Default::default()
+        });
+        let files = [("labels.txt".to_string(), b"cat\ndog\n".to_vec())];
+        let first = write_metadata(&model(true), &meta, &files).unwrap();
+        let second = write_metadata(&first, &meta, &[]).unwrap();
+        assert_eq!(second, first);
+        check_model(&second);
+        let mut archive = Archive::from_bytes(&second).unwrap().unwrap();
+        assert_eq!(archive.read("labels.txt").unwrap(), b"cat\ndog\n");
+    }
+}
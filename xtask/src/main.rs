@@ -0,0 +1,249 @@
+//! Development tasks, run with `cargo xtask <task>`.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use anyhow::{bail, ensure, Context};
+
+const USAGE: &str = "\
+usage: cargo xtask regen [--check]
+
+Regenerates the flatbuffer bindings in `generated/` from the schemas in `schemas/` with `flatc`
+(or the binary in `$FLATC`). With `--check`, the bindings are only compared with the checked-in
+files, and the task fails if any of them is stale.";
+
+/// First line of the files written by `flatc --rust`.
+const FLATC_HEADER: &str =
+    "// automatically generated by the FlatBuffers compiler, do not modify\n";
+
+fn main() {
+    if let Err(e) = run(env::args_os().skip(1).collect()) {
+        eprintln!("error: {e:#}");
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<OsString>) -> anyhow::Result<()> {
+    let args = args
+        .iter()
+        .map(|arg| arg.to_str().context("invalid argument"))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    match args[..] {
+        ["regen"] => regen(&Flatc::locate()?),
+        ["regen", "--check"] => {
+            let stale = check(&Flatc::locate()?)?;
+            for file in &stale {
+                eprintln!("{file}");
+            }
+            ensure!(
+                stale.is_empty(),
+                "{} stale file(s) in `generated/`, run `cargo xtask regen`",
+                stale.len(),
+            );
+            Ok(())
+        }
+        _ => bail!("{USAGE}"),
+    }
+}
+
+/// A `flatc` binary.
+struct Flatc {
+    path: OsString,
+    /// The version reported by `flatc --version`, e.g. `24.3.25`.
+    version: String,
+}
+
+impl Flatc {
+    /// Finds `flatc` in `$FLATC` or the `PATH`, and checks that its major version matches the
+    /// `flatbuffers` dependency.
+    fn locate() -> anyhow::Result<Self> {
+        let path = env::var_os("FLATC").unwrap_or_else(|| "flatc".into());
+        let output = Command::new(&path)
+            .arg("--version")
+            .output()
+            .with_context(|| format!("failed to run `{}`", path.to_string_lossy()))?;
+        ensure!(output.status.success(), "`flatc --version` failed");
+        let stdout = String::from_utf8(output.stdout)?;
+        let version = stdout
+            .trim()
+            .strip_prefix("flatc version ")
+            .with_context(|| format!("unexpected `flatc --version` output `{}`", stdout.trim()))?
+            .to_string();
+
+        let required = flatbuffers_requirement()?;
+        let major = version.split('.').next().unwrap_or_default();
+        ensure!(
+            major == required.split('.').next().unwrap_or_default(),
+            "flatc {version} doesn't match the `flatbuffers = \"{required}\"` dependency",
+        );
+        Ok(Self { path, version })
+    }
+
+    /// Generates the Rust bindings of `schema`, with the `flatc` version added to the header.
+    fn generate(&self, schema: &Path) -> anyhow::Result<String> {
+        let out = env::temp_dir().join(format!("xtask-regen-{}", process::id()));
+        fs::create_dir_all(&out)?;
+        let status = Command::new(&self.path)
+            .arg("--rust")
+            .arg("-o")
+            .arg(&out)
+            .arg(schema)
+            .status()
+            .with_context(|| format!("failed to run `{}`", self.path.to_string_lossy()))?;
+        ensure!(status.success(), "flatc failed on `{}`", schema.display());
+
+        let file = out.join(generated_name(schema)?);
+        let code = fs::read_to_string(&file);
+        fs::remove_dir_all(&out)?;
+        let code = code.with_context(|| format!("flatc didn't write `{}`", file.display()))?;
+        let body = code
+            .strip_prefix(FLATC_HEADER)
+            .context("unexpected header in flatc output")?;
+        Ok(format!(
+            "{FLATC_HEADER}// flatc version {}\n{body}",
+            self.version
+        ))
+    }
+}
+
+/// Returns the version requirement of the `flatbuffers` dependency in the root `Cargo.toml`.
+fn flatbuffers_requirement() -> anyhow::Result<String> {
+    let manifest = fs::read_to_string(root().join("Cargo.toml"))?;
+    manifest
+        .lines()
+        .find_map(|line| line.strip_prefix("flatbuffers = \""))
+        .and_then(|rest| rest.split('"').next())
+        .map(Into::into)
+        .context("no `flatbuffers` dependency in Cargo.toml")
+}
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask is in a subdirectory of the workspace")
+        .into()
+}
+
+/// Schemas whose bindings are written by hand outside of `generated/`, and aren't regenerated.
+const HAND_WRITTEN: [&str; 1] = ["image_segmenter_metadata_schema.fbs"];
+
+/// Returns the schemas in `schemas/` with generated bindings, sorted by name.
+fn schemas() -> anyhow::Result<Vec<PathBuf>> {
+    let mut schemas = Vec::new();
+    for entry in fs::read_dir(root().join("schemas"))? {
+        let path = entry?.path();
+        let hand_written = path
+            .file_name()
+            .is_some_and(|name| HAND_WRITTEN.iter().any(|h| name == *h));
+        if path.extension() == Some("fbs".as_ref()) && !hand_written {
+            schemas.push(path);
+        }
+    }
+    schemas.sort();
+    Ok(schemas)
+}
+
+/// Returns the name of the file `flatc --rust` generates for `schema`.
+fn generated_name(schema: &Path) -> anyhow::Result<String> {
+    let stem = schema
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .with_context(|| format!("invalid schema name `{}`", schema.display()))?;
+    Ok(format!("{stem}_generated.rs"))
+}
+
+fn regen(flatc: &Flatc) -> anyhow::Result<()> {
+    for schema in schemas()? {
+        let file = root().join("generated").join(generated_name(&schema)?);
+        fs::write(&file, flatc.generate(&schema)?)?;
+        println!("wrote {}", file.display());
+    }
+    Ok(())
+}
+
+/// Returns the files in `generated/` that differ from the output of `flatc`, and those that have
+/// no schema.
+fn check(flatc: &Flatc) -> anyhow::Result<Vec<String>> {
+    let mut stale = Vec::new();
+    let mut expected = Vec::new();
+    for schema in schemas()? {
+        let name = generated_name(&schema)?;
+        let file = root().join("generated").join(&name);
+        match fs::read_to_string(&file) {
+            Ok(code) if code == flatc.generate(&schema)? => {}
+            Ok(code) => {
+                let recorded = code
+                    .lines()
+                    .nth(1)
+                    .and_then(|line| line.strip_prefix("// flatc version "))
+                    .unwrap_or("an unknown version");
+                stale.push(format!(
+                    "generated/{name}: differs from the output of flatc {} (checked in: {recorded})",
+                    flatc.version,
+                ));
+            }
+            Err(_) => stale.push(format!("generated/{name}: missing")),
+        }
+        expected.push(name);
+    }
+    for entry in fs::read_dir(root().join("generated"))? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if !expected.contains(&name) {
+            stale.push(format!("generated/{name}: no schema in `schemas/`"));
+        }
+    }
+    Ok(stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fails if the checked-in bindings are stale. Ignored by default, since it needs `flatc`; the
+    /// `bindings` CI job runs it with `cargo test -p xtask -- --ignored`, where a missing `flatc`
+    /// is an error.
+    #[test]
+    #[ignore = "needs flatc"]
+    fn generated_bindings_are_fresh() {
+        let flatc = Flatc::locate().unwrap();
+        let stale = check(&flatc).unwrap();
+        assert!(
+            stale.is_empty(),
+            "stale bindings, run `cargo xtask regen`:\n{}",
+            stale.join("\n"),
+        );
+    }
+
+    /// The version recorded in the checked-in headers, if any, must match the `flatbuffers`
+    /// dependency, even where `flatc` isn't installed.
+    #[test]
+    fn recorded_flatc_version_matches_dependency() {
+        let required = flatbuffers_requirement().unwrap();
+        for schema in schemas().unwrap() {
+            let name = generated_name(&schema).unwrap();
+            let code = fs::read_to_string(root().join("generated").join(&name)).unwrap();
+            assert!(
+                code.starts_with(FLATC_HEADER),
+                "generated/{name} wasn't generated by flatc"
+            );
+            let Some(version) = code
+                .lines()
+                .nth(1)
+                .and_then(|line| line.strip_prefix("// flatc version "))
+            else {
+                // Generated before `cargo xtask regen` recorded the version.
+                continue;
+            };
+            assert_eq!(
+                version.split('.').next(),
+                required.split('.').next(),
+                "generated/{name} was generated by flatc {version}",
+            );
+        }
+    }
+}